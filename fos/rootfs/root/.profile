@@ -0,0 +1 @@
+/etc/profile
\ No newline at end of file
@@ -0,0 +1,39 @@
+// Checks a fos image made by `mkfs` and prints what is wrong with it.
+// usage: fsck IMAGE
+
+use std::{fs, process::ExitCode, rc::Rc};
+
+use fos::{
+    block::RamDisk,
+    image::{check, BLOCK_SIZE},
+};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let [image] = &args[..] else {
+        eprintln!("usage: fsck IMAGE");
+        return ExitCode::FAILURE;
+    };
+
+    let bytes = match fs::read(image) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("fsck: {image}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let problems = check(Rc::new(RamDisk::from_bytes(BLOCK_SIZE, bytes)));
+
+    if problems.is_empty() {
+        println!("{image}: clean");
+        return ExitCode::SUCCESS;
+    }
+
+    for problem in &problems {
+        println!("{image}: {problem}");
+    }
+
+    ExitCode::FAILURE
+}
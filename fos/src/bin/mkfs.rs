@@ -0,0 +1,120 @@
+// Packs a directory of the host into a fos image.
+// usage: mkfs DIRECTORY IMAGE [BLOCKS]
+
+use std::{
+    fs::{self, Metadata},
+    os::unix::fs::MetadataExt,
+    path::Path,
+    process::ExitCode,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use fos::{
+    block::RamDisk,
+    image::{ImageFs, BLOCK_SIZE},
+    set_clock, FileSystem, OpenError,
+};
+
+// 4 MiB
+const DEFAULT_BLOCKS: usize = 4096;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let (directory, image, blocks) = match &args[..] {
+        [directory, image] => (directory, image, DEFAULT_BLOCKS),
+        [directory, image, blocks] => match blocks.parse() {
+            Ok(blocks) => (directory, image, blocks),
+            Err(_) => return usage(),
+        },
+        _ => return usage(),
+    };
+
+    set_clock(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis() as u64)
+    });
+
+    let disk = Rc::new(RamDisk::new(BLOCK_SIZE, blocks));
+
+    // one inode for every 4 blocks is plenty for small files
+    let result = ImageFs::format(Rc::clone(&disk) as _, (blocks / 4) as u32)
+        .map_err(|err| format!("cannot format: {err:?}"))
+        .and_then(|filesystem| pack(&filesystem, Path::new(directory), "/"));
+
+    if let Err(err) = result {
+        eprintln!("mkfs: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    if let Err(err) = fs::write(image, disk.bytes()) {
+        eprintln!("mkfs: {image}: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn usage() -> ExitCode {
+    eprintln!("usage: mkfs DIRECTORY IMAGE [BLOCKS]");
+    ExitCode::FAILURE
+}
+
+// Copies the entries of a host directory into the image at `path`
+fn pack(image: &ImageFs, directory: &Path, path: &str) -> Result<(), String> {
+    let mut entries: Vec<_> = fs::read_dir(directory)
+        .and_then(|entries| entries.collect::<Result<_, _>>())
+        .map_err(|err| format!("{}: {err}", directory.display()))?;
+
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let host = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let inner = format!("{}/{name}", path.trim_end_matches('/'));
+
+        let error = |err: OpenError| format!("{inner}: {err:?}");
+        let host_error = |err: std::io::Error| format!("{}: {err}", host.display());
+
+        let metadata = fs::symlink_metadata(&host).map_err(host_error)?;
+        let kind = metadata.file_type();
+
+        if kind.is_symlink() {
+            let target = fs::read_link(&host).map_err(host_error)?;
+            image
+                .symlink(&target.to_string_lossy(), &inner)
+                .map_err(error)?;
+        } else if kind.is_dir() {
+            image.mkdir(&inner).map_err(error)?;
+            pack(image, &host, &inner)?;
+        } else if kind.is_file() {
+            image.create(&inner).map_err(error)?;
+            image
+                .write_bytes(&inner, &fs::read(&host).map_err(host_error)?)
+                .map_err(error)?;
+        } else {
+            // reading a fifo would block and devices have no place in an image
+            eprintln!("mkfs: {}: skipped, not a file", host.display());
+            continue;
+        }
+
+        copy_metadata(image, &inner, &metadata).map_err(error)?;
+    }
+
+    Ok(())
+}
+
+// Keeps the mode, owner and modification time of the host
+fn copy_metadata(image: &ImageFs, path: &str, metadata: &Metadata) -> Result<(), OpenError> {
+    // the permissions of a link are never checked
+    if !metadata.file_type().is_symlink() {
+        image.chmod(path, (metadata.mode() & 0o7777) as u16)?;
+    }
+
+    image.chown(path, metadata.uid(), metadata.gid())?;
+
+    let modified = metadata.mtime() * 1000 + metadata.mtime_nsec() / 1_000_000;
+    image.set_modified(path, modified.max(0) as u64)
+}
@@ -28,6 +28,11 @@ impl Executor {
             .push_front(Box::new(RefCell::new(task)));
     }
 
+    // Tasks that didn't finish, including the ones not polled yet
+    pub fn task_count(&self) -> usize {
+        self.tasks.len() + self.queue.borrow().len()
+    }
+
     pub fn execute(&self) -> bool {
         let mut queue = self.queue.borrow_mut();
 
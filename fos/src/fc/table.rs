@@ -88,6 +88,20 @@ impl<T> Table<T> {
         }
     }
 
+    // Copies of the items with their index
+    pub fn entries(&self) -> Vec<(usize, T)>
+    where
+        T: Clone,
+    {
+        let items = self.items.borrow();
+
+        items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| item.clone().map(|item| (index, item)))
+            .collect()
+    }
+
     pub fn filter(&self, callback: &dyn Fn(&T) -> bool) -> usize {
         let mut items = self.items.borrow_mut();
 
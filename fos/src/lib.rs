@@ -42,6 +42,11 @@ unsafe impl Send for RootWrapper {}
 static ROOT: Lazy<RootWrapper> = Lazy::new(|| RootWrapper::new());
 
 pub fn main<Platform: PlatformTrait + 'static>() {
+    // before root makes its filesystem
+    if let Some(archive) = Platform::root_archive() {
+        set_root_archive(archive);
+    }
+
     let root = Rc::clone(&ROOT.inner);
     root.main(vec![]);
 
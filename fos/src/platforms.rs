@@ -16,6 +16,15 @@ pub use tekenen::Tekenen;
 #[derive(Debug)]
 pub enum Keycode {
     Temp,
+    Backspace,
+    Delete,
+    Tab,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
 }
 
 #[derive(Debug)]
@@ -42,7 +51,16 @@ pub trait PlatformTrait {
         Self: Sized;
     fn display_pixels(&mut self, pixels: &tekenen::Pixels);
     fn read_events(&mut self) -> Option<Event>;
+    // milliseconds since the unix epoch
+    fn now(&self) -> u64;
     fn set_interval(callback: Box<dyn FnMut() -> bool>, fps: u32)
     where
         Self: Sized;
+    // a tar archive with the initial files, fos has its own otherwise
+    fn root_archive() -> Option<Vec<u8>>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
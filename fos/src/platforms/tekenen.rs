@@ -30,6 +30,10 @@ impl Tekenen {
     pub fn get_pixels(&self) -> &Pixels {
         &self.pixels
     }
+
+    pub fn get_pixels_mut(&mut self) -> &mut Pixels {
+        &mut self.pixels
+    }
 }
 
 // Drawing implementation
@@ -122,13 +126,41 @@ impl Tekenen {
         (curr_x, curr_y)
     }
 
-    pub fn draw_terminal(&mut self, buffer: &str, time: u64) {
-        let (x, y) = self.draw_text(buffer, 0, 0);
+    // Returns where draw_text would stop drawing, without drawing anything
+    pub fn measure_text(&self, text: &str) -> (i32, i32) {
+        const FONT_SIZE: i32 = 16;
+
+        let mut curr_x = 0;
+        let mut curr_y = 0;
+
+        for char in text.chars() {
+            if curr_x >= 800 || char == '\n' {
+                curr_x = 0;
+                curr_y += FONT_SIZE;
+
+                if char == '\n' {
+                    continue;
+                }
+            }
+
+            curr_x += FONT_SIZE;
+        }
+
+        (curr_x, curr_y)
+    }
+
+    // `cursor` is the char index in `buffer` where the cursor is drawn
+    pub fn draw_terminal(&mut self, buffer: &str, cursor: usize, time: u64) {
+        self.background(BLACK);
+        self.draw_text(buffer, 0, 0);
+
+        let before: String = buffer.chars().take(cursor).collect();
+        let (x, y) = self.measure_text(&before);
 
         const BLINKING_TIME: u64 = 500;
 
         if time % BLINKING_TIME > BLINKING_TIME / 2 {
-            self.rect(x, y, 16, 16, WHITE)
+            self.rect(x, y + 14, 16, 2, WHITE)
         }
     }
 }
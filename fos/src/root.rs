@@ -1,5 +1,5 @@
 use std::{
-    cell::{RefCell, RefMut},
+    cell::{Cell, RefCell, RefMut},
     rc::Rc,
 };
 
@@ -12,23 +12,36 @@ pub use fs::*;
 mod spawner;
 pub use spawner::*;
 
+mod user;
+pub use user::*;
+
+mod hash;
+
 mod terminal;
-use terminal::Terminal;
+use terminal::{key_sequence, Terminal};
 
 use crate::{
     fc::future::Executor,
     platforms::{tekenen::Tekenen, Event, PlatformTrait},
     shell::Shell,
-    descriptor::{ReadableDescriptor, WritableDescriptor, ReadableWritablePipe}
+    descriptor::{ReadableDescriptor, WritableDescriptor, ReadableWritablePipe},
+    ROOT,
 };
 
+// Logged in without a password at startup
+const DEFAULT_USER: &str = "fritz";
+
 pub struct Root {
     pub platform: RefCell<Option<Box<dyn PlatformTrait>>>,
     terminal: Terminal,
     proc: Proc,
     pub fs: Fs,
+    // drawn over the terminal, programs write to it through `/dev/fb0`
+    pub framebuffer: Rc<dev::Framebuffer>,
     pub executor: Executor,
     pub spawner: Spawner,
+    // number of updates since startup
+    ticks: Cell<u64>,
 }
 
 impl Process for Root {
@@ -42,8 +55,10 @@ impl Process for Root {
             terminal,
             proc,
             fs,
+            framebuffer: Rc::new(dev::Framebuffer::new()),
             executor,
             spawner: Spawner::new(),
+            ticks: Cell::new(0),
         }
     }
 
@@ -56,38 +71,60 @@ impl Process for Root {
     }
 
     fn main(self: Rc<Self>, _: Vec<&str>) {
-        let shell = self.proc.spawn::<Shell>();
-
-        // pipe stdin to shell stdin
+        // pipe stdout to terminal
         let self_clone = Rc::clone(&self);
 
-        let shell_clone = Rc::clone(&shell);
         self.executor.add_task(async move {
             loop {
-                let data = self_clone.proc.stdin.read(50).await;
-                shell_clone.proc.handler.write(&data.expect("Option sending to shell"));
+                let string = self_clone.proc.handler.read(50).await;
+
+                self_clone
+                    .terminal
+                    .write(&string.expect("Option sending to terminal"));
             }
         });
 
-        // pipe shell stdout to terminal
+        // Inodes are timed by the platform clock from now on
+        set_clock(|| ROOT.now());
+
+        // Every process inherits the host name from us
+        let mut env = self.proc.env.borrow_mut();
+        env.insert("HOSTNAME".to_string(), "tekenen".to_string());
+        drop(env);
+
         let self_clone = Rc::clone(&self);
 
-        let shell_clone = Rc::clone(&shell);
+        // A new shell is started whenever the last one exits
         self.executor.add_task(async move {
             loop {
-                let string = shell_clone.proc.handler.read(50).await;
+                // The shell inherits our stdin, stdout and stderr
+                let shell: Rc<dyn Process> = self_clone.proc.spawn::<Shell>();
 
-                self_clone
-                    .terminal
-                    .write(&string.expect("Option sending to terminal"));
+                // and runs as root if the user is missing from `/etc/passwd`
+                let _ = shell.get_proc().switch_user(DEFAULT_USER, "");
+
+                Rc::clone(&shell).main(vec![]);
+
+                self_clone.proc.wait(&shell).await;
             }
         });
-
-        shell.main(vec![]);
     }
 }
 
 impl Root {
+    // Time of the platform clock, 0 until the platform is set
+    pub fn now(&self) -> u64 {
+        self.platform
+            .borrow()
+            .as_ref()
+            .map_or(0, |platform| platform.now())
+    }
+
+    // Milliseconds since startup, counted in frames
+    pub fn uptime(&self) -> u64 {
+        self.ticks.get() * 1000 / 60
+    }
+
     pub fn update(&self, tekenen: &mut Tekenen) -> bool {
         self.executor.execute();
 
@@ -108,9 +145,17 @@ impl Root {
                     // true indicates to interrupt the loop
                     return true;
                 }
-                Event::KeyDown { char, keycode, .. } => {
+                Event::KeyDown { char, keycode, keymod, .. } => {
                     if let Some(c) = char {
-                        self.proc.handler.write_char(c);
+                        if keymod.ctrl && c.is_ascii_alphabetic() {
+                            // Ctrl-A is 0x01, Ctrl-B is 0x02...
+                            let control = (c.to_ascii_uppercase() as u8 - b'@') as char;
+                            self.proc.handler.write_char(control);
+                        } else {
+                            self.proc.handler.write_char(c);
+                        }
+                    } else if let Some(sequence) = key_sequence(&keycode) {
+                        self.proc.handler.write(sequence);
                     } else {
                         println!("unknown char {:?}", keycode)
                     }
@@ -118,7 +163,12 @@ impl Root {
             }
         }
 
-        self.terminal.render(tekenen, 0);
+        // set_interval runs at 60 fps
+        let ticks = self.ticks.get() + 1;
+        self.ticks.set(ticks);
+
+        self.terminal.render(tekenen, self.uptime());
+        self.framebuffer.compose(tekenen);
 
         platform.display_pixels(tekenen.get_pixels());
 
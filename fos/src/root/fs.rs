@@ -1,104 +1,1140 @@
 pub mod descriptor;
 pub mod pipe;
+pub mod file;
+pub mod memory;
+pub mod block;
+pub mod dev;
+pub mod image;
+pub mod fat;
+pub mod procfs;
+pub mod socket;
+pub mod tar;
+pub mod watch;
+#[cfg(feature = "host")]
+pub mod host;
 
-use descriptor::{ReadableWritableDescriptor, ReadableDescriptor, WritableDescriptor};
-use pipe::{new_pipe, PipeReader, PipeWriter};
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
 
-use crate::{Proc, ROOT};
+use descriptor::ReadableWritableDescriptor;
+use block::{BlockDevice, DeviceFile, RamDisk};
+use dev::DevFs;
+use file::{FileDescriptor, FileHandle, OpenFlags};
+use fat::{FatFs, FatOptions};
+use image::{ImageFs, BLOCK_SIZE};
+use memory::MemoryFs;
+use procfs::ProcFs;
+use socket::{Socket, SocketKind};
+use watch::{Event, Watch, Watchers};
+#[cfg(feature = "host")]
+use host::HostFs;
+use pipe::{new_pipe, Fifo, PipeReader, PipeWriter};
+
+use crate::{Credentials, Gid, Proc, Uid, MAY_EXECUTE, MAY_READ, MAY_WRITE, ROOT};
 
 pub enum FileDirectoryPipe {
     File(String),
     Directory(Vec<String>),
 }
 
-pub struct Name(pub String);
+thread_local! {
+    // Times of new and changed inodes, the platform clock is set once root runs
+    static CLOCK: Cell<fn() -> u64> = Cell::new(|| 0);
 
-pub struct Directory(pub Vec<(Name, Inode)>);
+    // Given by the platform before root starts, instead of `ROOT_ARCHIVE`
+    static ARCHIVE: RefCell<Option<Vec<u8>>> = const { RefCell::new(None) };
+}
 
-pub enum Inode {
-    File(String),
-    Directory(Directory),
+pub fn set_clock(clock: fn() -> u64) {
+    CLOCK.with(|cell| cell.set(clock));
+}
+
+// Milliseconds since the unix epoch
+pub fn now() -> u64 {
+    CLOCK.with(|cell| cell.get())()
+}
+
+// The tar archive the root filesystem is made from, it has to be set before root starts
+pub fn set_root_archive(archive: Vec<u8>) {
+    ARCHIVE.with(|cell| *cell.borrow_mut() = Some(archive));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    File,
+    Directory,
+    Symlink,
+    // storage read in blocks, like a disk
+    BlockDevice,
+    // a stream of bytes, like a terminal
+    CharDevice,
+    // a named pipe, connecting the processes that open it
+    Fifo,
+    // the address of a bound socket
+    Socket,
+}
+
+// A snapshot of the metadata of an inode
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stat {
+    pub ino: u64,
+    pub kind: Kind,
+    // bytes of a file or of the target of a link, entries of a directory
+    pub size: usize,
+    pub mode: u16,
+    pub uid: Uid,
+    pub gid: Gid,
+    pub links: u32,
+    pub created: u64,
+    pub modified: u64,
+    pub accessed: u64,
+    // the number of the device a special file stands for
+    pub device: u32,
+}
+
+// Paths given to a filesystem are relative to where it's mounted and start with `/`.
+// Links are resolved by the VFS, a filesystem never follows them.
+pub trait FileSystem {
+    // shown by `mount`
+    fn name(&self) -> &str;
+    fn stat(&self, path: &str) -> Result<Stat, OpenError>;
+    fn open(&self, path: &str) -> Result<Rc<dyn FileHandle>, OpenError>;
+    fn readdir(&self, path: &str) -> Result<Vec<(String, Stat)>, OpenError>;
+
+    fn readlink(&self, _path: &str) -> Result<String, OpenError> {
+        Err(OpenError::InvalidArgument)
+    }
+
+    // a read only filesystem doesn't implement the rest
+    fn create(&self, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::ReadOnly)
+    }
+
+    fn mkdir(&self, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::ReadOnly)
+    }
+
+    fn symlink(&self, _target: &str, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::ReadOnly)
+    }
+
+    fn link(&self, _existing: &str, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::ReadOnly)
+    }
+
+    fn unlink(&self, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::ReadOnly)
+    }
+
+    fn rmdir(&self, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::ReadOnly)
+    }
+
+    fn rename(&self, _from: &str, _to: &str) -> Result<(), OpenError> {
+        Err(OpenError::ReadOnly)
+    }
+
+    // Changes the permission bits, only some filesystems store them
+    fn chmod(&self, _path: &str, _mode: u16) -> Result<(), OpenError> {
+        Err(OpenError::NotSupported)
+    }
+
+    // Changes the owner and the group, only some filesystems store them
+    fn chown(&self, _path: &str, _uid: Uid, _gid: Gid) -> Result<(), OpenError> {
+        Err(OpenError::NotSupported)
+    }
+
+    // A special file for device `number`, only some filesystems can store them
+    fn mknod(&self, _path: &str, _kind: Kind, _number: u32) -> Result<(), OpenError> {
+        Err(OpenError::NotSupported)
+    }
+
+    // Writes what is buffered to the storage, before unmounting
+    fn sync(&self) -> Result<(), OpenError> {
+        Ok(())
+    }
+}
+
+type Mounter =
+    fn(proc: &Proc, source: &str, options: &[&str]) -> Result<Rc<dyn FileSystem>, OpenError>;
+
+// Filesystems that can be mounted by name, with the options other than `ro` and `rw`
+fn mounter(kind: &str) -> Option<Mounter> {
+    let mounter: Mounter = match kind {
+        "tmpfs" => |_, _, options| {
+            no_options(options)?;
+            Ok(Rc::new(MemoryFs::new()))
+        },
+        // an image on a block device like `/dev/ram0`
+        "imgfs" => |proc, source, options| {
+            no_options(options)?;
+            Ok(Rc::new(ImageFs::load(proc.block_device(source)?)?))
+        },
+        // FAT16 or FAT32 like on memory cards, owned by `uid=` and `gid=` with `umask=`
+        "vfat" => |proc, source, options| {
+            let options = FatOptions::parse(options)?;
+            Ok(Rc::new(FatFs::load_with(proc.block_device(source)?, options)?))
+        },
+        // a directory of the host, the source is its path
+        #[cfg(feature = "host")]
+        "hostfs" => |_, source, options| {
+            no_options(options)?;
+            Ok(Rc::new(HostFs::new(source)?))
+        },
+        _ => return None,
+    };
+
+    Some(mounter)
+}
+
+fn no_options(options: &[&str]) -> Result<(), OpenError> {
+    match options {
+        [] => Ok(()),
+        _ => Err(OpenError::InvalidArgument),
+    }
+}
+
+// Content of the filesystem at startup, made from the files in `fos/rootfs` with
+// tar --format=ustar --sort=name --owner=0 --group=0 --numeric-owner --mtime=@0 --exclude=./home/fritz --exclude=./root --exclude=./etc/shadow -cf fos/root.tar -C fos/rootfs .
+// and the home directories and the passwords appended with their owners and modes
+// tar --format=ustar --sort=name --owner=1000 --group=1000 --numeric-owner --mtime=@0 -rf fos/root.tar -C fos/rootfs ./home/fritz
+// tar --format=ustar --sort=name --owner=0 --group=0 --numeric-owner --mtime=@0 --mode=go-rwx -rf fos/root.tar -C fos/rootfs ./root ./etc/shadow
+const ROOT_ARCHIVE: &[u8] = include_bytes!("../../root.tar");
+
+// An empty RAM disk at `/dev/ram0`, in blocks of `BLOCK_SIZE`
+const RAM_DISK_BLOCKS: usize = 512;
+
+// Links followed while looking up a path before giving up on a loop
+const MAX_SYMLINKS: usize = 40;
+
+// Block devices with the same prefix, `/dev/ram0` to `/dev/ram7`
+const MAX_DEVICES: usize = 8;
+
+#[derive(Clone)]
+pub struct Mount {
+    pub path: String,
+    // what was mounted, like a disk image
+    pub source: String,
+    pub fs: Rc<dyn FileSystem>,
+    // refuses every change before it reaches the filesystem
+    pub read_only: bool,
 }
 
+// The address of a filesystem and an inode in it
+type InodeKey = (usize, u64);
+
+// The virtual filesystem, every path goes to the filesystem mounted closest to it.
+// All paths are absolute, processes resolve them first.
 pub struct Fs {
-    mount: Directory,
+    mounts: RefCell<Vec<Mount>>,
+    // found by the number of their special file in `/dev`
+    block_devices: RefCell<Vec<Rc<dyn BlockDevice>>>,
+    // the pipes of the FIFOs that are open
+    fifos: RefCell<Vec<(InodeKey, Rc<Fifo>)>>,
+    // gone when their process drops them, the special file stays
+    sockets: RefCell<Vec<(InodeKey, Weak<Socket>)>>,
+    // told about every change
+    watchers: Rc<Watchers>,
 }
 
 impl Fs {
     pub fn new() -> Self {
+        let root = MemoryFs::new();
+
+        let archive = ARCHIVE.with(|cell| cell.borrow_mut().take());
+        let members = tar::read(archive.as_deref().unwrap_or(ROOT_ARCHIVE))
+            .expect("Cannot read the initial files");
+
+        tar::extract(&root, &members).expect("Cannot create the initial files");
+
+        // devices and processes belong to this machine, not to the archive
+        for path in ["/dev", "/proc"] {
+            match root.mkdir(path) {
+                Ok(()) | Err(OpenError::AlreadyExists) => {}
+                Err(err) => panic!("Cannot create {path}: {err:?}"),
+            }
+        }
+
+        let devices = DevFs::new();
+        devices
+            .mknod("/ram0", Kind::BlockDevice, 0)
+            .expect("Cannot create the initial devices");
+
+        let ram_disk: Rc<dyn BlockDevice> = Rc::new(RamDisk::new(BLOCK_SIZE, RAM_DISK_BLOCKS));
+
+        let root = Mount {
+            path: "/".to_string(),
+            source: "none".to_string(),
+            fs: Rc::new(root),
+            read_only: false,
+        };
+
+        let dev = Mount {
+            path: "/dev".to_string(),
+            source: "devfs".to_string(),
+            fs: Rc::new(devices),
+            read_only: false,
+        };
+
+        let proc = Mount {
+            path: "/proc".to_string(),
+            source: "proc".to_string(),
+            fs: Rc::new(ProcFs),
+            read_only: true,
+        };
+
         Fs {
-            mount: Directory(vec![
-                (
-                    Name("mount-file".to_string()),
-                    Inode::File("content_of_mount_file".to_string()),
-                ),
-                (
-                    Name("mount_folder".to_string()),
-                    Inode::Directory(Directory(vec![
-                        (
-                            Name("sub_file_1".to_string()),
-                            Inode::File("content_of_sub_file_1".to_string()),
-                        ),
-                        (
-                            Name("sub_file_2".to_string()),
-                            Inode::File("content_of_sub_file_2".to_string()),
-                        ),
-                    ])),
-                ),
-            ]),
-        }
-    }
-}
-
-#[derive(Debug)]
+            mounts: RefCell::new(vec![root, dev, proc]),
+            block_devices: RefCell::new(vec![ram_disk]),
+            fifos: RefCell::new(vec![]),
+            sockets: RefCell::new(vec![]),
+            watchers: Rc::new(Watchers::default()),
+        }
+    }
+
+    fn mount_of(&self, path: &str) -> Mount {
+        let mounts = self.mounts.borrow();
+
+        mounts
+            .iter()
+            .filter(|mount| is_inside(path, &mount.path))
+            .max_by_key(|mount| mount.path.len())
+            .expect("Nothing mounted at /")
+            .clone()
+    }
+
+    // The filesystem mounted closest to `path` and the path inside of it
+    fn locate(&self, path: &str) -> (Rc<dyn FileSystem>, String) {
+        let mount = self.mount_of(path);
+        let inner = path[mount.path.len()..].trim_start_matches('/');
+
+        (mount.fs, format!("/{inner}"))
+    }
+
+    // Like `locate` for a path that is going to be changed
+    fn writable(&self, path: &str) -> Result<(Rc<dyn FileSystem>, String), OpenError> {
+        if self.mount_of(path).read_only {
+            return Err(OpenError::ReadOnly);
+        }
+
+        Ok(self.locate(path))
+    }
+
+    fn is_mount_point(&self, path: &str) -> bool {
+        self.mounts.borrow().iter().any(|mount| mount.path == path)
+    }
+
+    // The path without links, `follow` also resolves a link at the end
+    fn walk(&self, path: &str, follow: bool) -> Result<String, OpenError> {
+        self.walk_as(path, follow, None)
+    }
+
+    // Like `walk`, with `credentials` every directory looked into has to be searchable,
+    // including the ones a link passes through
+    fn walk_as(
+        &self,
+        path: &str,
+        follow: bool,
+        credentials: Option<&Credentials>,
+    ) -> Result<String, OpenError> {
+        let mut resolved: Vec<String> = vec![];
+        let mut links = 0;
+
+        // names are popped from the end
+        let mut names = components(path);
+        names.reverse();
+
+        while let Some(name) = names.pop() {
+            match name.as_str() {
+                "." => continue,
+                ".." => {
+                    resolved.pop();
+                    continue;
+                }
+                _ => {
+                    if let Some(credentials) = credentials {
+                        let (fs, inner) = self.locate(&join(&resolved));
+
+                        if !credentials.permits(&fs.stat(&inner)?, MAY_EXECUTE) {
+                            return Err(OpenError::PermissionDenied);
+                        }
+                    }
+
+                    resolved.push(name)
+                }
+            }
+
+            let (fs, inner) = self.locate(&join(&resolved));
+
+            match fs.stat(&inner)?.kind {
+                Kind::Symlink if follow || !names.is_empty() => {
+                    links += 1;
+
+                    if links > MAX_SYMLINKS {
+                        return Err(OpenError::TooManyLinks);
+                    }
+
+                    let target = fs.readlink(&inner)?;
+
+                    // relative targets start in the directory of the link
+                    resolved.pop();
+                    if target.starts_with('/') {
+                        resolved.clear();
+                    }
+
+                    names.extend(components(&target).into_iter().rev());
+                }
+                Kind::Directory => {}
+                _ if !names.is_empty() => return Err(OpenError::NotADirectory),
+                _ => {}
+            }
+        }
+
+        Ok(join(&resolved))
+    }
+
+    // The path of an entry which may not exist, links are followed only in the parents
+    fn entry(&self, path: &str) -> Result<String, OpenError> {
+        let (dirname, name) = split_parent(path.trim_end_matches('/'));
+
+        // `/` has no parent, `.` and `..` aren't entries of their own
+        if matches!(name, "" | "." | "..") {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let dirname = self.walk(dirname, true)?;
+        let (fs, inner) = self.locate(&dirname);
+
+        if fs.stat(&inner)?.kind != Kind::Directory {
+            return Err(OpenError::NotADirectory);
+        }
+
+        Ok(format!("{}/{name}", dirname.trim_end_matches('/')))
+    }
+
+    // The entry to create at `path`, with its path in the VFS to tell the watches
+    fn new_entry(&self, path: &str) -> Result<(Rc<dyn FileSystem>, String, String), OpenError> {
+        let path = self.entry(path).map_err(|err| match err {
+            OpenError::InvalidArgument => OpenError::AlreadyExists,
+            err => err,
+        })?;
+
+        let (fs, inner) = self.writable(&path)?;
+
+        Ok((fs, inner, path))
+    }
+
+    // Waits for changes to the entry at `path` and the ones directly inside of it
+    pub fn watch(&self, path: &str) -> Result<Rc<Watch>, OpenError> {
+        Ok(self.watchers.add(&self.walk(path, true)?))
+    }
+
+    pub fn stat(&self, path: &str) -> Result<Stat, OpenError> {
+        let (fs, inner) = self.locate(&self.walk(path, true)?);
+
+        fs.stat(&inner)
+    }
+
+    // Doesn't follow a link at the end of the path
+    pub fn lstat(&self, path: &str) -> Result<Stat, OpenError> {
+        let (fs, inner) = self.locate(&self.walk(path, false)?);
+
+        fs.stat(&inner)
+    }
+
+    pub fn readlink(&self, path: &str) -> Result<String, OpenError> {
+        let (fs, inner) = self.locate(&self.walk(path, false)?);
+
+        fs.readlink(&inner)
+    }
+
+    pub fn readdir(&self, path: &str) -> Result<Vec<(String, Stat)>, OpenError> {
+        let (fs, inner) = self.locate(&self.walk(path, true)?);
+
+        fs.readdir(&inner)
+    }
+
+    // Whether the bits of `access` are allowed on the inode at `path`, every directory on
+    // the way there has to be searchable
+    pub fn access(&self, path: &str, credentials: &Credentials, access: u16) -> Result<(), OpenError> {
+        let path = self.walk_as(path, true, Some(credentials))?;
+
+        if !credentials.permits(&self.stat(&path)?, access) {
+            return Err(OpenError::PermissionDenied);
+        }
+
+        Ok(())
+    }
+
+    // Whether every directory on the way to the path can be searched
+    pub fn search(&self, path: &str, follow: bool, credentials: &Credentials) -> Result<(), OpenError> {
+        self.walk_as(path, follow, Some(credentials))?;
+
+        Ok(())
+    }
+
+    // Without `READ` or `WRITE` the file is opened for reading.
+    // A FIFO is returned at once, its `opened` waits until the other end is open too.
+    pub fn open(&self, path: &str, flags: OpenFlags) -> Result<FileDescriptor, OpenError> {
+        let path = match self.walk(path, true) {
+            Err(OpenError::NoEntry) if flags.contains(OpenFlags::CREATE) => {
+                let path = self.entry(path)?;
+                let (fs, inner) = self.writable(&path)?;
+
+                fs.create(&inner)?;
+                self.watchers.notify(Event::Created(path.clone()));
+                path
+            }
+            path => path?,
+        };
+
+        let (fs, inner) = if flags.contains(OpenFlags::WRITE) || flags.contains(OpenFlags::TRUNCATE) {
+            self.writable(&path)?
+        } else {
+            self.locate(&path)
+        };
+
+        let stat = fs.stat(&inner)?;
+        let flags = if flags.contains(OpenFlags::WRITE) { flags } else { flags | OpenFlags::READ };
+
+        // streams have nothing to truncate
+        match stat.kind {
+            Kind::CharDevice => {
+                return Ok(FileDescriptor::stream(dev::open(stat.device)?, flags, &path));
+            }
+            Kind::Fifo => {
+                let end = self.fifo(&fs, stat.ino).open(
+                    flags.contains(OpenFlags::READ),
+                    flags.contains(OpenFlags::WRITE),
+                );
+
+                return Ok(FileDescriptor::fifo(end, flags, &path));
+            }
+            // sockets are connected to instead
+            Kind::Socket => return Err(OpenError::NotSupported),
+            _ => {}
+        }
+
+        let handle: Rc<dyn FileHandle> = match stat.kind {
+            Kind::BlockDevice => Rc::new(DeviceFile::new(self.device(stat.device)?)),
+            _ => fs.open(&inner)?,
+        };
+
+        if flags.contains(OpenFlags::TRUNCATE) {
+            handle.truncate(0).map_err(|_| OpenError::ReadOnly)?;
+            self.watchers.notify(Event::Modified(path.clone()));
+        }
+
+        Ok(FileDescriptor::new(handle, flags, &path).watched(Rc::clone(&self.watchers)))
+    }
+
+    pub fn mkdir(&self, path: &str) -> Result<(), OpenError> {
+        let (fs, inner, entry) = self.new_entry(path)?;
+
+        fs.mkdir(&inner)?;
+        self.watchers.notify(Event::Created(entry));
+
+        Ok(())
+    }
+
+    pub fn chmod(&self, path: &str, mode: u16) -> Result<(), OpenError> {
+        let (fs, inner) = self.writable(&self.walk(path, true)?)?;
+
+        fs.chmod(&inner, mode & 0o7777)
+    }
+
+    pub fn chown(&self, path: &str, uid: Uid, gid: Gid) -> Result<(), OpenError> {
+        let (fs, inner) = self.writable(&self.walk(path, true)?)?;
+
+        fs.chown(&inner, uid, gid)
+    }
+
+    // Doesn't follow a link at the end of the path
+    pub fn lchown(&self, path: &str, uid: Uid, gid: Gid) -> Result<(), OpenError> {
+        let (fs, inner) = self.writable(&self.walk(path, false)?)?;
+
+        fs.chown(&inner, uid, gid)
+    }
+
+    // A named pipe, only filesystems that can store special files have them
+    pub fn mkfifo(&self, path: &str) -> Result<(), OpenError> {
+        let (fs, inner, entry) = self.new_entry(path)?;
+
+        fs.mknod(&inner, Kind::Fifo, 0)?;
+        self.watchers.notify(Event::Created(entry));
+
+        Ok(())
+    }
+
+    // The pipe shared by everyone who opens the FIFO
+    fn fifo(&self, fs: &Rc<dyn FileSystem>, ino: u64) -> Rc<Fifo> {
+        let key = inode_key(fs, ino);
+        let mut fifos = self.fifos.borrow_mut();
+
+        // the ones that nobody has open are forgotten
+        fifos.retain(|(_, fifo)| Rc::strong_count(fifo) > 1);
+
+        if let Some((_, fifo)) = fifos.iter().find(|(other, _)| *other == key) {
+            return Rc::clone(fifo);
+        }
+
+        let fifo = Fifo::new();
+        fifos.push((key, Rc::clone(&fifo)));
+
+        fifo
+    }
+
+    // Makes the special file of the socket at `path`, which must not exist yet
+    pub fn bind(&self, socket: &Rc<Socket>, path: &str) -> Result<(), OpenError> {
+        if !socket.path().is_empty() {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let (fs, inner, entry) = self.new_entry(path)?;
+
+        fs.mknod(&inner, Kind::Socket, 0)?;
+        socket.bind(&entry)?;
+        self.watchers.notify(Event::Created(entry));
+
+        let key = inode_key(&fs, fs.stat(&inner)?.ino);
+        let mut sockets = self.sockets.borrow_mut();
+
+        sockets.retain(|(_, socket)| socket.strong_count() > 0);
+        sockets.push((key, Rc::downgrade(socket)));
+
+        Ok(())
+    }
+
+    // The socket bound to `path`, if it's still there
+    fn bound(&self, path: &str) -> Result<Rc<Socket>, OpenError> {
+        let (fs, inner) = self.locate(&self.walk(path, true)?);
+        let stat = fs.stat(&inner)?;
+
+        if stat.kind != Kind::Socket {
+            return Err(OpenError::ConnectionRefused);
+        }
+
+        let key = inode_key(&fs, stat.ino);
+        let sockets = self.sockets.borrow();
+
+        sockets
+            .iter()
+            .find(|(other, _)| *other == key)
+            .and_then(|(_, socket)| socket.upgrade())
+            .ok_or(OpenError::ConnectionRefused)
+    }
+
+    // A stream going both ways to the socket listening at `path`
+    pub fn connect(&self, path: &str) -> Result<FileDescriptor, OpenError> {
+        let connection = self.bound(path)?.connect()?;
+
+        Ok(FileDescriptor::stream(Rc::new(connection), OpenFlags::READ | OpenFlags::WRITE, path))
+    }
+
+    // Sends a message to the datagram socket at `path`, from `socket` to reply to
+    pub fn send_to(&self, socket: &Socket, path: &str, message: &str) -> Result<(), OpenError> {
+        self.bound(path)?.deliver(&socket.path(), message)
+    }
+
+    // Creates a link at `path` pointing to `target`, which doesn't have to exist
+    pub fn symlink(&self, target: &str, path: &str) -> Result<(), OpenError> {
+        let (fs, inner, entry) = self.new_entry(path)?;
+
+        fs.symlink(target, &inner)?;
+        self.watchers.notify(Event::Created(entry));
+
+        Ok(())
+    }
+
+    // Gives the file at `existing` a second name on the same filesystem
+    pub fn link(&self, existing: &str, path: &str) -> Result<(), OpenError> {
+        let (existing_fs, existing) = self.locate(&self.walk(existing, true)?);
+        let (fs, inner, entry) = self.new_entry(path)?;
+
+        if !Rc::ptr_eq(&existing_fs, &fs) {
+            return Err(OpenError::CrossDevice);
+        }
+
+        fs.link(&existing, &inner)?;
+        self.watchers.notify(Event::Created(entry));
+
+        Ok(())
+    }
+
+    // Removes a file or a link, directories have to be removed with `rmdir`
+    pub fn unlink(&self, path: &str) -> Result<(), OpenError> {
+        let path = self.entry(path)?;
+        let (fs, inner) = self.writable(&path)?;
+
+        fs.unlink(&inner)?;
+        self.watchers.notify(Event::Deleted(path));
+
+        Ok(())
+    }
+
+    // Removes an empty directory
+    pub fn rmdir(&self, path: &str) -> Result<(), OpenError> {
+        let path = self.entry(path)?;
+
+        if self.is_mount_point(&path) {
+            return Err(OpenError::Busy);
+        }
+
+        let (fs, inner) = self.writable(&path)?;
+
+        fs.rmdir(&inner)?;
+        self.watchers.notify(Event::Deleted(path));
+
+        Ok(())
+    }
+
+    // Moves an entry inside of a filesystem
+    pub fn rename(&self, from: &str, to: &str) -> Result<(), OpenError> {
+        let (from, to) = (self.entry(from)?, self.entry(to)?);
+
+        if self.is_mount_point(&from) || self.is_mount_point(&to) {
+            return Err(OpenError::Busy);
+        }
+
+        let (from_fs, from_inner) = self.writable(&from)?;
+        let (to_fs, to_inner) = self.writable(&to)?;
+
+        if !Rc::ptr_eq(&from_fs, &to_fs) {
+            return Err(OpenError::CrossDevice);
+        }
+
+        from_fs.rename(&from_inner, &to_inner)?;
+        self.watchers.notify(Event::Renamed(from, to));
+
+        Ok(())
+    }
+
+    // Hides the directory at `path` behind the root of `fs`
+    pub fn mount(
+        &self,
+        path: &str,
+        source: &str,
+        fs: Rc<dyn FileSystem>,
+        read_only: bool,
+    ) -> Result<(), OpenError> {
+        let path = self.walk(path, true)?;
+
+        if self.stat(&path)?.kind != Kind::Directory {
+            return Err(OpenError::NotADirectory);
+        }
+
+        if self.is_mount_point(&path) {
+            return Err(OpenError::Busy);
+        }
+
+        self.mounts.borrow_mut().push(Mount {
+            path,
+            source: source.to_string(),
+            fs,
+            read_only,
+        });
+
+        Ok(())
+    }
+
+    // Filesystems with other filesystems mounted inside of them stay
+    pub fn umount(&self, path: &str) -> Result<(), OpenError> {
+        let path = self.walk(path, true)?;
+
+        if !self.is_mount_point(&path) {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let mut mounts = self.mounts.borrow_mut();
+
+        let busy = mounts
+            .iter()
+            .any(|mount| mount.path != path && is_inside(&mount.path, &path));
+
+        if path == "/" || busy {
+            return Err(OpenError::Busy);
+        }
+
+        if let Some(mount) = mounts.iter().find(|mount| mount.path == path) {
+            mount.fs.sync()?;
+        }
+
+        mounts.retain(|mount| mount.path != path);
+
+        Ok(())
+    }
+
+    // In the order they were mounted
+    pub fn mounts(&self) -> Vec<Mount> {
+        self.mounts.borrow().clone()
+    }
+
+    fn device(&self, number: u32) -> Result<Rc<dyn BlockDevice>, OpenError> {
+        let devices = self.block_devices.borrow();
+
+        devices.get(number as usize).cloned().ok_or(OpenError::NoEntry)
+    }
+
+    // The device behind the block special file at `path`
+    pub fn block_device(&self, path: &str) -> Result<Rc<dyn BlockDevice>, OpenError> {
+        let stat = self.stat(path)?;
+
+        if stat.kind != Kind::BlockDevice {
+            return Err(OpenError::NotABlockDevice);
+        }
+
+        self.device(stat.device)
+    }
+
+    // Makes a device available as `/dev/{prefix}N` with the first free N, returns the path
+    pub fn attach(&self, prefix: &str, device: Rc<dyn BlockDevice>) -> Result<String, OpenError> {
+        let path = (0..MAX_DEVICES)
+            .map(|index| format!("/dev/{prefix}{index}"))
+            .find(|path| self.lstat(path) == Err(OpenError::NoEntry))
+            .ok_or(OpenError::NoSpace)?;
+
+        let number = self.block_devices.borrow().len() as u32;
+        let (fs, inner, entry) = self.new_entry(&path)?;
+
+        fs.mknod(&inner, Kind::BlockDevice, number)?;
+        self.block_devices.borrow_mut().push(device);
+        self.watchers.notify(Event::Created(entry));
+
+        Ok(path)
+    }
+}
+
+fn inode_key(fs: &Rc<dyn FileSystem>, ino: u64) -> InodeKey {
+    (Rc::as_ptr(fs) as *const () as usize, ino)
+}
+
+fn is_inside(path: &str, directory: &str) -> bool {
+    directory == "/" || path == directory || path.starts_with(&format!("{directory}/"))
+}
+
+fn components(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+fn join(names: &[String]) -> String {
+    format!("/{}", names.join("/"))
+}
+
+// Splits an absolute path in the path of the parent and the name of the entry
+fn split_parent(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(index) => (&path[..index + 1], &path[index + 1..]),
+        None => ("/", path),
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub enum OpenError {
     NoEntry,
-    NoDirectoy,
+    NotADirectory,
     IsDirectory,
-    IsFile
+    AlreadyExists,
+    NotEmpty,
+    // like moving a directory inside of itself
+    InvalidArgument,
+    // probably a loop of links
+    TooManyLinks,
+    ReadOnly,
+    // a mount point can't be removed
+    Busy,
+    // links and renames can't go to another filesystem
+    CrossDevice,
+    // no filesystem with that name
+    NotSupported,
+    // no free blocks or inodes are left
+    NoSpace,
+    // only block special files can be mounted
+    NotABlockDevice,
+    // the storage of a filesystem failed or is corrupted
+    DeviceError,
+    // nothing is listening at the path of a socket
+    ConnectionRefused,
+    // the mode or the owner of an inode doesn't allow it
+    PermissionDenied,
 }
 
 impl Proc {
-    pub fn open(
-        &self,
-        filename: String,
-    ) -> Result<ReadableWritableDescriptor<PipeReader, PipeWriter>, OpenError> {
-        let fs = &ROOT.fs;
+    // Makes a path absolute and removes `.`. A `..` is left for the VFS, after a link it
+    // leads to the parent of the target.
+    pub fn resolve(&self, path: &str) -> String {
+        let mut components: Vec<String> = vec![];
 
-        for (name, entry) in fs.mount.0.iter() {
-            if name.0 != filename {
-                continue;
+        if !path.starts_with('/') {
+            for name in self.cwd.borrow().split('/').filter(|name| !name.is_empty()) {
+                components.push(name.to_string());
             }
+        }
+
+        for name in path.split('/') {
+            match name {
+                "" | "." => {}
+                name => components.push(name.to_string()),
+            }
+        }
 
-            if let Inode::File(content) = entry {
-                
-                let pipe = self.pipe();
+        format!("/{}", components.join("/"))
+    }
+
+    // Whether the process may access the inode at `path`, with the bits of `MAY_READ`...
+    pub fn access(&self, path: &str, access: u16) -> Result<(), OpenError> {
+        ROOT.fs.access(&self.resolve(path), &self.credentials(), access)
+    }
 
-                pipe.write(content);
+    // Adding or removing an entry needs write and search permission on its directory
+    fn check_parent(&self, path: &str) -> Result<(), OpenError> {
+        let path = self.resolve(path);
+        let (dirname, _) = split_parent(&path);
 
-                return Ok(pipe);
-            }
+        ROOT.fs.access(dirname, &self.credentials(), MAY_WRITE | MAY_EXECUTE)
+    }
+
+    // Finding an entry needs search permission on the directories leading to it
+    fn check_search(&self, path: &str, follow: bool) -> Result<(), OpenError> {
+        ROOT.fs.search(&self.resolve(path), follow, &self.credentials())
+    }
+
+    // Only root changes what a path leads to, like by mounting over it
+    fn check_root(&self) -> Result<(), OpenError> {
+        if !self.credentials().is_root() {
+            return Err(OpenError::PermissionDenied);
         }
 
-        Err(OpenError::NoEntry)
+        Ok(())
     }
 
-    pub fn open_dir(&self, dirname: String) ->  Result<&Directory, OpenError> {
-        let fs = &ROOT.fs;
+    // New inodes belong to the user who made them
+    fn own(&self, path: &str) {
+        let credentials = self.credentials();
 
-        for (name, entry) in fs.mount.0.iter() {
-            if name.0 != dirname {
-                continue;
-            }
+        // filesystems without owners give everything to root
+        if !credentials.is_root() {
+            let _ = ROOT.fs.lchown(&self.resolve(path), credentials.uid, credentials.gid);
+        }
+    }
+
+    pub fn open(&self, filename: String, flags: OpenFlags) -> Result<FileDescriptor, OpenError> {
+        let path = self.resolve(&filename);
+
+        let mut access = 0;
+        if flags.contains(OpenFlags::READ) || !flags.contains(OpenFlags::WRITE) {
+            access |= MAY_READ;
+        }
+        if flags.contains(OpenFlags::WRITE) || flags.contains(OpenFlags::TRUNCATE) {
+            access |= MAY_WRITE;
+        }
+
+        match ROOT.fs.access(&path, &self.credentials(), access) {
+            Err(OpenError::NoEntry) if flags.contains(OpenFlags::CREATE) => {
+                self.check_parent(&path)?;
 
-            if let Inode::Directory(directory) = entry {
-                return Ok(directory);
+                let file = ROOT.fs.open(&path, flags)?;
+                self.own(&path);
+
+                Ok(file)
+            }
+            result => {
+                result?;
+                ROOT.fs.open(&path, flags)
             }
+        }
+    }
+
+    pub fn stat(&self, path: &str) -> Result<Stat, OpenError> {
+        self.check_search(path, true)?;
+        ROOT.fs.stat(&self.resolve(path))
+    }
+
+    // Doesn't follow a link at the end of the path
+    pub fn lstat(&self, path: &str) -> Result<Stat, OpenError> {
+        self.check_search(path, false)?;
+        ROOT.fs.lstat(&self.resolve(path))
+    }
+
+    // The target of a link is stored as it is given
+    pub fn symlink(&self, target: &str, path: &str) -> Result<(), OpenError> {
+        self.check_parent(path)?;
+        ROOT.fs.symlink(target, &self.resolve(path))?;
+        self.own(path);
+
+        Ok(())
+    }
+
+    pub fn readlink(&self, path: &str) -> Result<String, OpenError> {
+        self.check_search(path, false)?;
+        ROOT.fs.readlink(&self.resolve(path))
+    }
+
+    pub fn link(&self, existing: &str, path: &str) -> Result<(), OpenError> {
+        self.check_parent(path)?;
+        ROOT.fs.link(&self.resolve(existing), &self.resolve(path))
+    }
+
+    pub fn mkdir(&self, dirname: &str) -> Result<(), OpenError> {
+        self.check_parent(dirname)?;
+        ROOT.fs.mkdir(&self.resolve(dirname))?;
+        self.own(dirname);
+
+        Ok(())
+    }
+
+    pub fn mkfifo(&self, path: &str) -> Result<(), OpenError> {
+        self.check_parent(path)?;
+        ROOT.fs.mkfifo(&self.resolve(path))?;
+        self.own(path);
 
-            return Err(OpenError::IsFile)
+        Ok(())
+    }
+
+    // Only the owner and root may change the mode
+    pub fn chmod(&self, path: &str, mode: u16) -> Result<(), OpenError> {
+        let credentials = self.credentials();
+
+        if !credentials.is_root() && self.stat(path)?.uid != credentials.uid {
+            return Err(OpenError::PermissionDenied);
+        }
+
+        ROOT.fs.chmod(&self.resolve(path), mode)
+    }
+
+    // `None` keeps the owner or the group. Only root gives inodes away, their owner may
+    // only change the group to one of its own.
+    pub fn chown(&self, path: &str, uid: Option<Uid>, gid: Option<Gid>) -> Result<(), OpenError> {
+        let stat = self.stat(path)?;
+        let credentials = self.credentials();
+
+        let (uid, gid) = (uid.unwrap_or(stat.uid), gid.unwrap_or(stat.gid));
+
+        let allowed = credentials.is_root()
+            || (stat.uid == credentials.uid
+                && uid == stat.uid
+                && (gid == stat.gid || credentials.in_group(gid)));
+
+        if !allowed {
+            return Err(OpenError::PermissionDenied);
         }
 
-        Err(OpenError::NoEntry)
+        ROOT.fs.chown(&self.resolve(path), uid, gid)
+    }
+
+    // An unbound socket, only datagrams can be sent from it
+    pub fn socket(&self, kind: SocketKind) -> Rc<Socket> {
+        Socket::new(kind)
+    }
+
+    pub fn bind(&self, socket: &Rc<Socket>, path: &str) -> Result<(), OpenError> {
+        self.check_parent(path)?;
+        ROOT.fs.bind(socket, &self.resolve(path))?;
+        self.own(path);
+
+        Ok(())
+    }
+
+    // Like opening a file, connecting needs write permission on the socket
+    pub fn connect(&self, path: &str) -> Result<FileDescriptor, OpenError> {
+        self.access(path, MAY_WRITE)?;
+        ROOT.fs.connect(&self.resolve(path))
+    }
+
+    pub fn send_to(&self, socket: &Socket, path: &str, message: &str) -> Result<(), OpenError> {
+        self.access(path, MAY_WRITE)?;
+        ROOT.fs.send_to(socket, &self.resolve(path), message)
+    }
+
+    pub fn unlink(&self, filename: &str) -> Result<(), OpenError> {
+        self.check_parent(filename)?;
+        ROOT.fs.unlink(&self.resolve(filename))
+    }
+
+    pub fn rmdir(&self, dirname: &str) -> Result<(), OpenError> {
+        self.check_parent(dirname)?;
+        ROOT.fs.rmdir(&self.resolve(dirname))
+    }
+
+    pub fn rename(&self, from: &str, to: &str) -> Result<(), OpenError> {
+        self.check_parent(from)?;
+        self.check_parent(to)?;
+        ROOT.fs.rename(&self.resolve(from), &self.resolve(to))
+    }
+
+    // The entries of a directory with their metadata, sorted by name
+    pub fn readdir(&self, dirname: &str) -> Result<Vec<(String, Stat)>, OpenError> {
+        self.access(dirname, MAY_READ)?;
+        ROOT.fs.readdir(&self.resolve(dirname))
+    }
+
+    // Changes are seen by whoever could read them
+    pub fn watch(&self, path: &str) -> Result<Rc<Watch>, OpenError> {
+        self.access(path, MAY_READ)?;
+        ROOT.fs.watch(&self.resolve(path))
+    }
+
+    pub fn chdir(&self, dirname: &str) -> Result<(), OpenError> {
+        if self.stat(dirname)?.kind != Kind::Directory {
+            return Err(OpenError::NotADirectory);
+        }
+
+        self.access(dirname, MAY_EXECUTE)?;
+
+        // without links and `..`
+        *self.cwd.borrow_mut() = ROOT.fs.walk(&self.resolve(dirname), true)?;
+
+        Ok(())
+    }
+
+    // Mounts a new filesystem of type `kind` made from `source` at `dirname`,
+    // `options` are separated by commas like `ro,uid=1000`
+    pub fn mount(
+        &self,
+        kind: &str,
+        source: &str,
+        dirname: &str,
+        options: &str,
+    ) -> Result<(), OpenError> {
+        self.check_root()?;
+
+        let mounter = mounter(kind).ok_or(OpenError::NotSupported)?;
+
+        let mut read_only = false;
+        let mut rest = vec![];
+
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            match option {
+                "ro" => read_only = true,
+                "rw" => read_only = false,
+                _ => rest.push(option),
+            }
+        }
+
+        ROOT.fs.mount(&self.resolve(dirname), source, mounter(self, source, &rest)?, read_only)
+    }
+
+    pub fn umount(&self, dirname: &str) -> Result<(), OpenError> {
+        self.check_root()?;
+        ROOT.fs.umount(&self.resolve(dirname))
+    }
+
+    pub fn mounts(&self) -> Vec<Mount> {
+        ROOT.fs.mounts()
+    }
+
+    pub fn block_device(&self, path: &str) -> Result<Rc<dyn BlockDevice>, OpenError> {
+        self.access(path, MAY_READ | MAY_WRITE)?;
+        ROOT.fs.block_device(&self.resolve(path))
+    }
+
+    pub fn attach(&self, prefix: &str, device: Rc<dyn BlockDevice>) -> Result<String, OpenError> {
+        self.check_root()?;
+        ROOT.fs.attach(prefix, device)
     }
 
     pub fn pipe(&self) -> ReadableWritableDescriptor<PipeReader, PipeWriter> {
@@ -116,6 +1152,494 @@ impl Proc {
 // d	Directory file
 // l	Link file
 // b	Block special file => buffered access, chunks of data
-// p	Named pipe file => interproces communication
+// p	Named pipe file => interproces communication, made by `mkfifo`
 // c	Character special file => direct access, byte by byte
-// s	Socket file => ip:socket
+// s	Socket file => made by `bind`, found by `connect`
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fc::future::Executor;
+    use descriptor::{IOError, ReadableDescriptor, WritableDescriptor};
+    use std::io::SeekFrom;
+
+    fn kind(stat: Result<Stat, OpenError>) -> Result<Kind, OpenError> {
+        stat.map(|stat| stat.kind)
+    }
+
+    #[test]
+    fn lookup() {
+        let fs = Fs::new();
+
+        assert_eq!(kind(fs.stat("/")), Ok(Kind::Directory));
+        assert_eq!(kind(fs.stat("/mount_folder/sub_file_1")), Ok(Kind::File));
+        assert_eq!(fs.stat("/missing"), Err(OpenError::NoEntry));
+        assert_eq!(fs.stat("/mount-file/x"), Err(OpenError::NotADirectory));
+        assert_eq!(
+            fs.open("/etc", OpenFlags::READ).err(),
+            Some(OpenError::IsDirectory)
+        );
+    }
+
+    #[test]
+    fn links() {
+        let fs = Fs::new();
+
+        assert_eq!(kind(fs.stat("/home/fritz/.profile")), Ok(Kind::File));
+        assert_eq!(kind(fs.lstat("/home/fritz/.profile")), Ok(Kind::Symlink));
+
+        // relative to the directory of the link
+        fs.symlink("../mount_folder", "/etc/shared").unwrap();
+        assert_eq!(
+            fs.stat("/etc/shared/sub_file_1").map(|stat| stat.size),
+            Ok(21)
+        );
+        assert_eq!(
+            fs.readdir("/etc/shared").map(|entries| entries.len()),
+            Ok(2)
+        );
+
+        fs.symlink("/loop_b", "/loop_a").unwrap();
+        fs.symlink("/loop_a", "/loop_b").unwrap();
+        assert_eq!(fs.stat("/loop_a"), Err(OpenError::TooManyLinks));
+
+        fs.symlink("/missing", "/dangling").unwrap();
+        assert_eq!(fs.stat("/dangling"), Err(OpenError::NoEntry));
+        assert_eq!(fs.unlink("/dangling"), Ok(()));
+
+        // files are created through links to directories
+        fs.open("/etc/shared/new", OpenFlags::WRITE | OpenFlags::CREATE)
+            .unwrap();
+        assert!(fs.stat("/mount_folder/new").is_ok());
+
+        // `..` after a link is the parent of the target, not of the link
+        let proc = Proc::new(0);
+        *proc.cwd.borrow_mut() = "/etc".to_string();
+        let path = proc.resolve("shared/../mount-file");
+        assert_eq!(kind(fs.stat(&path)), Ok(Kind::File));
+        assert!(fs.stat("/etc/profile").is_ok());
+        assert_eq!(
+            kind(fs.stat("/etc/shared/../profile")),
+            Err(OpenError::NoEntry)
+        );
+        fs.mkdir("/etc/shared/../made").unwrap();
+        assert!(fs.stat("/made").is_ok());
+        assert_eq!(fs.mkdir("/etc/shared/.."), Err(OpenError::AlreadyExists));
+        assert_eq!(fs.rmdir("/etc/shared/.."), Err(OpenError::InvalidArgument));
+    }
+
+    #[test]
+    fn mounts() {
+        let fs = Fs::new();
+        let tmpfs = || Rc::new(MemoryFs::new());
+
+        fs.mkdir("/mnt").unwrap();
+        fs.mount("/mnt", "none", tmpfs(), false).unwrap();
+        assert_eq!(
+            fs.mount("/mnt", "none", tmpfs(), false),
+            Err(OpenError::Busy)
+        );
+        assert_eq!(
+            fs.mount("/mount-file", "none", tmpfs(), false),
+            Err(OpenError::NotADirectory)
+        );
+
+        let file = fs
+            .open("/mnt/file", OpenFlags::WRITE | OpenFlags::CREATE)
+            .unwrap();
+        file.pwrite(0, "mounted").unwrap();
+
+        let file = fs.open("/mnt/file", OpenFlags::READ).unwrap();
+        assert_eq!(file.read_sync(100), Ok("mounted".to_string()));
+
+        assert_eq!(fs.readdir("/mnt").map(|entries| entries.len()), Ok(1));
+        assert_eq!(fs.rename("/mnt/file", "/file"), Err(OpenError::CrossDevice));
+        assert_eq!(fs.link("/mnt/file", "/file"), Err(OpenError::CrossDevice));
+        assert_eq!(fs.rmdir("/mnt"), Err(OpenError::Busy));
+        assert_eq!(fs.umount("/"), Err(OpenError::Busy));
+
+        // the directory below is untouched
+        fs.umount("/mnt").unwrap();
+        assert_eq!(fs.readdir("/mnt").map(|entries| entries.len()), Ok(0));
+        assert_eq!(fs.umount("/mnt"), Err(OpenError::InvalidArgument));
+    }
+
+    #[test]
+    fn read_only() {
+        let fs = Fs::new();
+        let tmpfs = Rc::new(MemoryFs::new());
+        tmpfs.create("/file").unwrap();
+
+        fs.mount("/mount_folder", "none", tmpfs, true).unwrap();
+
+        assert!(fs.open("/mount_folder/file", OpenFlags::READ).is_ok());
+        assert_eq!(
+            fs.open("/mount_folder/file", OpenFlags::WRITE).err(),
+            Some(OpenError::ReadOnly)
+        );
+        assert_eq!(
+            fs.open("/mount_folder/new", OpenFlags::WRITE | OpenFlags::CREATE)
+                .err(),
+            Some(OpenError::ReadOnly)
+        );
+        assert_eq!(fs.mkdir("/mount_folder/dir"), Err(OpenError::ReadOnly));
+        assert_eq!(fs.unlink("/mount_folder/file"), Err(OpenError::ReadOnly));
+        assert_eq!(
+            fs.rename("/mount_folder/file", "/mount_folder/moved"),
+            Err(OpenError::ReadOnly)
+        );
+    }
+
+    #[test]
+    fn devices() {
+        let fs = Fs::new();
+
+        assert_eq!(kind(fs.stat("/dev/ram0")), Ok(Kind::BlockDevice));
+        assert_eq!(
+            fs.block_device("/etc").err(),
+            Some(OpenError::NotABlockDevice)
+        );
+
+        let disk = Rc::new(RamDisk::new(BLOCK_SIZE, 16));
+        assert_eq!(fs.attach("ram", disk.clone()), Ok("/dev/ram1".to_string()));
+
+        // the bytes of the device
+        let file = fs
+            .open("/dev/ram1", OpenFlags::READ | OpenFlags::WRITE)
+            .unwrap();
+        file.pwrite(BLOCK_SIZE - 1, "ab").unwrap();
+        assert_eq!(file.pread(BLOCK_SIZE, 1), Ok("b".to_string()));
+        assert_eq!(file.pread(16 * BLOCK_SIZE, 1), Err(IOError::ChannelClosed));
+
+        let image = ImageFs::format(fs.block_device("/dev/ram1").unwrap(), 8).unwrap();
+        fs.mount("/mount_folder", "/dev/ram1", Rc::new(image), false)
+            .unwrap();
+        assert_eq!(
+            fs.readdir("/mount_folder").map(|entries| entries.len()),
+            Ok(0)
+        );
+
+        // there are only so many numbers
+        for index in 2..MAX_DEVICES {
+            assert_eq!(
+                fs.attach("ram", disk.clone()),
+                Ok(format!("/dev/ram{index}"))
+            );
+        }
+        assert_eq!(fs.attach("ram", disk), Err(OpenError::NoSpace));
+    }
+
+    #[test]
+    fn character_devices() {
+        let fs = Fs::new();
+
+        let names: Vec<String> = fs
+            .readdir("/dev")
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["fb0", "null", "ram0", "random", "tty", "zero"]);
+
+        let null = fs
+            .open("/dev/null", OpenFlags::WRITE | OpenFlags::TRUNCATE)
+            .unwrap();
+        assert_eq!(null.write("gone"), Ok(()));
+        assert_eq!(null.seek(SeekFrom::Start(0)), Err(IOError::InvalidOffset));
+        assert_eq!(null.read_sync(1), Err(IOError::BadDescriptor));
+
+        let zero = fs.open("/dev/zero", OpenFlags::READ).unwrap();
+        assert_eq!(zero.read_sync(2), Ok("\0\0".to_string()));
+
+        assert_eq!(fs.mkdir("/dev/dir"), Err(OpenError::NotSupported));
+        assert_eq!(
+            fs.block_device("/dev/null").err(),
+            Some(OpenError::NotABlockDevice)
+        );
+    }
+
+    #[test]
+    fn fifos() {
+        let fs = Fs::new();
+        fs.mkfifo("/pipe").unwrap();
+        assert_eq!(kind(fs.stat("/pipe")), Ok(Kind::Fifo));
+        assert_eq!(fs.mkfifo("/pipe"), Err(OpenError::AlreadyExists));
+        assert_eq!(fs.mkfifo("/proc/pipe"), Err(OpenError::ReadOnly));
+
+        // both ends are connected through the path
+        let writer = fs
+            .open("/pipe", OpenFlags::WRITE | OpenFlags::TRUNCATE)
+            .unwrap();
+        assert_eq!(writer.write("lost"), Err(IOError::ChannelClosed));
+
+        let reader = fs.open("/pipe", OpenFlags::READ).unwrap();
+        Executor::block(writer.opened());
+        writer.write("hello").unwrap();
+        assert_eq!(reader.read_sync(100), Ok("hello".to_string()));
+        assert_eq!(reader.read_sync(100), Err(IOError::Empty));
+        assert_eq!(reader.seek(SeekFrom::Start(0)), Err(IOError::InvalidOffset));
+
+        drop(writer);
+        assert_eq!(reader.read_sync(100), Err(IOError::ChannelClosed));
+    }
+
+    #[test]
+    fn sockets() {
+        let fs = Fs::new();
+        let server = Socket::new(SocketKind::Stream);
+
+        fs.bind(&server, "/server").unwrap();
+        assert_eq!(kind(fs.stat("/server")), Ok(Kind::Socket));
+        assert_eq!(
+            fs.connect("/server").err(),
+            Some(OpenError::ConnectionRefused)
+        );
+        assert_eq!(
+            fs.connect("/etc/profile").err(),
+            Some(OpenError::ConnectionRefused)
+        );
+        assert_eq!(
+            fs.open("/server", OpenFlags::READ).err(),
+            Some(OpenError::NotSupported)
+        );
+
+        server.listen().unwrap();
+        let client = fs.connect("/server").unwrap();
+        let accepted = Executor::block(server.accept()).unwrap();
+        client.write("get key").unwrap();
+        assert_eq!(accepted.read_sync(100), Ok("get key".to_string()));
+
+        // datagrams carry the path to reply to
+        let log = Socket::new(SocketKind::Datagram);
+        let sender = Socket::new(SocketKind::Datagram);
+        fs.bind(&log, "/log").unwrap();
+        fs.bind(&sender, "/sender").unwrap();
+        assert_eq!(fs.bind(&sender, "/again"), Err(OpenError::InvalidArgument));
+        assert_eq!(
+            fs.bind(&Socket::new(SocketKind::Stream), "/log"),
+            Err(OpenError::AlreadyExists)
+        );
+
+        fs.send_to(&sender, "/log", "started").unwrap();
+        let received = Executor::block(log.receive());
+        assert_eq!(received, Ok(("/sender".to_string(), "started".to_string())));
+
+        // the file stays after the socket is gone
+        drop(log);
+        assert_eq!(
+            fs.send_to(&sender, "/log", "lost"),
+            Err(OpenError::ConnectionRefused)
+        );
+    }
+
+    #[test]
+    fn archive() {
+        let members = [tar::Member {
+            path: "motd".to_string(),
+            mode: 0o644,
+            uid: 0,
+            gid: 0,
+            modified: 0,
+            content: tar::Content::File(b"welcome\n".to_vec()),
+        }];
+        set_root_archive(tar::write(&members).unwrap());
+
+        let fs = Fs::new();
+        let names: Vec<String> = fs
+            .readdir("/")
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["dev", "motd", "proc"]);
+        assert_eq!(kind(fs.stat("/dev/ram0")), Ok(Kind::BlockDevice));
+
+        // the next one is made from the embedded archive again
+        let fs = Fs::new();
+        assert_eq!(kind(fs.stat("/etc/profile")), Ok(Kind::File));
+
+        // with the owners and modes of the archive
+        let home = fs.stat("/home/fritz").unwrap();
+        assert_eq!((home.uid, home.gid, home.mode), (1000, 1000, 0o755));
+        assert_eq!(fs.stat("/root").unwrap().mode, 0o700);
+    }
+
+    #[test]
+    fn permissions() {
+        let fs = Fs::new();
+        let fritz = Credentials {
+            uid: 1000,
+            gid: 1000,
+            groups: vec![],
+        };
+
+        fs.mkdir("/private").unwrap();
+        fs.open("/private/notes", OpenFlags::CREATE).unwrap();
+        assert_eq!(fs.access("/private/notes", &fritz, MAY_READ), Ok(()));
+        assert_eq!(
+            fs.access("/private/notes", &fritz, MAY_WRITE),
+            Err(OpenError::PermissionDenied)
+        );
+
+        // nothing inside can be found without searching the directory
+        fs.chmod("/private", 0o700).unwrap();
+        assert_eq!(
+            fs.access("/private/notes", &fritz, MAY_READ),
+            Err(OpenError::PermissionDenied)
+        );
+        assert_eq!(
+            fs.search("/private/notes", false, &fritz),
+            Err(OpenError::PermissionDenied)
+        );
+        assert_eq!(fs.search("/private", false, &fritz), Ok(()));
+
+        fs.chown("/private", 1000, 1000).unwrap();
+        assert_eq!(fs.access("/private/notes", &fritz, MAY_READ), Ok(()));
+        assert_eq!(
+            fs.access("/private/missing", &fritz, MAY_READ),
+            Err(OpenError::NoEntry)
+        );
+        assert_eq!(
+            fs.access("/private", &Credentials::root(), MAY_WRITE),
+            Ok(())
+        );
+
+        // the owner of a link is its own, the mode is the one of the target
+        fs.symlink("/private/notes", "/link").unwrap();
+        fs.lchown("/link", 1000, 1000).unwrap();
+        assert_eq!(fs.lstat("/link").unwrap().uid, 1000);
+        assert_eq!(fs.stat("/link").unwrap().uid, 0);
+
+        // a link doesn't skip the directory it's in
+        fs.chown("/private", 0, 0).unwrap();
+        fs.symlink("/etc/profile", "/private/profile").unwrap();
+        assert_eq!(
+            fs.access("/private/profile", &fritz, MAY_READ),
+            Err(OpenError::PermissionDenied)
+        );
+        assert_eq!(fs.access("/etc/profile", &fritz, MAY_READ), Ok(()));
+    }
+
+    #[test]
+    fn lookups_need_search() {
+        let proc = Proc::new(1);
+        proc.set_credentials(Credentials {
+            uid: 1000,
+            gid: 1000,
+            groups: vec![],
+        });
+
+        // `/root` is only for root
+        assert_eq!(proc.stat("/root/missing"), Err(OpenError::PermissionDenied));
+        assert_eq!(
+            proc.lstat("/root/missing"),
+            Err(OpenError::PermissionDenied)
+        );
+        assert_eq!(
+            proc.readlink("/root/link").err(),
+            Some(OpenError::PermissionDenied)
+        );
+        assert_eq!(proc.stat("/root").map(|stat| stat.mode), Ok(0o700));
+        assert_eq!(Proc::new(1).stat("/root/missing"), Err(OpenError::NoEntry));
+    }
+
+    #[test]
+    fn mounts_need_root() {
+        let proc = Proc::new(1);
+        proc.set_credentials(Credentials {
+            uid: 1000,
+            gid: 1000,
+            groups: vec![],
+        });
+
+        let disk = Rc::new(RamDisk::new(BLOCK_SIZE, 1));
+
+        assert_eq!(
+            proc.mount("tmpfs", "none", "/etc", ""),
+            Err(OpenError::PermissionDenied)
+        );
+        assert_eq!(proc.umount("/proc"), Err(OpenError::PermissionDenied));
+        assert_eq!(
+            proc.attach("loop", disk).err(),
+            Some(OpenError::PermissionDenied)
+        );
+    }
+
+    #[test]
+    fn watches() {
+        let fs = Fs::new();
+        let event = |watch: &Watch| watch.try_next();
+        let path = |path: &str| path.to_string();
+
+        // through a link the path the change is made at is seen
+        fs.symlink("/etc", "/config").unwrap();
+        let etc = fs.watch("/config").unwrap();
+        assert_eq!(fs.watch("/missing").err(), Some(OpenError::NoEntry));
+
+        let file = fs
+            .open("/config/app", OpenFlags::WRITE | OpenFlags::CREATE)
+            .unwrap();
+        assert_eq!(event(&etc), Some(Event::Created(path("/etc/app"))));
+
+        let app = fs.watch("/etc/app").unwrap();
+        file.write("debug=1").unwrap();
+        assert_eq!(
+            Executor::block(app.next()),
+            Event::Modified(path("/etc/app"))
+        );
+        assert_eq!(event(&etc), Some(Event::Modified(path("/etc/app"))));
+
+        fs.open("/etc/app", OpenFlags::WRITE | OpenFlags::TRUNCATE)
+            .unwrap();
+        fs.rename("/etc/app", "/etc/app.old").unwrap();
+        fs.unlink("/etc/app.old").unwrap();
+        assert_eq!(event(&app), Some(Event::Modified(path("/etc/app"))));
+        assert_eq!(
+            event(&app),
+            Some(Event::Renamed(path("/etc/app"), path("/etc/app.old")))
+        );
+        assert_eq!(event(&app), None);
+
+        // reading changes nothing
+        fs.open("/etc/profile", OpenFlags::READ)
+            .unwrap()
+            .read_sync(10)
+            .unwrap();
+        fs.mkdir("/etc/init.d").unwrap();
+        fs.open("/etc/init.d/rc", OpenFlags::CREATE).unwrap();
+        fs.rmdir("/etc/init.d").unwrap_err();
+        assert_eq!(event(&etc), Some(Event::Modified(path("/etc/app"))));
+        assert_eq!(
+            event(&etc),
+            Some(Event::Renamed(path("/etc/app"), path("/etc/app.old")))
+        );
+        assert_eq!(event(&etc), Some(Event::Deleted(path("/etc/app.old"))));
+        assert_eq!(event(&etc), Some(Event::Created(path("/etc/init.d"))));
+        assert_eq!(event(&etc), None);
+    }
+
+    #[test]
+    fn parents() {
+        assert_eq!(
+            split_parent("/mount_folder/sub_file_1"),
+            ("/mount_folder/", "sub_file_1")
+        );
+        assert_eq!(split_parent("/mount-file"), ("/", "mount-file"));
+        assert_eq!(split_parent("/"), ("/", ""));
+    }
+
+    #[test]
+    fn resolve() {
+        let proc = Proc::new(0);
+
+        assert_eq!(proc.resolve("mount_folder"), "/mount_folder");
+        assert_eq!(proc.resolve(""), "/");
+
+        *proc.cwd.borrow_mut() = "/mount_folder".to_string();
+
+        assert_eq!(proc.resolve("sub_file_1"), "/mount_folder/sub_file_1");
+        assert_eq!(proc.resolve("../mount-file"), "/mount_folder/../mount-file");
+        assert_eq!(proc.resolve("./../../"), "/mount_folder/../..");
+        assert_eq!(proc.resolve("/mount_folder/."), "/mount_folder");
+    }
+}
@@ -0,0 +1,293 @@
+use std::{cell::RefCell, rc::Rc};
+
+use super::{
+    descriptor::IOError,
+    file::{utf8_prefix, FileHandle},
+};
+#[cfg(feature = "host")]
+use super::{host, OpenError};
+
+// Storage that is read and written in whole blocks, filesystem images sit on top of it
+pub trait BlockDevice {
+    // bytes in a block
+    fn block_size(&self) -> usize;
+    fn block_count(&self) -> usize;
+    // `buffer` and `data` are one block long
+    fn read_block(&self, index: usize, buffer: &mut [u8]) -> Result<(), IOError>;
+    fn write_block(&self, index: usize, data: &[u8]) -> Result<(), IOError>;
+    // Makes sure what was written reaches the storage
+    fn flush(&self) -> Result<(), IOError>;
+}
+
+// A device kept in a byte buffer, like an image loaded from the host
+pub struct RamDisk {
+    block_size: usize,
+    data: RefCell<Vec<u8>>,
+}
+
+impl RamDisk {
+    pub fn new(block_size: usize, blocks: usize) -> Self {
+        RamDisk {
+            block_size,
+            data: RefCell::new(vec![0; block_size * blocks]),
+        }
+    }
+
+    // The last block is padded with zeros
+    pub fn from_bytes(block_size: usize, mut bytes: Vec<u8>) -> Self {
+        bytes.resize(bytes.len().div_ceil(block_size) * block_size, 0);
+
+        RamDisk {
+            block_size,
+            data: RefCell::new(bytes),
+        }
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        self.data.borrow().clone()
+    }
+
+    fn range(&self, index: usize, len: usize) -> Result<std::ops::Range<usize>, IOError> {
+        if index >= self.block_count() || len != self.block_size {
+            return Err(IOError::InvalidOffset);
+        }
+
+        Ok(index * self.block_size..(index + 1) * self.block_size)
+    }
+}
+
+impl BlockDevice for RamDisk {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn block_count(&self) -> usize {
+        self.data.borrow().len() / self.block_size
+    }
+
+    fn read_block(&self, index: usize, buffer: &mut [u8]) -> Result<(), IOError> {
+        let range = self.range(index, buffer.len())?;
+
+        buffer.copy_from_slice(&self.data.borrow()[range]);
+        Ok(())
+    }
+
+    fn write_block(&self, index: usize, data: &[u8]) -> Result<(), IOError> {
+        let range = self.range(index, data.len())?;
+
+        self.data.borrow_mut()[range].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), IOError> {
+        Ok(())
+    }
+}
+
+// A file of the host used as a disk, like an image made by `mkfs`
+#[cfg(feature = "host")]
+pub struct FileDisk {
+    file: std::fs::File,
+    block_size: usize,
+}
+
+#[cfg(feature = "host")]
+impl FileDisk {
+    pub fn open(path: &str, block_size: usize) -> Result<Self, OpenError> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(host::error)?;
+
+        Ok(FileDisk { file, block_size })
+    }
+}
+
+#[cfg(feature = "host")]
+impl BlockDevice for FileDisk {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    // a partial block at the end is not used
+    fn block_count(&self) -> usize {
+        self.file
+            .metadata()
+            .map_or(0, |meta| meta.len() as usize / self.block_size)
+    }
+
+    fn read_block(&self, index: usize, buffer: &mut [u8]) -> Result<(), IOError> {
+        use std::os::unix::fs::FileExt;
+
+        if index >= self.block_count() || buffer.len() != self.block_size {
+            return Err(IOError::InvalidOffset);
+        }
+
+        self.file
+            .read_exact_at(buffer, (index * self.block_size) as u64)
+            .map_err(|_| IOError::DeviceError)
+    }
+
+    fn write_block(&self, index: usize, data: &[u8]) -> Result<(), IOError> {
+        use std::os::unix::fs::FileExt;
+
+        if index >= self.block_count() || data.len() != self.block_size {
+            return Err(IOError::InvalidOffset);
+        }
+
+        self.file
+            .write_all_at(data, (index * self.block_size) as u64)
+            .map_err(|_| IOError::DeviceError)
+    }
+
+    fn flush(&self) -> Result<(), IOError> {
+        self.file.sync_data().map_err(|_| IOError::DeviceError)
+    }
+}
+
+// Reads bytes at any offset, the blocks around them are read whole
+pub fn read_bytes(
+    device: &dyn BlockDevice,
+    offset: usize,
+    buffer: &mut [u8],
+) -> Result<(), IOError> {
+    let block_size = device.block_size();
+    let mut block = vec![0; block_size];
+    let mut position = 0;
+
+    while position < buffer.len() {
+        let (index, start) = (
+            (offset + position) / block_size,
+            (offset + position) % block_size,
+        );
+        let count = (block_size - start).min(buffer.len() - position);
+
+        device.read_block(index, &mut block)?;
+        buffer[position..position + count].copy_from_slice(&block[start..start + count]);
+
+        position += count;
+    }
+
+    Ok(())
+}
+
+// Writes bytes at any offset, partly written blocks are read first
+pub fn write_bytes(device: &dyn BlockDevice, offset: usize, data: &[u8]) -> Result<(), IOError> {
+    let block_size = device.block_size();
+    let mut block = vec![0; block_size];
+    let mut position = 0;
+
+    while position < data.len() {
+        let (index, start) = (
+            (offset + position) / block_size,
+            (offset + position) % block_size,
+        );
+        let count = (block_size - start).min(data.len() - position);
+
+        if count < block_size {
+            device.read_block(index, &mut block)?;
+        }
+
+        block[start..start + count].copy_from_slice(&data[position..position + count]);
+        device.write_block(index, &block)?;
+
+        position += count;
+    }
+
+    Ok(())
+}
+
+// A block special file opened like a file, its bytes are those of the device
+pub struct DeviceFile {
+    device: Rc<dyn BlockDevice>,
+}
+
+impl DeviceFile {
+    pub fn new(device: Rc<dyn BlockDevice>) -> Self {
+        DeviceFile { device }
+    }
+}
+
+impl FileHandle for DeviceFile {
+    fn read_at(&self, offset: usize, len: usize) -> Result<(String, usize), IOError> {
+        if offset >= self.size() {
+            return Err(IOError::ChannelClosed);
+        }
+
+        // enough for a whole char
+        let end = offset.saturating_add(len.max(4)).min(self.size());
+        let mut data = vec![0; end - offset];
+
+        read_bytes(self.device.as_ref(), offset, &mut data)?;
+
+        Ok(utf8_prefix(&data, len))
+    }
+
+    fn write_at(&self, offset: usize, data: &str) -> Result<(), IOError> {
+        if offset + data.len() > self.size() {
+            return Err(IOError::NoSpace);
+        }
+
+        write_bytes(self.device.as_ref(), offset, data.as_bytes())
+    }
+
+    fn size(&self) -> usize {
+        self.device.block_size() * self.device.block_count()
+    }
+
+    // a device keeps its size
+    fn truncate(&self, _size: usize) -> Result<(), IOError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::root::{
+        descriptor::ReadableDescriptor,
+        file::{FileDescriptor, OpenFlags},
+    };
+
+    #[test]
+    fn ram_disk() {
+        let disk = RamDisk::from_bytes(4, b"abcdefghij".to_vec());
+        let mut buffer = [0; 4];
+
+        assert_eq!(disk.block_count(), 3);
+        disk.read_block(2, &mut buffer).unwrap();
+        assert_eq!(&buffer, b"ij\0\0");
+        assert_eq!(disk.read_block(3, &mut buffer), Err(IOError::InvalidOffset));
+
+        disk.write_block(0, b"ABCD").unwrap();
+        assert_eq!(&disk.bytes()[..6], b"ABCDef");
+    }
+
+    #[test]
+    fn device_file() {
+        let file = DeviceFile::new(Rc::new(RamDisk::from_bytes(4, b"abcdefghij".to_vec())));
+
+        assert_eq!(file.size(), 12);
+        assert_eq!(file.read_at(2, 5), Ok(("cdefg".to_string(), 5)));
+
+        file.write_at(3, "XYZ").unwrap();
+        assert_eq!(file.read_at(0, 8), Ok(("abcXYZgh".to_string(), 8)));
+        assert_eq!(file.write_at(10, "123"), Err(IOError::NoSpace));
+        assert_eq!(file.read_at(12, 1), Err(IOError::ChannelClosed));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let disk = RamDisk::from_bytes(4, b"\xffabc\xc3".to_vec());
+        let file = DeviceFile::new(Rc::new(disk));
+        assert_eq!(file.read_at(0, 4), Ok(("\u{fffd}".to_string(), 1)));
+
+        // nothing after a replaced byte is skipped
+        let file = FileDescriptor::new(Rc::new(file), OpenFlags::READ, "/dev/ram0");
+        assert_eq!(file.read_sync(4), Ok("\u{fffd}".to_string()));
+        assert_eq!(file.read_sync(3), Ok("abc".to_string()));
+        assert_eq!(file.read_sync(4), Ok("\u{fffd}".to_string()));
+        assert_eq!(file.tell(), 5);
+    }
+}
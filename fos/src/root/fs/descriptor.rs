@@ -1,11 +1,19 @@
-use std::{future::Future, pin::Pin};
+use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc};
 
 use crate::pipe::{PipeReader, PipeWriter};
 
 #[derive(Debug, PartialEq)]
 pub enum IOError {
     ChannelClosed,
-    Empty
+    Empty,
+    // not opened for reading or writing
+    BadDescriptor,
+    // seeking before the start of a file
+    InvalidOffset,
+    // the storage behind a file failed
+    DeviceError,
+    // no free blocks are left or the file is too large
+    NoSpace,
 }
 
 pub trait ReadableDescriptor {
@@ -13,12 +21,22 @@ pub trait ReadableDescriptor {
     fn read_char(&self) -> Pin<Box<dyn Future<Output = Result<char, IOError>>>>;
     fn read_sync(&self, len: u32) -> Result<String, IOError>;
     fn read_char_sync(&self) -> Result<char, IOError>;
+
+    // What is read from, like the path of a file, shown in `/proc/PID/fd`
+    fn source(&self) -> String {
+        "pipe".to_string()
+    }
 }
 
 pub trait WritableDescriptor {
     fn write(&self, str: &str) -> Result<(), IOError>;
     fn write_char(&self, char: char) -> Result<(), IOError>;
     fn clone(&self) -> Result<Self, IOError> where Self:Sized;
+
+    // What is written to, like `source` of a reader
+    fn target(&self) -> String {
+        "pipe".to_string()
+    }
 }
 
 pub struct ReadableWritableDescriptor<Reader: ReadableDescriptor, Writer: WritableDescriptor>  {
@@ -51,6 +69,10 @@ impl<R: ReadableDescriptor, W: WritableDescriptor> ReadableDescriptor for Readab
     fn read_char_sync(&self) -> Result<char, IOError> {
         self.reader.read_char_sync()
     }
+
+    fn source(&self) -> String {
+        self.reader.source()
+    }
 }
 
 impl<R: ReadableDescriptor, W: WritableDescriptor> ReadableWritableDescriptor<R, W> {
@@ -68,13 +90,120 @@ impl<R: ReadableDescriptor, W: WritableDescriptor> ReadableWritableDescriptor<R,
 }
 
 impl<R: ReadableDescriptor, W: WritableDescriptor> ReadableWritableDescriptor<R, W> {
-    fn close_read(self) -> W {
+    pub fn close_read(self) -> W {
         self.writer
     }
 
-    fn close_write(self) -> R {
+    pub fn close_write(self) -> R {
         self.reader
     }
 }
 
-pub type ReadableWritablePipe = ReadableWritableDescriptor<PipeReader, PipeWriter>;
\ No newline at end of file
+pub type ReadableWritablePipe = ReadableWritableDescriptor<PipeReader, PipeWriter>;
+
+// Descriptor that can be shared and replaced, used for the standard streams of a process.
+pub struct SharedReader {
+    inner: RefCell<Option<Rc<dyn ReadableDescriptor>>>,
+}
+
+impl SharedReader {
+    pub fn new(reader: Rc<dyn ReadableDescriptor>) -> Self {
+        SharedReader {
+            inner: RefCell::new(Some(reader)),
+        }
+    }
+
+    pub fn share(&self) -> Option<Rc<dyn ReadableDescriptor>> {
+        self.inner.borrow().clone()
+    }
+
+    pub fn replace(&self, reader: Option<Rc<dyn ReadableDescriptor>>) {
+        *self.inner.borrow_mut() = reader;
+    }
+
+    pub fn close(&self) {
+        self.replace(None);
+    }
+}
+
+impl ReadableDescriptor for SharedReader {
+    fn read(&self, len: u32) -> Pin<Box<dyn Future<Output = Result<String, IOError>>>> {
+        match self.inner.borrow().as_ref() {
+            Some(reader) => reader.read(len),
+            None => Box::pin(std::future::ready(Err(IOError::ChannelClosed))),
+        }
+    }
+
+    fn read_char(&self) -> Pin<Box<dyn Future<Output = Result<char, IOError>>>> {
+        match self.inner.borrow().as_ref() {
+            Some(reader) => reader.read_char(),
+            None => Box::pin(std::future::ready(Err(IOError::ChannelClosed))),
+        }
+    }
+
+    fn read_sync(&self, len: u32) -> Result<String, IOError> {
+        match self.inner.borrow().as_ref() {
+            Some(reader) => reader.read_sync(len),
+            None => Err(IOError::ChannelClosed),
+        }
+    }
+
+    fn read_char_sync(&self) -> Result<char, IOError> {
+        match self.inner.borrow().as_ref() {
+            Some(reader) => reader.read_char_sync(),
+            None => Err(IOError::ChannelClosed),
+        }
+    }
+    fn source(&self) -> String {
+        self.inner.borrow().as_ref().map_or_else(String::new, |reader| reader.source())
+    }
+}
+
+pub struct SharedWriter {
+    inner: RefCell<Option<Rc<dyn WritableDescriptor>>>,
+}
+
+impl SharedWriter {
+    pub fn new(writer: Rc<dyn WritableDescriptor>) -> Self {
+        SharedWriter {
+            inner: RefCell::new(Some(writer)),
+        }
+    }
+
+    pub fn share(&self) -> Option<Rc<dyn WritableDescriptor>> {
+        self.inner.borrow().clone()
+    }
+
+    pub fn replace(&self, writer: Option<Rc<dyn WritableDescriptor>>) {
+        *self.inner.borrow_mut() = writer;
+    }
+
+    pub fn close(&self) {
+        self.replace(None);
+    }
+}
+
+impl WritableDescriptor for SharedWriter {
+    fn write(&self, str: &str) -> Result<(), IOError> {
+        match self.inner.borrow().as_ref() {
+            Some(writer) => writer.write(str),
+            None => Err(IOError::ChannelClosed),
+        }
+    }
+
+    fn write_char(&self, char: char) -> Result<(), IOError> {
+        match self.inner.borrow().as_ref() {
+            Some(writer) => writer.write_char(char),
+            None => Err(IOError::ChannelClosed),
+        }
+    }
+
+    fn clone(&self) -> Result<Self, IOError> {
+        Ok(SharedWriter {
+            inner: RefCell::new(self.share()),
+        })
+    }
+    fn target(&self) -> String {
+        self.inner.borrow().as_ref().map_or_else(String::new, |writer| writer.target())
+    }
+}
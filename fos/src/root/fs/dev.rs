@@ -0,0 +1,631 @@
+// The special files in `/dev`, made by the kernel instead of being stored somewhere.
+//
+// A character device is a driver object behind the descriptor traits, every open makes a
+// new one. Block devices attached later, like the ones of `losetup`, are added with `mknod`.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+};
+
+use super::{
+    descriptor::{IOError, ReadableDescriptor, WritableDescriptor},
+    file::FileHandle,
+    memory::{DEVICE_MODE, DIRECTORY_MODE},
+    now, FileSystem, Kind, OpenError, Stat,
+};
+use crate::{platforms::tekenen::Tekenen, Gid, Process, Uid, ROOT};
+
+// A stream of bytes opened by a process, without offsets
+pub trait CharDevice: ReadableDescriptor + WritableDescriptor {}
+
+impl<T: ReadableDescriptor + WritableDescriptor> CharDevice for T {}
+
+type Driver = fn() -> Rc<dyn CharDevice>;
+
+// Character devices by number, the name is the one of their file in `/dev`
+const DRIVERS: [(&str, Driver); 5] = [
+    ("null", || Rc::new(Null)),
+    ("zero", || Rc::new(Zero)),
+    ("random", || Rc::new(Random)),
+    ("tty", || Rc::new(Tty)),
+    ("fb0", || Rc::new(Fb::new(Rc::clone(&ROOT.framebuffer)))),
+];
+
+// Everyone may use the devices, except for drawing on the screen
+const SHARED_MODE: u16 = 0o666;
+
+// The groups of `/etc/group` allowed to use the disks and the screen
+const DISK_GID: Gid = 6;
+const VIDEO_GID: Gid = 44;
+
+pub fn open(number: u32) -> Result<Rc<dyn CharDevice>, OpenError> {
+    DRIVERS
+        .get(number as usize)
+        .map(|(_, driver)| driver())
+        .ok_or(OpenError::DeviceError)
+}
+
+fn ready<T: 'static>(value: T) -> Pin<Box<dyn Future<Output = T>>> {
+    Box::pin(std::future::ready(value))
+}
+
+// Nothing to read, everything written is thrown away
+pub struct Null;
+
+impl ReadableDescriptor for Null {
+    fn read(&self, len: u32) -> Pin<Box<dyn Future<Output = Result<String, IOError>>>> {
+        ready(self.read_sync(len))
+    }
+
+    fn read_char(&self) -> Pin<Box<dyn Future<Output = Result<char, IOError>>>> {
+        ready(self.read_char_sync())
+    }
+
+    fn read_sync(&self, _len: u32) -> Result<String, IOError> {
+        Err(IOError::ChannelClosed)
+    }
+
+    fn read_char_sync(&self) -> Result<char, IOError> {
+        Err(IOError::ChannelClosed)
+    }
+}
+
+impl WritableDescriptor for Null {
+    fn write(&self, _str: &str) -> Result<(), IOError> {
+        Ok(())
+    }
+
+    fn write_char(&self, _char: char) -> Result<(), IOError> {
+        Ok(())
+    }
+
+    fn clone(&self) -> Result<Self, IOError> {
+        Ok(Null)
+    }
+}
+
+// Endless zeros, writes are thrown away
+pub struct Zero;
+
+impl ReadableDescriptor for Zero {
+    fn read(&self, len: u32) -> Pin<Box<dyn Future<Output = Result<String, IOError>>>> {
+        ready(self.read_sync(len))
+    }
+
+    fn read_char(&self) -> Pin<Box<dyn Future<Output = Result<char, IOError>>>> {
+        ready(self.read_char_sync())
+    }
+
+    fn read_sync(&self, len: u32) -> Result<String, IOError> {
+        Ok("\0".repeat(len as usize))
+    }
+
+    fn read_char_sync(&self) -> Result<char, IOError> {
+        Ok('\0')
+    }
+}
+
+impl WritableDescriptor for Zero {
+    fn write(&self, _str: &str) -> Result<(), IOError> {
+        Ok(())
+    }
+
+    fn write_char(&self, _char: char) -> Result<(), IOError> {
+        Ok(())
+    }
+
+    fn clone(&self) -> Result<Self, IOError> {
+        Ok(Zero)
+    }
+}
+
+thread_local! {
+    // Shared by every open `/dev/random`, seeded by the clock on the first read
+    static SEED: Cell<u64> = const { Cell::new(0) };
+}
+
+// Pseudo random ASCII bytes from a xorshift generator, not good enough for keys
+pub struct Random;
+
+impl Random {
+    fn next() -> char {
+        SEED.with(|seed| {
+            let mut state = match seed.get() {
+                0 => now() | 1,
+                state => state,
+            };
+
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            seed.set(state);
+
+            // the top bits are the most random, text only holds single bytes below 128
+            (state >> 57) as u8 as char
+        })
+    }
+}
+
+impl ReadableDescriptor for Random {
+    fn read(&self, len: u32) -> Pin<Box<dyn Future<Output = Result<String, IOError>>>> {
+        ready(self.read_sync(len))
+    }
+
+    fn read_char(&self) -> Pin<Box<dyn Future<Output = Result<char, IOError>>>> {
+        ready(self.read_char_sync())
+    }
+
+    fn read_sync(&self, len: u32) -> Result<String, IOError> {
+        Ok((0..len).map(|_| Random::next()).collect())
+    }
+
+    fn read_char_sync(&self) -> Result<char, IOError> {
+        Ok(Random::next())
+    }
+}
+
+impl WritableDescriptor for Random {
+    // there is no entropy pool to add to
+    fn write(&self, _str: &str) -> Result<(), IOError> {
+        Ok(())
+    }
+
+    fn write_char(&self, _char: char) -> Result<(), IOError> {
+        Ok(())
+    }
+
+    fn clone(&self) -> Result<Self, IOError> {
+        Ok(Random)
+    }
+}
+
+// The terminal of root, even when the standard streams are redirected
+pub struct Tty;
+
+impl ReadableDescriptor for Tty {
+    // waits for the keyboard like the shell does
+    fn read(&self, len: u32) -> Pin<Box<dyn Future<Output = Result<String, IOError>>>> {
+        ROOT.get_proc().stdin.read(len)
+    }
+
+    fn read_char(&self) -> Pin<Box<dyn Future<Output = Result<char, IOError>>>> {
+        ROOT.get_proc().stdin.read_char()
+    }
+
+    fn read_sync(&self, len: u32) -> Result<String, IOError> {
+        ROOT.get_proc().stdin.read_sync(len)
+    }
+
+    fn read_char_sync(&self) -> Result<char, IOError> {
+        ROOT.get_proc().stdin.read_char_sync()
+    }
+}
+
+impl WritableDescriptor for Tty {
+    fn write(&self, str: &str) -> Result<(), IOError> {
+        ROOT.get_proc().stdout.write(str)
+    }
+
+    fn write_char(&self, char: char) -> Result<(), IOError> {
+        ROOT.get_proc().stdout.write_char(char)
+    }
+
+    fn clone(&self) -> Result<Self, IOError> {
+        Ok(Tty)
+    }
+}
+
+// The pixels of the screen as RGBA bytes, row by row.
+// What is written to `/dev/fb0` is drawn over the terminal, transparent pixels show it.
+pub struct Framebuffer {
+    overlay: RefCell<Vec<u8>>,
+    // the last frame that was shown
+    screen: RefCell<Vec<u8>>,
+    // nothing to draw until something is written
+    drawn: Cell<bool>,
+}
+
+impl Framebuffer {
+    pub fn new() -> Self {
+        Framebuffer {
+            overlay: RefCell::new(vec![]),
+            screen: RefCell::new(vec![]),
+            drawn: Cell::new(false),
+        }
+    }
+
+    // Draws the overlay over a frame and keeps the result for reading
+    pub fn compose(&self, tekenen: &mut Tekenen) {
+        let pixels = tekenen.get_pixels_mut();
+        let mut overlay = self.overlay.borrow_mut();
+
+        // the size follows the screen
+        overlay.resize(pixels.len(), 0);
+
+        if self.drawn.get() {
+            for (pixel, drawn) in pixels.chunks_exact_mut(4).zip(overlay.chunks_exact(4)) {
+                if drawn[3] != 0 {
+                    pixel.copy_from_slice(drawn);
+                }
+            }
+        }
+
+        self.screen.borrow_mut().clone_from(pixels);
+    }
+
+    fn read(&self, offset: usize, len: usize) -> Result<String, IOError> {
+        let screen = self.screen.borrow();
+        let bytes = screen.get(offset..).ok_or(IOError::ChannelClosed)?;
+
+        if bytes.is_empty() {
+            return Err(IOError::ChannelClosed);
+        }
+
+        Ok(bytes[..len.min(bytes.len())]
+            .iter()
+            .map(|&byte| byte as char)
+            .collect())
+    }
+
+    fn write(&self, offset: usize, bytes: &[u8]) -> Result<(), IOError> {
+        let mut overlay = self.overlay.borrow_mut();
+        let end = offset + bytes.len();
+
+        if end > overlay.len() {
+            return Err(IOError::NoSpace);
+        }
+
+        overlay[offset..end].copy_from_slice(bytes);
+        self.drawn.set(true);
+
+        Ok(())
+    }
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// An open `/dev/fb0`, every byte is one char from `\0` to `\u{ff}`.
+// Clones share the position like a duplicated file.
+pub struct Fb {
+    framebuffer: Rc<Framebuffer>,
+    position: Rc<Cell<usize>>,
+}
+
+impl Fb {
+    pub fn new(framebuffer: Rc<Framebuffer>) -> Self {
+        Fb {
+            framebuffer,
+            position: Rc::new(Cell::new(0)),
+        }
+    }
+}
+
+impl ReadableDescriptor for Fb {
+    fn read(&self, len: u32) -> Pin<Box<dyn Future<Output = Result<String, IOError>>>> {
+        ready(self.read_sync(len))
+    }
+
+    fn read_char(&self) -> Pin<Box<dyn Future<Output = Result<char, IOError>>>> {
+        ready(self.read_char_sync())
+    }
+
+    fn read_sync(&self, len: u32) -> Result<String, IOError> {
+        let data = self.framebuffer.read(self.position.get(), len as usize)?;
+
+        self.position
+            .set(self.position.get() + data.chars().count());
+        Ok(data)
+    }
+
+    fn read_char_sync(&self) -> Result<char, IOError> {
+        let data = self.read_sync(1)?;
+
+        data.chars().next().ok_or(IOError::ChannelClosed)
+    }
+}
+
+impl WritableDescriptor for Fb {
+    // chars after `\u{ff}` don't fit in a byte and become 255
+    fn write(&self, str: &str) -> Result<(), IOError> {
+        let bytes: Vec<u8> = str
+            .chars()
+            .map(|char| u8::try_from(char).unwrap_or(u8::MAX))
+            .collect();
+
+        self.framebuffer.write(self.position.get(), &bytes)?;
+
+        self.position.set(self.position.get() + bytes.len());
+        Ok(())
+    }
+
+    fn write_char(&self, char: char) -> Result<(), IOError> {
+        self.write(char.encode_utf8(&mut [0; 4]))
+    }
+
+    fn clone(&self) -> Result<Self, IOError> {
+        Ok(Fb {
+            framebuffer: Rc::clone(&self.framebuffer),
+            position: Rc::clone(&self.position),
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    ino: u64,
+    kind: Kind,
+    number: u32,
+    mode: u16,
+    uid: Uid,
+    gid: Gid,
+    created: u64,
+}
+
+// The directory of the special files, mounted at `/dev`
+pub struct DevFs {
+    nodes: RefCell<BTreeMap<String, Node>>,
+    next_ino: Cell<u64>,
+    created: u64,
+}
+
+impl DevFs {
+    // With the character devices of the drivers
+    pub fn new() -> Self {
+        let fs = DevFs {
+            nodes: RefCell::new(BTreeMap::new()),
+            next_ino: Cell::new(2),
+            created: now(),
+        };
+
+        for (number, (name, _)) in DRIVERS.iter().enumerate() {
+            let (mode, gid) = if *name == "fb0" {
+                (DEVICE_MODE, VIDEO_GID)
+            } else {
+                (SHARED_MODE, 0)
+            };
+
+            fs.add(name, Kind::CharDevice, number as u32, mode, gid);
+        }
+
+        fs
+    }
+
+    fn add(&self, name: &str, kind: Kind, number: u32, mode: u16, gid: Gid) {
+        let ino = self.next_ino.get();
+        self.next_ino.set(ino + 1);
+
+        let node = Node {
+            ino,
+            kind,
+            number,
+            mode,
+            uid: 0,
+            gid,
+            created: now(),
+        };
+
+        self.nodes.borrow_mut().insert(name.to_string(), node);
+    }
+
+    // The name of the file at `path`, there are no subdirectories
+    fn name(&self, path: &str) -> Result<Option<String>, OpenError> {
+        let names: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+
+        match names[..] {
+            [] => Ok(None),
+            [name] => Ok(Some(name.to_string())),
+            [name, ..] if self.nodes.borrow().contains_key(name) => Err(OpenError::NotADirectory),
+            _ => Err(OpenError::NoEntry),
+        }
+    }
+
+    // The directory itself can't be changed
+    fn change(&self, path: &str, change: impl FnOnce(&mut Node)) -> Result<(), OpenError> {
+        let name = self.name(path)?.ok_or(OpenError::NotSupported)?;
+        let mut nodes = self.nodes.borrow_mut();
+
+        change(nodes.get_mut(&name).ok_or(OpenError::NoEntry)?);
+        Ok(())
+    }
+
+    fn node_stat(node: &Node) -> Stat {
+        Stat {
+            ino: node.ino,
+            kind: node.kind,
+            size: 0,
+            mode: node.mode,
+            uid: node.uid,
+            gid: node.gid,
+            links: 1,
+            created: node.created,
+            modified: node.created,
+            accessed: node.created,
+            device: node.number,
+        }
+    }
+}
+
+impl Default for DevFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem for DevFs {
+    fn name(&self) -> &str {
+        "devfs"
+    }
+
+    fn stat(&self, path: &str) -> Result<Stat, OpenError> {
+        let Some(name) = self.name(path)? else {
+            return Ok(Stat {
+                ino: 1,
+                kind: Kind::Directory,
+                size: self.nodes.borrow().len(),
+                mode: DIRECTORY_MODE,
+                uid: 0,
+                gid: 0,
+                links: 2,
+                created: self.created,
+                modified: self.created,
+                accessed: self.created,
+                device: 0,
+            });
+        };
+
+        let nodes = self.nodes.borrow();
+        let node = nodes.get(&name).ok_or(OpenError::NoEntry)?;
+
+        Ok(DevFs::node_stat(node))
+    }
+
+    // the VFS opens devices through their driver
+    fn open(&self, path: &str) -> Result<Rc<dyn FileHandle>, OpenError> {
+        match self.stat(path)?.kind {
+            Kind::Directory => Err(OpenError::IsDirectory),
+            _ => Err(OpenError::NotSupported),
+        }
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<(String, Stat)>, OpenError> {
+        if self.name(path)?.is_some() {
+            self.stat(path)?;
+            return Err(OpenError::NotADirectory);
+        }
+
+        Ok(self
+            .nodes
+            .borrow()
+            .iter()
+            .map(|(name, node)| (name.clone(), DevFs::node_stat(node)))
+            .collect())
+    }
+
+    fn create(&self, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::NotSupported)
+    }
+
+    fn mkdir(&self, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::NotSupported)
+    }
+
+    fn symlink(&self, _target: &str, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::NotSupported)
+    }
+
+    fn unlink(&self, path: &str) -> Result<(), OpenError> {
+        let name = self.name(path)?.ok_or(OpenError::IsDirectory)?;
+
+        self.nodes
+            .borrow_mut()
+            .remove(&name)
+            .map(|_| ())
+            .ok_or(OpenError::NoEntry)
+    }
+
+    fn mknod(&self, path: &str, kind: Kind, number: u32) -> Result<(), OpenError> {
+        let name = self.name(path)?.ok_or(OpenError::AlreadyExists)?;
+
+        if !matches!(kind, Kind::BlockDevice | Kind::CharDevice) {
+            return Err(OpenError::NotSupported);
+        }
+
+        if self.nodes.borrow().contains_key(&name) {
+            return Err(OpenError::AlreadyExists);
+        }
+
+        let gid = if kind == Kind::BlockDevice {
+            DISK_GID
+        } else {
+            0
+        };
+
+        self.add(&name, kind, number, DEVICE_MODE, gid);
+        Ok(())
+    }
+
+    fn chmod(&self, path: &str, mode: u16) -> Result<(), OpenError> {
+        self.change(path, |node| node.mode = mode)
+    }
+
+    fn chown(&self, path: &str, uid: Uid, gid: Gid) -> Result<(), OpenError> {
+        self.change(path, |node| {
+            node.uid = uid;
+            node.gid = gid;
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn drivers() {
+        assert_eq!(Null.read_sync(10), Err(IOError::ChannelClosed));
+        assert_eq!(Null.write("gone"), Ok(()));
+        assert_eq!(Zero.read_sync(3), Ok("\0\0\0".to_string()));
+
+        let random = Random.read_sync(64).unwrap();
+        assert_eq!(random.len(), 64);
+        assert!(random.is_ascii());
+        assert_ne!(random, Random.read_sync(64).unwrap());
+    }
+
+    #[test]
+    fn framebuffer() {
+        let framebuffer = Rc::new(Framebuffer::new());
+        let mut tekenen = Tekenen::new(2, 2);
+        framebuffer.compose(&mut tekenen);
+
+        let fb = Fb::new(Rc::clone(&framebuffer));
+        fb.write("\u{ff}\0\0\u{ff}").unwrap();
+        assert_eq!(fb.write(&"x".repeat(16)), Err(IOError::NoSpace));
+
+        // only the pixel that was drawn covers the terminal
+        framebuffer.compose(&mut tekenen);
+        assert_eq!(&tekenen.get_pixels()[..8], &[255, 0, 0, 255, 0, 0, 0, 0]);
+
+        let other = Fb::new(framebuffer);
+        assert_eq!(other.read_sync(5), Ok("\u{ff}\0\0\u{ff}\0".to_string()));
+        assert_eq!(other.read_sync(100).map(|data| data.len()), Ok(11));
+        assert_eq!(other.read_sync(1), Err(IOError::ChannelClosed));
+    }
+
+    #[test]
+    fn nodes() {
+        let fs = DevFs::new();
+
+        let names: Vec<String> = fs
+            .readdir("/")
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["fb0", "null", "random", "tty", "zero"]);
+        assert_eq!(fs.stat("/tty").map(|stat| stat.device), Ok(3));
+        assert_eq!(fs.stat("/null/x").err(), Some(OpenError::NotADirectory));
+
+        fs.mknod("/loop0", Kind::BlockDevice, 1).unwrap();
+        assert_eq!(
+            fs.mknod("/loop0", Kind::BlockDevice, 2),
+            Err(OpenError::AlreadyExists)
+        );
+        assert_eq!(
+            fs.stat("/loop0").map(|stat| stat.kind),
+            Ok(Kind::BlockDevice)
+        );
+        assert_eq!(fs.mkdir("/dir"), Err(OpenError::NotSupported));
+
+        fs.unlink("/loop0").unwrap();
+        assert_eq!(fs.stat("/loop0").err(), Some(OpenError::NoEntry));
+    }
+}
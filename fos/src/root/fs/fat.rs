@@ -0,0 +1,1618 @@
+// FAT16 and FAT32 volumes like those of memory cards and USB sticks, with long file names.
+//
+// All numbers are little endian. The boot sector describes the layout of the volume:
+// reserved sectors, the copies of the file allocation table (FAT), the root directory on
+// FAT16 and the clusters of data. The FAT has an entry for every cluster: 0 when it's free,
+// the next cluster of the same file or a marker at the end of the chain.
+// Entries are 16 bits on FAT16 and 28 on FAT32, FAT12 volumes are too small to be worth it.
+//
+// A directory is a list of 32 bytes entries: a 8.3 name in upper case, attributes, times,
+// the first cluster and the size. A long name is kept in entries before the short one,
+// 13 UTF-16 units each and the end of the name first. Names are not case sensitive.
+// There are no links, devices or owners, every file belongs to the owner given when mounting.
+
+use std::{cell::Cell, ops::Range, rc::Rc};
+
+use super::{
+    block::{read_bytes, write_bytes, BlockDevice},
+    components,
+    descriptor::IOError,
+    file::{utf8_prefix, FileHandle},
+    now, split_parent, FileSystem, Gid, Kind, OpenError, Stat, Uid,
+};
+
+const ENTRY_SIZE: usize = 32;
+// UTF-16 units of a long name, the first 255 are kept
+const NAME_MAX: usize = 255;
+// where the units of a long name are in an entry
+const LONG_UNITS: [usize; 13] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+const READ_ONLY: u8 = 0x01;
+const VOLUME_ID: u8 = 0x08;
+const DIRECTORY: u8 = 0x10;
+const ARCHIVE: u8 = 0x20;
+const LONG_NAME: u8 = 0x0F;
+
+// first byte of a removed entry, 0 ends the directory
+const FREE: u8 = 0xE5;
+const LAST_LONG: u8 = 0x40;
+// the base or the extension of a short name is shown in lower case
+const LOWER_BASE: u8 = 0x08;
+const LOWER_EXTENSION: u8 = 0x10;
+
+const ROOT_INO: u64 = 1;
+// signature of the FAT32 sector keeping the number of free clusters
+const INFO_SIGNATURE: u32 = 0x4161_5252;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Variant {
+    Fat16,
+    Fat32,
+}
+
+// The layout read from the boot sector, all offsets and sizes are in bytes
+struct Volume {
+    device: Rc<dyn BlockDevice>,
+    variant: Variant,
+    cluster_size: usize,
+    fat_start: usize,
+    fat_size: usize,
+    fats: usize,
+    // the root of FAT16 is before the clusters, FAT32 keeps it in a chain
+    root_start: usize,
+    root_entries: usize,
+    root_cluster: u32,
+    data_start: usize,
+    clusters: u32,
+    info_sector: Option<usize>,
+    // where to look for a free cluster
+    next_free: Cell<u32>,
+    options: FatOptions,
+}
+
+// The owner and the modes of every file, from the mount options `uid=`, `gid=` and `umask=`
+// like on Linux. Files are not executable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FatOptions {
+    pub uid: Uid,
+    pub gid: Gid,
+    pub umask: u16,
+}
+
+impl Default for FatOptions {
+    fn default() -> Self {
+        FatOptions {
+            uid: 0,
+            gid: 0,
+            umask: 0o022,
+        }
+    }
+}
+
+impl FatOptions {
+    pub fn parse(options: &[&str]) -> Result<Self, OpenError> {
+        let mut parsed = FatOptions::default();
+
+        for option in options {
+            let invalid = |_| OpenError::InvalidArgument;
+
+            match option.split_once('=') {
+                Some(("uid", uid)) => parsed.uid = uid.parse().map_err(invalid)?,
+                Some(("gid", gid)) => parsed.gid = gid.parse().map_err(invalid)?,
+                Some(("umask", umask)) => {
+                    parsed.umask = u16::from_str_radix(umask, 8).map_err(invalid)? & 0o777
+                }
+                _ => return Err(OpenError::InvalidArgument),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+// A short entry with its name, directories are named by their first cluster and 0 is the root
+#[derive(Clone, Debug)]
+struct Entry {
+    name: String,
+    raw: [u8; ENTRY_SIZE],
+    offset: usize,
+    // the long name entries and the short one
+    slots: Vec<usize>,
+}
+
+impl Entry {
+    fn is_directory(&self) -> bool {
+        self.raw[11] & DIRECTORY != 0
+    }
+
+    fn cluster(&self) -> u32 {
+        cluster_of(&self.raw)
+    }
+}
+
+impl Volume {
+    fn load(device: Rc<dyn BlockDevice>) -> Result<Self, OpenError> {
+        let mut boot = [0; 512];
+
+        if device.block_size() * device.block_count() < boot.len() {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        read_bytes(device.as_ref(), 0, &mut boot).map_err(|_| OpenError::DeviceError)?;
+
+        if boot[510..] != [0x55, 0xAA] {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let sector_size = u16_at(&boot, 11) as usize;
+        let sectors_per_cluster = boot[13] as usize;
+        let reserved = u16_at(&boot, 14) as usize;
+        let fats = boot[16] as usize;
+        let root_entries = u16_at(&boot, 17) as usize;
+
+        let sectors = match u16_at(&boot, 19) {
+            0 => u32_at(&boot, 32) as usize,
+            sectors => sectors as usize,
+        };
+        let fat_sectors = match u16_at(&boot, 22) {
+            0 => u32_at(&boot, 36) as usize,
+            fat_sectors => fat_sectors as usize,
+        };
+
+        if ![512, 1024, 2048, 4096].contains(&sector_size)
+            || !sectors_per_cluster.is_power_of_two()
+            || reserved == 0
+            || fats == 0
+            || fat_sectors == 0
+        {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let root_sectors = (root_entries * ENTRY_SIZE).div_ceil(sector_size);
+        let data_sector = reserved + fats * fat_sectors + root_sectors;
+
+        if sectors <= data_sector
+            || sectors * sector_size > device.block_size() * device.block_count()
+        {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        // the kind of FAT only depends on the number of clusters
+        let clusters = (sectors - data_sector) / sectors_per_cluster;
+        let variant = match clusters {
+            0..4085 => return Err(OpenError::NotSupported),
+            4085..65525 => Variant::Fat16,
+            _ => Variant::Fat32,
+        };
+
+        let entry_size = match variant {
+            Variant::Fat16 => 2,
+            Variant::Fat32 => 4,
+        };
+
+        if (clusters + 2) * entry_size > fat_sectors * sector_size {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let (root_cluster, info_sector) = match variant {
+            Variant::Fat16 => (0, None),
+            Variant::Fat32 => match u16_at(&boot, 48) {
+                0 | 0xFFFF => (u32_at(&boot, 44), None),
+                sector => (u32_at(&boot, 44), Some(sector as usize * sector_size)),
+            },
+        };
+
+        let volume = Volume {
+            device,
+            variant,
+            cluster_size: sectors_per_cluster * sector_size,
+            fat_start: reserved * sector_size,
+            fat_size: fat_sectors * sector_size,
+            fats,
+            root_start: (reserved + fats * fat_sectors) * sector_size,
+            root_entries,
+            root_cluster,
+            data_start: data_sector * sector_size,
+            clusters: clusters as u32,
+            info_sector,
+            next_free: Cell::new(2),
+            options: FatOptions::default(),
+        };
+
+        if variant == Variant::Fat32 && !volume.is_cluster(root_cluster) {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        Ok(volume)
+    }
+
+    fn read(&self, offset: usize, buffer: &mut [u8]) -> Result<(), OpenError> {
+        read_bytes(self.device.as_ref(), offset, buffer).map_err(|_| OpenError::DeviceError)
+    }
+
+    fn write(&self, offset: usize, data: &[u8]) -> Result<(), OpenError> {
+        write_bytes(self.device.as_ref(), offset, data).map_err(|_| OpenError::DeviceError)
+    }
+
+    fn is_cluster(&self, cluster: u32) -> bool {
+        (2..self.clusters + 2).contains(&cluster)
+    }
+
+    fn end_of_chain(&self) -> u32 {
+        match self.variant {
+            Variant::Fat16 => 0xFFFF,
+            Variant::Fat32 => 0x0FFF_FFFF,
+        }
+    }
+
+    fn cluster_offset(&self, cluster: u32) -> usize {
+        self.data_start + (cluster - 2) as usize * self.cluster_size
+    }
+
+    // The entry of `cluster` in the FAT
+    fn next(&self, cluster: u32) -> Result<u32, OpenError> {
+        match self.variant {
+            Variant::Fat16 => {
+                let mut entry = [0; 2];
+                self.read(self.fat_start + cluster as usize * 2, &mut entry)?;
+                Ok(u16::from_le_bytes(entry) as u32)
+            }
+            Variant::Fat32 => {
+                let mut entry = [0; 4];
+                self.read(self.fat_start + cluster as usize * 4, &mut entry)?;
+                Ok(u32::from_le_bytes(entry) & 0x0FFF_FFFF)
+            }
+        }
+    }
+
+    // Changes the entry of `cluster` in every copy of the FAT
+    fn set_next(&self, cluster: u32, next: u32) -> Result<(), OpenError> {
+        for copy in 0..self.fats {
+            let start = self.fat_start + copy * self.fat_size;
+
+            match self.variant {
+                Variant::Fat16 => {
+                    self.write(start + cluster as usize * 2, &(next as u16).to_le_bytes())?
+                }
+                Variant::Fat32 => {
+                    // the top 4 bits are reserved
+                    let offset = start + cluster as usize * 4;
+                    let mut entry = [0; 4];
+                    self.read(offset, &mut entry)?;
+
+                    let entry = u32::from_le_bytes(entry) & 0xF000_0000 | next & 0x0FFF_FFFF;
+                    self.write(offset, &entry.to_le_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // The clusters of a file from the first one, a loop is an error
+    fn chain(&self, first: u32) -> Result<Vec<u32>, OpenError> {
+        let mut chain = vec![];
+        let mut cluster = first;
+
+        while self.is_cluster(cluster) {
+            if chain.len() >= self.clusters as usize {
+                return Err(OpenError::DeviceError);
+            }
+
+            chain.push(cluster);
+            cluster = self.next(cluster)?;
+        }
+
+        Ok(chain)
+    }
+
+    // Takes a free cluster filled with zeros and ends a chain with it
+    fn alloc(&self) -> Result<u32, OpenError> {
+        let start = self.next_free.get();
+
+        for index in 0..self.clusters {
+            let cluster = 2 + (start - 2 + index) % self.clusters;
+
+            if self.next(cluster)? == 0 {
+                self.set_next(cluster, self.end_of_chain())?;
+                self.write(self.cluster_offset(cluster), &vec![0; self.cluster_size])?;
+
+                self.next_free.set(2 + (cluster - 1) % self.clusters);
+                self.forget_free_count()?;
+
+                return Ok(cluster);
+            }
+        }
+
+        Err(OpenError::NoSpace)
+    }
+
+    fn free(&self, first: u32) -> Result<(), OpenError> {
+        for cluster in self.chain(first)? {
+            self.set_next(cluster, 0)?;
+        }
+
+        self.forget_free_count()
+    }
+
+    // The count of free clusters of FAT32 is only a hint, it's marked as unknown
+    // instead of being kept up to date
+    fn forget_free_count(&self) -> Result<(), OpenError> {
+        let Some(offset) = self.info_sector else {
+            return Ok(());
+        };
+
+        let mut info = [0; 512];
+        self.read(offset, &mut info)?;
+
+        if u32_at(&info, 0) == INFO_SIGNATURE && u32_at(&info, 488) != u32::MAX {
+            self.write(offset + 488, &[0xFF; 8])?;
+        }
+
+        Ok(())
+    }
+
+    // Grows a chain to hold `size` bytes, the first cluster is set in `raw`
+    fn extend(
+        &self,
+        raw: &mut [u8; ENTRY_SIZE],
+        chain: &mut Vec<u32>,
+        size: usize,
+    ) -> Result<(), OpenError> {
+        while chain.len() * self.cluster_size < size {
+            let cluster = self.alloc()?;
+
+            match chain.last() {
+                Some(&last) => self.set_next(last, cluster)?,
+                None => set_cluster(raw, cluster),
+            }
+
+            chain.push(cluster);
+        }
+
+        Ok(())
+    }
+
+    // Where the bytes `offset..offset + len` of a chain are on the device
+    fn pieces(
+        &self,
+        chain: &[u32],
+        offset: usize,
+        len: usize,
+    ) -> Result<Vec<(usize, Range<usize>)>, OpenError> {
+        let mut pieces = vec![];
+        let mut position = 0;
+
+        while position < len {
+            let (index, start) = (
+                (offset + position) / self.cluster_size,
+                (offset + position) % self.cluster_size,
+            );
+            let count = (self.cluster_size - start).min(len - position);
+
+            let cluster = *chain.get(index).ok_or(OpenError::DeviceError)?;
+            pieces.push((
+                self.cluster_offset(cluster) + start,
+                position..position + count,
+            ));
+
+            position += count;
+        }
+
+        Ok(pieces)
+    }
+
+    fn read_file(
+        &self,
+        raw: &[u8; ENTRY_SIZE],
+        offset: usize,
+        len: usize,
+    ) -> Result<Vec<u8>, OpenError> {
+        let end = (offset + len).min(u32_at(raw, 28) as usize);
+        let mut data = vec![0; end.saturating_sub(offset)];
+
+        let chain = self.chain(cluster_of(raw))?;
+
+        for (at, range) in self.pieces(&chain, offset, data.len())? {
+            self.read(at, &mut data[range])?;
+        }
+
+        Ok(data)
+    }
+
+    // Writes into a file, `raw` has to be saved even when it fails
+    fn write_file(
+        &self,
+        raw: &mut [u8; ENTRY_SIZE],
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), OpenError> {
+        let size = u32_at(raw, 28) as usize;
+        let end = offset + data.len();
+
+        if end > u32::MAX as usize {
+            return Err(OpenError::NoSpace);
+        }
+
+        let mut chain = self.chain(cluster_of(raw))?;
+        self.extend(raw, &mut chain, end)?;
+
+        // a removed tail may still be on the device
+        if offset > size {
+            let zeros = vec![0; offset - size];
+
+            for (at, range) in self.pieces(&chain, size, zeros.len())? {
+                self.write(at, &zeros[range])?;
+            }
+        }
+
+        for (at, range) in self.pieces(&chain, offset, data.len())? {
+            self.write(at, &data[range])?;
+        }
+
+        put_u32(raw, 28, size.max(end) as u32);
+
+        Ok(())
+    }
+
+    fn resize(&self, raw: &mut [u8; ENTRY_SIZE], size: usize) -> Result<(), OpenError> {
+        let old = u32_at(raw, 28) as usize;
+
+        if size > old {
+            return self.write_file(raw, old, &vec![0; size - old]);
+        }
+
+        let chain = self.chain(cluster_of(raw))?;
+        let keep = size.div_ceil(self.cluster_size);
+
+        if keep < chain.len() {
+            match keep {
+                0 => set_cluster(raw, 0),
+                _ => self.set_next(chain[keep - 1], self.end_of_chain())?,
+            }
+
+            self.free(chain[keep])?;
+        }
+
+        put_u32(raw, 28, size as u32);
+
+        Ok(())
+    }
+
+    // Every slot of a directory with its offset
+    fn slots(&self, directory: u32) -> Result<Vec<(usize, [u8; ENTRY_SIZE])>, OpenError> {
+        let areas = match (directory, self.variant) {
+            (0, Variant::Fat16) => vec![(self.root_start, self.root_entries * ENTRY_SIZE)],
+            _ => {
+                let first = if directory == 0 {
+                    self.root_cluster
+                } else {
+                    directory
+                };
+
+                self.chain(first)?
+                    .into_iter()
+                    .map(|cluster| (self.cluster_offset(cluster), self.cluster_size))
+                    .collect()
+            }
+        };
+
+        let mut slots = vec![];
+
+        for (start, len) in areas {
+            let mut data = vec![0; len];
+            self.read(start, &mut data)?;
+
+            for (index, raw) in data.chunks_exact(ENTRY_SIZE).enumerate() {
+                slots.push((start + index * ENTRY_SIZE, raw.try_into().unwrap()));
+            }
+        }
+
+        Ok(slots)
+    }
+
+    // The entries of a directory without `.` and `..`
+    fn entries(&self, directory: u32) -> Result<Vec<Entry>, OpenError> {
+        let mut entries = vec![];
+        // parts of a long name before its short entry
+        let mut long = vec![];
+
+        for (offset, raw) in self.slots(directory)? {
+            match raw[0] {
+                0 => break,
+                FREE => long.clear(),
+                _ if raw[11] & 0x3F == LONG_NAME => {
+                    if raw[0] & LAST_LONG != 0 {
+                        long.clear();
+                    }
+                    long.push((offset, raw));
+                }
+                _ if raw[11] & VOLUME_ID != 0 => long.clear(),
+                _ => {
+                    // a long name that doesn't match is left over from another system
+                    let (name, mut slots) = match long_name(&long, &raw) {
+                        Some(name) => (name, long.iter().map(|(offset, _)| *offset).collect()),
+                        None => (short_name(&raw), vec![]),
+                    };
+                    long.clear();
+
+                    if name == "." || name == ".." {
+                        continue;
+                    }
+
+                    slots.push(offset);
+                    entries.push(Entry {
+                        name,
+                        raw,
+                        offset,
+                        slots,
+                    });
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn find(&self, directory: u32, name: &str) -> Result<Option<Entry>, OpenError> {
+        let name = name.to_lowercase();
+
+        Ok(self
+            .entries(directory)?
+            .into_iter()
+            .find(|entry| entry.name.to_lowercase() == name))
+    }
+
+    // The entry at `path`, None for the root
+    fn lookup(&self, path: &str) -> Result<Option<Entry>, OpenError> {
+        let mut entry = None;
+
+        for name in components(path) {
+            let directory = directory_of(&entry)?;
+            entry = Some(self.find(directory, &name)?.ok_or(OpenError::NoEntry)?);
+        }
+
+        Ok(entry)
+    }
+
+    // The directory that would hold `path` and the name in it
+    fn parent<'a>(&self, path: &'a str) -> Result<(u32, &'a str), OpenError> {
+        let (parent, name) = split_parent(path.trim_end_matches('/'));
+
+        if name.is_empty() {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        Ok((directory_of(&self.lookup(parent)?)?, name))
+    }
+
+    // Adds an entry named `name` to a directory, `raw` has everything but the name
+    fn insert(
+        &self,
+        directory: u32,
+        name: &str,
+        mut raw: [u8; ENTRY_SIZE],
+    ) -> Result<(), OpenError> {
+        if !is_valid(name) {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let entries = self.entries(directory)?;
+        let lower = name.to_lowercase();
+
+        if entries
+            .iter()
+            .any(|entry| entry.name.to_lowercase() == lower)
+        {
+            return Err(OpenError::AlreadyExists);
+        }
+
+        let taken: Vec<[u8; 11]> = entries
+            .iter()
+            .map(|entry| entry.raw[..11].try_into().unwrap())
+            .collect();
+
+        let long = match fit_short(name) {
+            Some((short, flags)) if !taken.contains(&short) => {
+                raw[..11].copy_from_slice(&short);
+                raw[12] = flags;
+                vec![]
+            }
+            _ => {
+                // names in mixed case keep their short name in upper case
+                let short = match fit_short(&name.to_ascii_uppercase()) {
+                    Some((short, _)) if !taken.contains(&short) => short,
+                    _ => make_short(name, &taken)?,
+                };
+                raw[..11].copy_from_slice(&short);
+                raw[12] = 0;
+                long_entries(name, checksum(&short))
+            }
+        };
+
+        let slots = self.place(directory, long.len() + 1)?;
+
+        for (offset, raw) in slots.into_iter().zip(long.iter().chain([&raw])) {
+            self.write(offset, raw)?;
+        }
+
+        Ok(())
+    }
+
+    // Offsets of `count` free slots in a row, the directory grows when there are none
+    fn place(&self, directory: u32, count: usize) -> Result<Vec<usize>, OpenError> {
+        let mut run = vec![];
+
+        for (offset, raw) in self.slots(directory)? {
+            if raw[0] == 0 || raw[0] == FREE {
+                run.push(offset);
+
+                if run.len() == count {
+                    return Ok(run);
+                }
+            } else {
+                run.clear();
+            }
+        }
+
+        // the root of FAT16 has a fixed size
+        if directory == 0 && self.variant == Variant::Fat16 {
+            return Err(OpenError::NoSpace);
+        }
+
+        let first = if directory == 0 {
+            self.root_cluster
+        } else {
+            directory
+        };
+        let last = *self.chain(first)?.last().ok_or(OpenError::DeviceError)?;
+
+        // the run can go on in the new cluster
+        while run.len() < count {
+            let cluster = self.alloc()?;
+            self.set_next(last, cluster)?;
+
+            let start = self.cluster_offset(cluster);
+            run.extend((0..self.cluster_size / ENTRY_SIZE).map(|index| start + index * ENTRY_SIZE));
+        }
+
+        run.truncate(count);
+
+        Ok(run)
+    }
+
+    // Frees the slots of an entry and returns what they were
+    fn delete(&self, entry: &Entry) -> Result<Vec<(usize, [u8; ENTRY_SIZE])>, OpenError> {
+        let mut saved = vec![];
+
+        for &offset in &entry.slots {
+            let mut raw = [0; ENTRY_SIZE];
+            self.read(offset, &mut raw)?;
+            self.write(offset, &[FREE])?;
+
+            saved.push((offset, raw));
+        }
+
+        Ok(saved)
+    }
+
+    fn remove(&self, entry: &Entry) -> Result<(), OpenError> {
+        self.delete(entry)?;
+        self.free(entry.cluster())
+    }
+
+    fn stat(&self, entry: &Option<Entry>) -> Result<Stat, OpenError> {
+        let Some(entry) = entry else {
+            let entries = self.entries(0)?;
+
+            return Ok(Stat {
+                ino: ROOT_INO,
+                kind: Kind::Directory,
+                size: entries.len(),
+                mode: 0o777 & !self.options.umask,
+                uid: self.options.uid,
+                gid: self.options.gid,
+                links: 2 + entries.iter().filter(|entry| entry.is_directory()).count() as u32,
+                created: 0,
+                modified: 0,
+                accessed: 0,
+                device: 0,
+            });
+        };
+
+        let raw = &entry.raw;
+
+        let (kind, size, mode, links) = if entry.is_directory() {
+            let entries = self.entries(entry.cluster())?;
+            let links = 2 + entries.iter().filter(|entry| entry.is_directory()).count() as u32;
+
+            (Kind::Directory, entries.len(), 0o777, links)
+        } else {
+            (Kind::File, u32_at(raw, 28) as usize, 0o666, 1)
+        };
+
+        let mode = mode & !self.options.umask;
+
+        Ok(Stat {
+            // there are no inodes, where the entry is stored is unique
+            ino: entry.offset as u64,
+            kind,
+            size,
+            mode: if raw[11] & READ_ONLY != 0 {
+                mode & !0o222
+            } else {
+                mode
+            },
+            uid: self.options.uid,
+            gid: self.options.gid,
+            links,
+            created: millis(u16_at(raw, 16), u16_at(raw, 14)) + raw[13] as u64 * 10,
+            modified: millis(u16_at(raw, 24), u16_at(raw, 22)),
+            accessed: millis(u16_at(raw, 18), 0),
+            device: 0,
+        })
+    }
+}
+
+pub struct FatFs {
+    volume: Rc<Volume>,
+}
+
+impl FatFs {
+    pub fn load(device: Rc<dyn BlockDevice>) -> Result<Self, OpenError> {
+        Self::load_with(device, FatOptions::default())
+    }
+
+    pub fn load_with(device: Rc<dyn BlockDevice>, options: FatOptions) -> Result<Self, OpenError> {
+        Ok(FatFs {
+            volume: Rc::new(Volume {
+                options,
+                ..Volume::load(device)?
+            }),
+        })
+    }
+}
+
+impl FileSystem for FatFs {
+    fn name(&self) -> &str {
+        "vfat"
+    }
+
+    fn sync(&self) -> Result<(), OpenError> {
+        self.volume
+            .device
+            .flush()
+            .map_err(|_| OpenError::DeviceError)
+    }
+
+    fn stat(&self, path: &str) -> Result<Stat, OpenError> {
+        self.volume.stat(&self.volume.lookup(path)?)
+    }
+
+    fn open(&self, path: &str) -> Result<Rc<dyn FileHandle>, OpenError> {
+        match self.volume.lookup(path)? {
+            Some(entry) if !entry.is_directory() => Ok(Rc::new(FatFile {
+                volume: Rc::clone(&self.volume),
+                offset: entry.offset,
+            })),
+            _ => Err(OpenError::IsDirectory),
+        }
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<(String, Stat)>, OpenError> {
+        let directory = directory_of(&self.volume.lookup(path)?)?;
+        let mut entries = vec![];
+
+        for entry in self.volume.entries(directory)? {
+            let stat = self.volume.stat(&Some(entry.clone()))?;
+            entries.push((entry.name, stat));
+        }
+
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Ok(entries)
+    }
+
+    fn create(&self, path: &str) -> Result<(), OpenError> {
+        let (directory, name) = self.volume.parent(path)?;
+
+        self.volume.insert(directory, name, new_entry(ARCHIVE))
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), OpenError> {
+        let volume = &self.volume;
+        let (directory, name) = volume.parent(path)?;
+
+        if volume.find(directory, name)?.is_some() {
+            return Err(OpenError::AlreadyExists);
+        }
+
+        let cluster = volume.alloc()?;
+
+        let mut raw = new_entry(DIRECTORY);
+        set_cluster(&mut raw, cluster);
+
+        let mut dot = raw;
+        dot[..11].copy_from_slice(b".          ");
+
+        let mut dot_dot = raw;
+        dot_dot[..11].copy_from_slice(b"..         ");
+        set_cluster(&mut dot_dot, directory);
+
+        let start = volume.cluster_offset(cluster);
+        volume.write(start, &dot)?;
+        volume.write(start + ENTRY_SIZE, &dot_dot)?;
+
+        if let Err(err) = volume.insert(directory, name, raw) {
+            volume.free(cluster)?;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    fn symlink(&self, _target: &str, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::NotSupported)
+    }
+
+    fn link(&self, _existing: &str, _path: &str) -> Result<(), OpenError> {
+        Err(OpenError::NotSupported)
+    }
+
+    fn unlink(&self, path: &str) -> Result<(), OpenError> {
+        match self.volume.lookup(path)? {
+            Some(entry) if !entry.is_directory() => self.volume.remove(&entry),
+            _ => Err(OpenError::IsDirectory),
+        }
+    }
+
+    fn rmdir(&self, path: &str) -> Result<(), OpenError> {
+        let entry = match self.volume.lookup(path)? {
+            Some(entry) if entry.is_directory() => entry,
+            Some(_) => return Err(OpenError::NotADirectory),
+            None => return Err(OpenError::Busy),
+        };
+
+        if !self.volume.entries(entry.cluster())?.is_empty() {
+            return Err(OpenError::NotEmpty);
+        }
+
+        self.volume.remove(&entry)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), OpenError> {
+        let volume = &self.volume;
+
+        let (from_directory, _) = volume.parent(from)?;
+        let (to_directory, to_name) = volume.parent(to)?;
+
+        let entry = volume.lookup(from)?.ok_or(OpenError::Busy)?;
+
+        // a directory can't be moved inside of itself
+        let from = from.trim_end_matches('/');
+        if to.trim_end_matches('/') == from {
+            return Ok(());
+        }
+        if to
+            .to_lowercase()
+            .starts_with(&format!("{}/", from.to_lowercase()))
+        {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        match volume.find(to_directory, to_name)? {
+            None => {}
+            // only the case of the name changes
+            Some(target) if target.offset == entry.offset => {}
+            Some(target) => match (entry.is_directory(), target.is_directory()) {
+                (true, true) if volume.entries(target.cluster())?.is_empty() => {
+                    volume.remove(&target)?
+                }
+                (true, true) => return Err(OpenError::NotEmpty),
+                (true, false) => return Err(OpenError::NotADirectory),
+                (false, true) => return Err(OpenError::IsDirectory),
+                (false, false) => volume.remove(&target)?,
+            },
+        }
+
+        // the old slots can be reused, they are put back when there is no space
+        let saved = volume.delete(&entry)?;
+
+        if let Err(err) = volume.insert(to_directory, to_name, entry.raw) {
+            for (offset, raw) in saved {
+                volume.write(offset, &raw)?;
+            }
+
+            return Err(err);
+        }
+
+        // the `..` of a directory changes parent
+        if entry.is_directory() && from_directory != to_directory {
+            let offset = volume.cluster_offset(entry.cluster()) + ENTRY_SIZE;
+            let mut dot_dot = [0; ENTRY_SIZE];
+            volume.read(offset, &mut dot_dot)?;
+
+            if &dot_dot[..11] == b"..         " {
+                set_cluster(&mut dot_dot, to_directory);
+                volume.write(offset, &dot_dot)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The file is found again by where its entry is stored
+struct FatFile {
+    volume: Rc<Volume>,
+    offset: usize,
+}
+
+impl FatFile {
+    // Fails once the file was removed or moved
+    fn entry(&self) -> Result<[u8; ENTRY_SIZE], IOError> {
+        let mut raw = [0; ENTRY_SIZE];
+        self.volume.read(self.offset, &mut raw).map_err(io_error)?;
+
+        if raw[0] == 0 || raw[0] == FREE || raw[11] & (DIRECTORY | VOLUME_ID) != 0 {
+            return Err(IOError::DeviceError);
+        }
+
+        Ok(raw)
+    }
+
+    fn save(&self, raw: &[u8; ENTRY_SIZE]) -> Result<(), IOError> {
+        self.volume.write(self.offset, raw).map_err(io_error)
+    }
+}
+
+impl FileHandle for FatFile {
+    fn read_at(&self, offset: usize, len: usize) -> Result<(String, usize), IOError> {
+        let mut raw = self.entry()?;
+
+        if offset >= u32_at(&raw, 28) as usize {
+            return Err(IOError::ChannelClosed);
+        }
+
+        // enough for a whole char
+        let data = self
+            .volume
+            .read_file(&raw, offset, len.max(4))
+            .map_err(io_error)?;
+
+        // only the day of the last access is kept
+        let (date, _, _) = fat_time(now());
+        if u16_at(&raw, 18) != date {
+            put_u16(&mut raw, 18, date);
+            self.save(&raw)?;
+        }
+
+        Ok(utf8_prefix(&data, len))
+    }
+
+    fn write_at(&self, offset: usize, data: &str) -> Result<(), IOError> {
+        let mut raw = self.entry()?;
+
+        let result = self.volume.write_file(&mut raw, offset, data.as_bytes());
+        touch(&mut raw);
+        self.save(&raw)?;
+
+        result.map_err(io_error)
+    }
+
+    fn size(&self) -> usize {
+        self.entry().map_or(0, |raw| u32_at(&raw, 28) as usize)
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), IOError> {
+        let mut raw = self.entry()?;
+
+        let result = self.volume.resize(&mut raw, size);
+        touch(&mut raw);
+        self.save(&raw)?;
+
+        result.map_err(io_error)
+    }
+}
+
+fn io_error(err: OpenError) -> IOError {
+    match err {
+        OpenError::NoSpace => IOError::NoSpace,
+        _ => IOError::DeviceError,
+    }
+}
+
+// The cluster of a directory, 0 for the root
+fn directory_of(entry: &Option<Entry>) -> Result<u32, OpenError> {
+    match entry {
+        None => Ok(0),
+        Some(entry) if entry.is_directory() => Ok(entry.cluster()),
+        Some(_) => Err(OpenError::NotADirectory),
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn put_u16(bytes: &mut [u8], offset: usize, value: u16) {
+    bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+// FAT16 only uses the low half
+fn cluster_of(raw: &[u8]) -> u32 {
+    (u16_at(raw, 20) as u32) << 16 | u16_at(raw, 26) as u32
+}
+
+fn set_cluster(raw: &mut [u8], cluster: u32) {
+    put_u16(raw, 20, (cluster >> 16) as u16);
+    put_u16(raw, 26, cluster as u16);
+}
+
+// An entry made now without a name
+fn new_entry(attributes: u8) -> [u8; ENTRY_SIZE] {
+    let mut raw = [0; ENTRY_SIZE];
+    raw[11] = attributes;
+
+    let (date, time, hundredths) = fat_time(now());
+    raw[13] = hundredths;
+    put_u16(&mut raw, 14, time);
+    put_u16(&mut raw, 16, date);
+    touch(&mut raw);
+
+    raw
+}
+
+// Marks a file as changed now
+fn touch(raw: &mut [u8; ENTRY_SIZE]) {
+    let (date, time, _) = fat_time(now());
+
+    raw[11] |= ARCHIVE;
+    put_u16(raw, 18, date);
+    put_u16(raw, 22, time);
+    put_u16(raw, 24, date);
+}
+
+fn checksum(short: &[u8]) -> u8 {
+    short
+        .iter()
+        .fold(0u8, |sum, &byte| sum.rotate_right(1).wrapping_add(byte))
+}
+
+// The long name in the entries before `raw`, if they belong to it
+fn long_name(long: &[(usize, [u8; ENTRY_SIZE])], raw: &[u8; ENTRY_SIZE]) -> Option<String> {
+    let (_, first) = long.first()?;
+
+    if first[0] & LAST_LONG == 0 {
+        return None;
+    }
+
+    let checksum = checksum(&raw[..11]);
+    let mut units = vec![];
+
+    for (index, (_, part)) in long.iter().rev().enumerate() {
+        if (part[0] & 0x3F) as usize != index + 1 || part[13] != checksum {
+            return None;
+        }
+
+        units.extend(LONG_UNITS.iter().map(|&offset| u16_at(part, offset)));
+    }
+
+    // the name ends with a 0 when there is room, followed by 0xFFFF
+    let end = units
+        .iter()
+        .position(|&unit| unit == 0)
+        .unwrap_or(units.len());
+
+    String::from_utf16(&units[..end]).ok()
+}
+
+// The entries of a long name in the order they are stored
+fn long_entries(name: &str, checksum: u8) -> Vec<[u8; ENTRY_SIZE]> {
+    let mut units: Vec<u16> = name.encode_utf16().collect();
+    let count = units.len().div_ceil(LONG_UNITS.len());
+
+    if units.len() < count * LONG_UNITS.len() {
+        units.push(0);
+        units.resize(count * LONG_UNITS.len(), 0xFFFF);
+    }
+
+    (0..count)
+        .rev()
+        .map(|index| {
+            let mut raw = [0; ENTRY_SIZE];
+
+            raw[0] = (index as u8 + 1) | if index + 1 == count { LAST_LONG } else { 0 };
+            raw[11] = LONG_NAME;
+            raw[13] = checksum;
+
+            let part = &units[index * LONG_UNITS.len()..][..LONG_UNITS.len()];
+            for (&offset, &unit) in LONG_UNITS.iter().zip(part) {
+                put_u16(&mut raw, offset, unit);
+            }
+
+            raw
+        })
+        .collect()
+}
+
+fn short_name(raw: &[u8; ENTRY_SIZE]) -> String {
+    let mut base = raw[..8].to_vec();
+
+    // a name starting with 0xE5 would look removed
+    if base[0] == 0x05 {
+        base[0] = FREE;
+    }
+
+    let part = |bytes: &[u8], lower: bool| {
+        let part: String = bytes.iter().map(|&byte| byte as char).collect();
+        let part = part.trim_end();
+
+        match lower {
+            true => part.to_lowercase(),
+            false => part.to_string(),
+        }
+    };
+
+    let base = part(&base, raw[12] & LOWER_BASE != 0);
+    let extension = part(&raw[8..11], raw[12] & LOWER_EXTENSION != 0);
+
+    match extension.is_empty() {
+        true => base,
+        false => format!("{base}.{extension}"),
+    }
+}
+
+fn is_valid(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name.encode_utf16().count() <= NAME_MAX
+        && !name
+            .chars()
+            .any(|char| char < ' ' || "\"*/:<>?\\|".contains(char))
+}
+
+fn is_short_char(char: char) -> bool {
+    char.is_ascii_alphanumeric() || "$%'-_@~`!(){}^#&".contains(char)
+}
+
+fn pack_short(base: &str, extension: &str) -> [u8; 11] {
+    let mut short = [b' '; 11];
+
+    short[..base.len()].copy_from_slice(base.as_bytes());
+    short[8..8 + extension.len()].copy_from_slice(extension.as_bytes());
+
+    short
+}
+
+// The 8.3 name of `name` with its case flags, if it needs no long name
+fn fit_short(name: &str) -> Option<([u8; 11], u8)> {
+    let (base, extension) = match name.rsplit_once('.') {
+        Some((base, extension)) if !extension.is_empty() => (base, extension),
+        Some(_) => return None,
+        None => (name, ""),
+    };
+
+    if base.is_empty()
+        || base.len() > 8
+        || extension.len() > 3
+        || !base.chars().chain(extension.chars()).all(is_short_char)
+    {
+        return None;
+    }
+
+    // a part in lower case is only a flag, mixed case needs a long name
+    let case = |part: &str, flag: u8| match (
+        part.chars().any(|char| char.is_ascii_lowercase()),
+        part.chars().any(|char| char.is_ascii_uppercase()),
+    ) {
+        (true, true) => None,
+        (true, false) => Some(flag),
+        (false, _) => Some(0),
+    };
+
+    let flags = case(base, LOWER_BASE)? | case(extension, LOWER_EXTENSION)?;
+
+    Some((
+        pack_short(&base.to_ascii_uppercase(), &extension.to_ascii_uppercase()),
+        flags,
+    ))
+}
+
+// A new 8.3 name like `LONGNA~1.TXT` for a name that needs a long one
+fn make_short(name: &str, taken: &[[u8; 11]]) -> Result<[u8; 11], OpenError> {
+    let clean = |part: &str| -> String {
+        part.chars()
+            .filter(|&char| char != ' ' && char != '.')
+            .map(|char| match is_short_char(char) {
+                true => char.to_ascii_uppercase(),
+                false => '_',
+            })
+            .collect()
+    };
+
+    let (base, extension) = match name.rsplit_once('.') {
+        Some((base, extension)) if !base.is_empty() => (clean(base), clean(extension)),
+        _ => (clean(name), String::new()),
+    };
+
+    let extension: String = extension.chars().take(3).collect();
+
+    for number in 1..1_000_000 {
+        let tail = format!("~{number}");
+        let base: String = base.chars().take(8 - tail.len()).collect();
+
+        let short = pack_short(&format!("{base}{tail}"), &extension);
+
+        if !taken.contains(&short) {
+            return Ok(short);
+        }
+    }
+
+    Err(OpenError::NoSpace)
+}
+
+// Times are local on FAT, fos has no time zones so they are taken as UTC
+fn millis(date: u16, time: u16) -> u64 {
+    if date == 0 {
+        return 0;
+    }
+
+    let (year, month, day) = (
+        1980 + (date >> 9) as i64,
+        ((date >> 5) & 0xF) as i64,
+        (date & 0x1F) as i64,
+    );
+    let seconds =
+        (time >> 11) as u64 * 3600 + ((time >> 5) & 0x3F) as u64 * 60 + (time & 0x1F) as u64 * 2;
+
+    days_from_civil(year, month, day).max(0) as u64 * 86_400_000 + seconds * 1000
+}
+
+// The date, time and hundredths of a second of FAT, clamped to the years it can store
+fn fat_time(millis: u64) -> (u16, u16, u8) {
+    let (year, month, day) = civil_from_days((millis / 86_400_000) as i64);
+
+    if year < 1980 {
+        return (1 << 5 | 1, 0, 0);
+    }
+    if year > 2107 {
+        return (127 << 9 | 12 << 5 | 31, 23 << 11 | 59 << 5 | 29, 199);
+    }
+
+    let millis = millis % 86_400_000;
+    let seconds = millis / 1000;
+
+    let date = ((year - 1980) as u16) << 9 | (month as u16) << 5 | day as u16;
+    let time = ((seconds / 3600) as u16) << 11
+        | ((seconds / 60 % 60) as u16) << 5
+        | (seconds % 60 / 2) as u16;
+    let hundredths = (millis % 2000 / 10) as u8;
+
+    (date, time, hundredths)
+}
+
+// Days since the unix epoch of a date of the gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+
+    (year_of_era + era * 400 + (month <= 2) as i64, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{block::RamDisk, set_clock};
+
+    // An empty volume laid out like `mkfs.vfat` does, with a sector for every cluster
+    fn volume(block_size: usize, sectors: usize, fat32: bool) -> Rc<RamDisk> {
+        let disk = RamDisk::new(block_size, sectors * 512 / block_size);
+
+        let reserved = if fat32 { 32 } else { 4 };
+        let fat_sectors = (sectors * if fat32 { 4 } else { 2 }).div_ceil(512);
+
+        let mut boot = [0; 512];
+        boot[..11].copy_from_slice(b"\xEB\x3C\x90mkfs.fat");
+        put_u16(&mut boot, 11, 512);
+        boot[13] = 1;
+        put_u16(&mut boot, 14, reserved as u16);
+        boot[16] = 2;
+        boot[21] = 0xF8;
+        boot[510..].copy_from_slice(&[0x55, 0xAA]);
+
+        let fat: &[u8] = if fat32 {
+            put_u32(&mut boot, 32, sectors as u32);
+            put_u32(&mut boot, 36, fat_sectors as u32);
+            put_u32(&mut boot, 44, 2);
+            put_u16(&mut boot, 48, 1);
+
+            let mut info = [0; 512];
+            put_u32(&mut info, 0, INFO_SIGNATURE);
+            put_u32(&mut info, 484, 0x6141_7272);
+            put_u32(&mut info, 488, 1000);
+            put_u32(&mut info, 508, 0xAA55_0000);
+            write_bytes(&disk, 512, &info).unwrap();
+
+            // the root is in cluster 2
+            b"\xF8\xFF\xFF\x0F\xFF\xFF\xFF\x0F\xFF\xFF\xFF\x0F"
+        } else {
+            put_u16(&mut boot, 17, 512);
+            put_u16(&mut boot, 19, sectors as u16);
+            put_u16(&mut boot, 22, fat_sectors as u16);
+
+            b"\xF8\xFF\xFF\xFF"
+        };
+
+        write_bytes(&disk, 0, &boot).unwrap();
+
+        for copy in 0..2 {
+            write_bytes(&disk, (reserved + copy * fat_sectors) * 512, fat).unwrap();
+        }
+
+        Rc::new(disk)
+    }
+
+    fn listing(fs: &FatFs, path: &str) -> Vec<String> {
+        fs.readdir(path)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn fat16() {
+        // 2024-02-29 12:34:56.780
+        set_clock(|| 1_709_210_096_780);
+
+        let disk = volume(512, 8192, false);
+        let fs = FatFs::load(Rc::clone(&disk) as Rc<dyn BlockDevice>).unwrap();
+        assert_eq!(fs.volume.variant, Variant::Fat16);
+
+        fs.create("/readme.txt").unwrap();
+        fs.mkdir("/Long Directory Name").unwrap();
+        fs.create("/Long Directory Name/Ünïcode file.md").unwrap();
+
+        assert_eq!(fs.create("/README.TXT"), Err(OpenError::AlreadyExists));
+        assert_eq!(fs.create("/a:b"), Err(OpenError::InvalidArgument));
+        assert_eq!(
+            fs.symlink("/readme.txt", "/link"),
+            Err(OpenError::NotSupported)
+        );
+
+        let file = fs.open("/ReadMe.Txt").unwrap();
+        file.write_at(0, &"fat ".repeat(300)).unwrap();
+        assert_eq!(file.size(), 1200);
+        assert_eq!(file.read_at(1196, 100), Ok(("fat ".to_string(), 4)));
+
+        // a hole reads as zeros
+        file.truncate(10).unwrap();
+        file.write_at(20, "end").unwrap();
+        assert_eq!(
+            file.read_at(8, 100),
+            Ok(("fa\0\0\0\0\0\0\0\0\0\0end".to_string(), 15))
+        );
+
+        let stat = fs.stat("/readme.txt").unwrap();
+        assert_eq!(
+            (stat.kind, stat.size, stat.modified),
+            (Kind::File, 23, 1_709_210_096_000)
+        );
+        assert_eq!(stat.created, 1_709_210_096_780);
+        assert_eq!(fs.stat("/").map(|stat| stat.links), Ok(3));
+
+        assert_eq!(listing(&fs, "/"), ["Long Directory Name", "readme.txt"]);
+        assert_eq!(listing(&fs, "/long directory name"), ["Ünïcode file.md"]);
+
+        // short names in lower case need no long name
+        fs.create("/Makefile").unwrap();
+        let mut root = [0; 6 * ENTRY_SIZE];
+        read_bytes(disk.as_ref(), fs.volume.root_start, &mut root).unwrap();
+        assert_eq!(&root[..11], b"README  TXT");
+        assert_eq!(root[12], LOWER_BASE | LOWER_EXTENSION);
+        assert_eq!((root[32], root[32 + 11]), (0x42, LONG_NAME));
+        assert_eq!(&root[96..107], b"LONGDI~1   ");
+        assert_eq!((root[128], &root[160..171]), (0x41, &b"MAKEFILE   "[..]));
+        fs.unlink("/makefile").unwrap();
+
+        assert_eq!(fs.rmdir("/long directory name"), Err(OpenError::NotEmpty));
+        assert_eq!(
+            fs.unlink("/Long Directory Name"),
+            Err(OpenError::IsDirectory)
+        );
+        fs.unlink("/Long Directory Name/Ünïcode file.md").unwrap();
+        fs.rmdir("/Long Directory Name").unwrap();
+        fs.unlink("/readme.txt").unwrap();
+
+        assert!(listing(&fs, "/").is_empty());
+        assert_eq!(file.read_at(0, 1), Err(IOError::DeviceError));
+
+        // every cluster is free again in both copies
+        let volume = &fs.volume;
+        let mut fats = vec![0; volume.fat_size * 2];
+        read_bytes(disk.as_ref(), volume.fat_start, &mut fats).unwrap();
+        assert_eq!(fats[..volume.fat_size], fats[volume.fat_size..]);
+        assert!((2..volume.clusters + 2).all(|cluster| volume.next(cluster) == Ok(0)));
+    }
+
+    #[test]
+    fn fat32() {
+        set_clock(|| 0);
+
+        let disk = volume(1024, 70_000, true);
+        let fs = FatFs::load(Rc::clone(&disk) as Rc<dyn BlockDevice>).unwrap();
+        assert_eq!(fs.volume.variant, Variant::Fat32);
+
+        // the root grows past its first cluster
+        for index in 0..20 {
+            fs.create(&format!("/file with a long name {index}"))
+                .unwrap();
+        }
+        assert_eq!(fs.stat("/").map(|stat| stat.size), Ok(20));
+        assert!(fs.volume.chain(fs.volume.root_cluster).unwrap().len() > 1);
+
+        // the count of free clusters isn't kept
+        let mut info = [0; 4];
+        read_bytes(disk.as_ref(), 512 + 488, &mut info).unwrap();
+        assert_eq!(info, [0xFF; 4]);
+
+        // a file in many clusters
+        let file = fs.open("/file with a long name 3").unwrap();
+        let data: String = (0..5000)
+            .map(|index| char::from(b'a' + (index % 26) as u8))
+            .collect();
+        file.write_at(0, &data).unwrap();
+        assert_eq!(
+            file.read_at(1000, 30),
+            Ok((data[1000..1030].to_string(), 30))
+        );
+
+        file.truncate(600).unwrap();
+        let cluster = cluster_of(
+            &fs.volume
+                .lookup("/file with a long name 3")
+                .unwrap()
+                .unwrap()
+                .raw,
+        );
+        assert_eq!(fs.volume.chain(cluster).map(|chain| chain.len()), Ok(2));
+
+        // moving a directory changes its `..`
+        fs.mkdir("/a").unwrap();
+        fs.mkdir("/b").unwrap();
+        fs.rename("/file with a long name 3", "/a/moved").unwrap();
+        fs.rename("/a", "/b/A").unwrap();
+
+        assert_eq!(fs.rename("/b", "/b/A/c"), Err(OpenError::InvalidArgument));
+        assert_eq!(
+            fs.rename("/b/a", "/file with a long name 4"),
+            Err(OpenError::NotADirectory)
+        );
+        assert_eq!(
+            fs.rename("/file with a long name 4", "/b"),
+            Err(OpenError::IsDirectory)
+        );
+        assert_eq!(fs.stat("/b/a/moved").map(|stat| stat.size), Ok(600));
+
+        let moved = fs.volume.lookup("/b/a").unwrap().unwrap();
+        let b = fs.volume.lookup("/b").unwrap().unwrap();
+        let mut dot_dot = [0; ENTRY_SIZE];
+        read_bytes(
+            disk.as_ref(),
+            fs.volume.cluster_offset(moved.cluster()) + ENTRY_SIZE,
+            &mut dot_dot,
+        )
+        .unwrap();
+        assert_eq!(cluster_of(&dot_dot), b.cluster());
+
+        // only the case changes
+        fs.rename("/b/A", "/b/a").unwrap();
+        assert_eq!(listing(&fs, "/b"), ["a"]);
+    }
+
+    #[test]
+    fn options() {
+        set_clock(|| 0);
+
+        assert_eq!(
+            FatOptions::parse(&["uid=1000", "gid=100", "umask=027"]),
+            Ok(FatOptions {
+                uid: 1000,
+                gid: 100,
+                umask: 0o027
+            })
+        );
+        assert_eq!(
+            FatOptions::parse(&["uid=fritz"]),
+            Err(OpenError::InvalidArgument)
+        );
+        assert_eq!(
+            FatOptions::parse(&["umask=9"]),
+            Err(OpenError::InvalidArgument)
+        );
+        assert_eq!(
+            FatOptions::parse(&["noexec"]),
+            Err(OpenError::InvalidArgument)
+        );
+
+        let disk = volume(512, 8192, false);
+        let options = FatOptions::parse(&["uid=1000", "umask=027"]).unwrap();
+        let fs = FatFs::load_with(disk as Rc<dyn BlockDevice>, options).unwrap();
+
+        fs.create("/notes.txt").unwrap();
+        fs.mkdir("/docs").unwrap();
+
+        let owner = |path| {
+            let stat = fs.stat(path).unwrap();
+            (stat.uid, stat.gid, stat.mode)
+        };
+
+        assert_eq!(owner("/"), (1000, 0, 0o750));
+        assert_eq!(owner("/docs"), (1000, 0, 0o750));
+        assert_eq!(owner("/notes.txt"), (1000, 0, 0o640));
+    }
+
+    #[test]
+    fn names() {
+        let taken = [pack_short("LONGNA~1", "TXT")];
+
+        assert_eq!(
+            fit_short("hello.c"),
+            Some((*b"HELLO   C  ", LOWER_BASE | LOWER_EXTENSION))
+        );
+        assert_eq!(
+            fit_short("README.md"),
+            Some((*b"README  MD ", LOWER_EXTENSION))
+        );
+        assert_eq!(fit_short("Makefile"), None);
+        assert_eq!(fit_short("a.tar.gz"), None);
+        assert_eq!(make_short("long name.txt", &taken), Ok(*b"LONGNA~2TXT"));
+        assert_eq!(make_short(".bashrc", &[]), Ok(*b"BASHRC~1   "));
+        assert_eq!(make_short("é+x.tar.gz", &[]), Ok(*b"__XTAR~1GZ "));
+
+        // a name of exactly 13 units has no end
+        let long = long_entries("thirteen char", checksum(b"THIRTE~1   "));
+        assert_eq!(long.len(), 1);
+
+        let mut short = [0; ENTRY_SIZE];
+        short[..11].copy_from_slice(b"THIRTE~1   ");
+        let slots: Vec<_> = long.into_iter().map(|raw| (0, raw)).collect();
+        assert_eq!(long_name(&slots, &short), Some("thirteen char".to_string()));
+
+        // left over from a renamed short entry
+        short[..11].copy_from_slice(b"OTHER      ");
+        assert_eq!(long_name(&slots, &short), None);
+        assert_eq!(short_name(&short), "OTHER");
+
+        assert_eq!(fat_time(0), (0x21, 0, 0));
+        let (date, time, _) = fat_time(1_709_210_096_780);
+        assert_eq!(millis(date, time), 1_709_210_096_000);
+    }
+}
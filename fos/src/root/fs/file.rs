@@ -0,0 +1,337 @@
+use std::{cell::Cell, future::Future, io::SeekFrom, ops::BitOr, pin::Pin, rc::Rc};
+
+use super::{
+    descriptor::{IOError, ReadableDescriptor, WritableDescriptor},
+    dev::CharDevice,
+    pipe::FifoEnd,
+    watch::{Event, Watchers},
+};
+
+// How a file is opened, combined with `|`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OpenFlags(u8);
+
+impl OpenFlags {
+    pub const READ: OpenFlags = OpenFlags(1);
+    pub const WRITE: OpenFlags = OpenFlags(1 << 1);
+    // every write goes to the end of the file
+    pub const APPEND: OpenFlags = OpenFlags(1 << 2);
+    // create the file if it doesn't exist
+    pub const CREATE: OpenFlags = OpenFlags(1 << 3);
+    // empty the file when opening it
+    pub const TRUNCATE: OpenFlags = OpenFlags(1 << 4);
+
+    pub fn contains(self, flags: OpenFlags) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+}
+
+impl BitOr for OpenFlags {
+    type Output = OpenFlags;
+
+    fn bitor(self, rhs: OpenFlags) -> OpenFlags {
+        OpenFlags(self.0 | rhs.0)
+    }
+}
+
+// The open file of a filesystem, read and written at byte offsets
+pub trait FileHandle {
+    // At most `len` bytes as text and how many bytes of the file it was made of,
+    // `ChannelClosed` at the end of the file
+    fn read_at(&self, offset: usize, len: usize) -> Result<(String, usize), IOError>;
+    // Overwrites from `offset`, the gap after the end of the file is filled with zeros
+    fn write_at(&self, offset: usize, data: &str) -> Result<(), IOError>;
+    fn size(&self) -> usize;
+    fn truncate(&self, size: usize) -> Result<(), IOError>;
+}
+
+// At most `len` bytes as text but at least one char, for files stored as bytes, and how
+// many of the bytes it took. A char split at the end is left for the next read, an invalid
+// byte is replaced by U+FFFD on its own.
+pub fn utf8_prefix(bytes: &[u8], len: usize) -> (String, usize) {
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        Err(err) => err.valid_up_to(),
+    };
+
+    if valid == 0 {
+        let taken = bytes.len().min(1);
+        return (String::from_utf8_lossy(&bytes[..taken]).into_owned(), taken);
+    }
+
+    let text = std::str::from_utf8(&bytes[..valid]).unwrap_or_default();
+    let mut end = len.min(valid);
+
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    if end == 0 {
+        end = text.chars().next().map_or(0, char::len_utf8);
+    }
+
+    (text[..end].to_string(), end)
+}
+
+// What an open file reads and writes
+#[derive(Clone)]
+enum Target {
+    File(Rc<dyn FileHandle>),
+    // a device, read as a stream without offsets
+    Stream(Rc<dyn CharDevice>),
+    // a stream too, which has to wait for its other end
+    Fifo(Rc<FifoEnd>),
+}
+
+// An open file with an offset in bytes.
+// Clones share the offset, like a duplicated descriptor.
+pub struct FileDescriptor {
+    target: Target,
+    offset: Rc<Cell<usize>>,
+    flags: OpenFlags,
+    // as it was opened, after resolving links
+    path: String,
+    // told when the file is written
+    watchers: Option<Rc<Watchers>>,
+}
+
+impl FileDescriptor {
+    pub fn new(handle: Rc<dyn FileHandle>, flags: OpenFlags, path: &str) -> Self {
+        FileDescriptor {
+            target: Target::File(handle),
+            offset: Rc::new(Cell::new(0)),
+            flags,
+            path: path.to_string(),
+            watchers: None,
+        }
+    }
+
+    pub fn stream(stream: Rc<dyn CharDevice>, flags: OpenFlags, path: &str) -> Self {
+        FileDescriptor {
+            target: Target::Stream(stream),
+            offset: Rc::new(Cell::new(0)),
+            flags,
+            path: path.to_string(),
+            watchers: None,
+        }
+    }
+
+    // Tells the watches of the VFS about every write
+    pub fn watched(mut self, watchers: Rc<Watchers>) -> Self {
+        self.watchers = Some(watchers);
+        self
+    }
+
+    pub fn fifo(end: FifoEnd, flags: OpenFlags, path: &str) -> Self {
+        FileDescriptor {
+            target: Target::Fifo(Rc::new(end)),
+            offset: Rc::new(Cell::new(0)),
+            flags,
+            path: path.to_string(),
+            watchers: None,
+        }
+    }
+
+    // Waits until the other end of a FIFO is open, anything else is open already
+    pub fn opened(&self) -> Pin<Box<dyn Future<Output = ()>>> {
+        match &self.target {
+            Target::Fifo(end) => end.opened(),
+            _ => Box::pin(std::future::ready(())),
+        }
+    }
+
+    // Streams can't seek or be read at an offset
+    fn handle(&self) -> Result<&Rc<dyn FileHandle>, IOError> {
+        match &self.target {
+            Target::File(handle) => Ok(handle),
+            Target::Stream(_) | Target::Fifo(_) => Err(IOError::InvalidOffset),
+        }
+    }
+
+    // What devices and FIFOs are read and written with
+    fn as_stream(&self) -> Option<Rc<dyn CharDevice>> {
+        match &self.target {
+            Target::File(_) => None,
+            Target::Stream(stream) => Some(Rc::clone(stream)),
+            Target::Fifo(end) => Some(Rc::clone(end) as Rc<dyn CharDevice>),
+        }
+    }
+
+    fn check(&self, flags: OpenFlags) -> Result<(), IOError> {
+        if !self.flags.contains(flags) {
+            return Err(IOError::BadDescriptor);
+        }
+
+        Ok(())
+    }
+
+    pub fn tell(&self) -> usize {
+        self.offset.get()
+    }
+
+    // Moves the offset, which can go past the end of the file
+    pub fn seek(&self, position: SeekFrom) -> Result<usize, IOError> {
+        let size = self.handle()?.size();
+
+        let (base, delta) = match position {
+            SeekFrom::Start(offset) => (0, offset as i64),
+            SeekFrom::Current(delta) => (self.offset.get(), delta),
+            SeekFrom::End(delta) => (size, delta),
+        };
+
+        let offset = usize::try_from(base as i64 + delta).map_err(|_| IOError::InvalidOffset)?;
+
+        self.offset.set(offset);
+        Ok(offset)
+    }
+
+    // Reads up to `len` bytes at `offset` without moving the offset
+    pub fn pread(&self, offset: usize, len: u32) -> Result<String, IOError> {
+        self.check(OpenFlags::READ)?;
+
+        Ok(self.handle()?.read_at(offset, len as usize)?.0)
+    }
+
+    // Writes at `offset` without moving the offset, overwriting what is there
+    pub fn pwrite(&self, offset: usize, str: &str) -> Result<(), IOError> {
+        self.check(OpenFlags::WRITE)?;
+        self.handle()?.write_at(offset, str)?;
+
+        if let Some(watchers) = &self.watchers {
+            watchers.notify(Event::Modified(self.path.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+impl ReadableDescriptor for FileDescriptor {
+    fn read(&self, len: u32) -> Pin<Box<dyn Future<Output = Result<String, IOError>>>> {
+        match self.as_stream() {
+            // a stream like the terminal may have to wait
+            Some(stream) if self.flags.contains(OpenFlags::READ) => stream.read(len),
+            // a file never has to wait for data
+            _ => Box::pin(std::future::ready(self.read_sync(len))),
+        }
+    }
+
+    fn read_char(&self) -> Pin<Box<dyn Future<Output = Result<char, IOError>>>> {
+        match self.as_stream() {
+            Some(stream) if self.flags.contains(OpenFlags::READ) => stream.read_char(),
+            _ => Box::pin(std::future::ready(self.read_char_sync())),
+        }
+    }
+
+    fn read_sync(&self, len: u32) -> Result<String, IOError> {
+        if let Some(stream) = self.as_stream() {
+            self.check(OpenFlags::READ)?;
+            return stream.read_sync(len);
+        }
+
+        self.check(OpenFlags::READ)?;
+
+        // invalid bytes are replaced, the text may be longer than what was read
+        let (data, taken) = self.handle()?.read_at(self.offset.get(), len as usize)?;
+
+        self.offset.set(self.offset.get() + taken);
+        Ok(data)
+    }
+
+    fn read_char_sync(&self) -> Result<char, IOError> {
+        let data = self.read_sync(1)?;
+
+        data.chars().next().ok_or(IOError::ChannelClosed)
+    }
+
+    fn source(&self) -> String {
+        self.path.clone()
+    }
+}
+
+impl WritableDescriptor for FileDescriptor {
+    fn write(&self, str: &str) -> Result<(), IOError> {
+        if let Some(stream) = self.as_stream() {
+            self.check(OpenFlags::WRITE)?;
+            return stream.write(str);
+        }
+
+        if self.flags.contains(OpenFlags::APPEND) {
+            self.offset.set(self.handle()?.size());
+        }
+
+        self.pwrite(self.offset.get(), str)?;
+
+        self.offset.set(self.offset.get() + str.len());
+        Ok(())
+    }
+
+    fn write_char(&self, char: char) -> Result<(), IOError> {
+        self.write(char.encode_utf8(&mut [0; 4]))
+    }
+
+    fn clone(&self) -> Result<Self, IOError> {
+        Ok(FileDescriptor {
+            target: self.target.clone(),
+            offset: Rc::clone(&self.offset),
+            flags: self.flags,
+            path: self.path.clone(),
+            watchers: self.watchers.clone(),
+        })
+    }
+
+    fn target(&self) -> String {
+        self.path.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{File, Metadata};
+
+    fn open(content: &str, flags: OpenFlags) -> (Rc<File>, FileDescriptor) {
+        let file = File::new(content, Metadata::new(1, 0o644, 1, 0));
+
+        (Rc::clone(&file), FileDescriptor::new(file, flags, "/file"))
+    }
+
+    #[test]
+    fn read_and_seek() {
+        let (_, file) = open("hello world", OpenFlags::READ);
+
+        assert_eq!(file.read_sync(5), Ok("hello".to_string()));
+        assert_eq!(file.tell(), 5);
+        assert_eq!(file.seek(SeekFrom::Current(1)), Ok(6));
+        assert_eq!(file.read_sync(100), Ok("world".to_string()));
+        assert_eq!(file.read_sync(100), Err(IOError::ChannelClosed));
+
+        assert_eq!(file.seek(SeekFrom::End(-5)), Ok(6));
+        assert_eq!(file.read_char_sync(), Ok('w'));
+        assert_eq!(file.pread(0, 4), Ok("hell".to_string()));
+        assert_eq!(file.tell(), 7);
+        assert_eq!(
+            file.seek(SeekFrom::Current(-8)),
+            Err(IOError::InvalidOffset)
+        );
+        assert_eq!(file.write("x"), Err(IOError::BadDescriptor));
+    }
+
+    #[test]
+    fn write_at_offsets() {
+        let (content, file) = open("hello world", OpenFlags::READ | OpenFlags::WRITE);
+
+        file.seek(SeekFrom::Start(6)).unwrap();
+        file.write("there").unwrap();
+        assert_eq!(*content.content.borrow(), "hello there");
+        assert_eq!(file.tell(), 11);
+
+        let shared = WritableDescriptor::clone(&file).unwrap();
+        shared.write_char('?').unwrap();
+        assert_eq!(file.tell(), 12);
+
+        let (content, append) = open("a", OpenFlags::WRITE | OpenFlags::APPEND);
+        append.seek(SeekFrom::Start(0)).unwrap();
+        append.write("b").unwrap();
+        assert_eq!(*content.content.borrow(), "ab");
+    }
+}
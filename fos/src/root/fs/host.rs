@@ -0,0 +1,328 @@
+use std::{
+    fs::{self, Metadata, OpenOptions},
+    io::ErrorKind,
+    os::unix::fs::{FileExt, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    descriptor::IOError,
+    file::{utf8_prefix, FileHandle},
+    FileSystem, Kind, OpenError, Stat,
+};
+
+// A directory of the host seen through fos, changes go straight to the host.
+// Read only mounts are handled by the VFS.
+pub struct HostFs {
+    root: PathBuf,
+}
+
+impl HostFs {
+    pub fn new(root: &str) -> Result<Self, OpenError> {
+        let root = PathBuf::from(root);
+
+        if !fs::metadata(&root).map_err(error)?.is_dir() {
+            return Err(OpenError::NotADirectory);
+        }
+
+        Ok(HostFs { root })
+    }
+
+    // The host path of a path inside of the mount, it never contains `..`
+    fn host(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+}
+
+impl FileSystem for HostFs {
+    fn name(&self) -> &str {
+        "hostfs"
+    }
+
+    fn stat(&self, path: &str) -> Result<Stat, OpenError> {
+        let path = self.host(path);
+        let meta = fs::symlink_metadata(&path).map_err(error)?;
+
+        Ok(stat(&path, &meta))
+    }
+
+    fn open(&self, path: &str) -> Result<Rc<dyn FileHandle>, OpenError> {
+        let path = self.host(path);
+
+        if fs::symlink_metadata(&path).map_err(error)?.is_dir() {
+            return Err(OpenError::IsDirectory);
+        }
+
+        // files the host doesn't let us write can still be read
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .or_else(|_| fs::File::open(&path))
+            .map_err(error)?;
+
+        Ok(Rc::new(HostFile { file }))
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<(String, Stat)>, OpenError> {
+        let mut entries = vec![];
+
+        for entry in fs::read_dir(self.host(path)).map_err(error)? {
+            let entry = entry.map_err(error)?;
+            let meta = fs::symlink_metadata(entry.path()).map_err(error)?;
+
+            let name = entry.file_name().to_string_lossy().into_owned();
+            entries.push((name, stat(&entry.path(), &meta)));
+        }
+
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Ok(entries)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, OpenError> {
+        let path = self.host(path);
+
+        if !fs::symlink_metadata(&path).map_err(error)?.is_symlink() {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let target = fs::read_link(path).map_err(error)?;
+
+        Ok(target.to_string_lossy().into_owned())
+    }
+
+    fn create(&self, path: &str) -> Result<(), OpenError> {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.host(path))
+            .map_err(error)?;
+
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), OpenError> {
+        fs::create_dir(self.host(path)).map_err(error)
+    }
+
+    fn symlink(&self, target: &str, path: &str) -> Result<(), OpenError> {
+        std::os::unix::fs::symlink(target, self.host(path)).map_err(error)
+    }
+
+    fn link(&self, existing: &str, path: &str) -> Result<(), OpenError> {
+        fs::hard_link(self.host(existing), self.host(path)).map_err(error)
+    }
+
+    fn unlink(&self, path: &str) -> Result<(), OpenError> {
+        let path = self.host(path);
+
+        if fs::symlink_metadata(&path).map_err(error)?.is_dir() {
+            return Err(OpenError::IsDirectory);
+        }
+
+        fs::remove_file(path).map_err(error)
+    }
+
+    fn rmdir(&self, path: &str) -> Result<(), OpenError> {
+        let path = self.host(path);
+
+        if !fs::symlink_metadata(&path).map_err(error)?.is_dir() {
+            return Err(OpenError::NotADirectory);
+        }
+
+        fs::remove_dir(path).map_err(error)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), OpenError> {
+        fs::rename(self.host(from), self.host(to)).map_err(error)
+    }
+
+    // Owners can't be changed, only root of the host could
+    fn chmod(&self, path: &str, mode: u16) -> Result<(), OpenError> {
+        fs::set_permissions(self.host(path), fs::Permissions::from_mode(mode as u32))
+            .map_err(error)
+    }
+}
+
+// Offsets are in bytes like on the host, invalid UTF-8 is replaced
+struct HostFile {
+    file: fs::File,
+}
+
+impl FileHandle for HostFile {
+    fn read_at(&self, offset: usize, len: usize) -> Result<(String, usize), IOError> {
+        // enough for a whole char
+        let mut buffer = vec![0; len.max(4)];
+
+        let read = self
+            .file
+            .read_at(&mut buffer, offset as u64)
+            .map_err(|_| IOError::DeviceError)?;
+
+        if read == 0 {
+            return Err(IOError::ChannelClosed);
+        }
+
+        buffer.truncate(read);
+
+        Ok(utf8_prefix(&buffer, len))
+    }
+
+    fn write_at(&self, offset: usize, data: &str) -> Result<(), IOError> {
+        self.file
+            .write_all_at(data.as_bytes(), offset as u64)
+            .map_err(|_| IOError::DeviceError)
+    }
+
+    fn size(&self) -> usize {
+        self.file.metadata().map_or(0, |meta| meta.len() as usize)
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), IOError> {
+        self.file
+            .set_len(size as u64)
+            .map_err(|_| IOError::DeviceError)
+    }
+}
+
+fn stat(path: &Path, meta: &Metadata) -> Stat {
+    let kind = if meta.is_symlink() {
+        Kind::Symlink
+    } else if meta.is_dir() {
+        Kind::Directory
+    } else {
+        Kind::File
+    };
+
+    let size = match kind {
+        Kind::Directory => fs::read_dir(path).map_or(0, |entries| entries.count()),
+        _ => meta.len() as usize,
+    };
+
+    let modified = millis(meta.modified());
+
+    Stat {
+        ino: meta.ino(),
+        kind,
+        size,
+        mode: (meta.mode() & 0o7777) as u16,
+        // the users of the host, which may not be the ones of fos
+        uid: meta.uid(),
+        gid: meta.gid(),
+        links: meta.nlink() as u32,
+        // not every host knows when a file was created
+        created: meta.created().map_or(modified, |time| millis(Ok(time))),
+        modified,
+        accessed: millis(meta.accessed()),
+        // devices of the host are not devices of fos
+        device: 0,
+    }
+}
+
+fn millis(time: std::io::Result<SystemTime>) -> u64 {
+    time.ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |time| time.as_millis() as u64)
+}
+
+pub fn error(err: std::io::Error) -> OpenError {
+    match err.kind() {
+        ErrorKind::NotFound => OpenError::NoEntry,
+        ErrorKind::AlreadyExists => OpenError::AlreadyExists,
+        ErrorKind::NotADirectory => OpenError::NotADirectory,
+        ErrorKind::IsADirectory => OpenError::IsDirectory,
+        ErrorKind::DirectoryNotEmpty => OpenError::NotEmpty,
+        ErrorKind::ResourceBusy => OpenError::Busy,
+        ErrorKind::CrossesDevices => OpenError::CrossDevice,
+        ErrorKind::PermissionDenied => OpenError::PermissionDenied,
+        ErrorKind::ReadOnlyFilesystem => OpenError::ReadOnly,
+        _ => OpenError::InvalidArgument,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A new empty directory on the host, removed at the end of the test
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("fos-{name}-{}", std::process::id()));
+
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir(&path).unwrap();
+
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn passthrough() {
+        let dir = TempDir::new("passthrough");
+        fs::write(dir.0.join("script"), "echo hi\n").unwrap();
+
+        let host = HostFs::new(dir.0.to_str().unwrap()).unwrap();
+
+        assert_eq!(host.stat("/").map(|stat| stat.kind), Ok(Kind::Directory));
+        assert_eq!(host.stat("/script").map(|stat| stat.size), Ok(8));
+        assert_eq!(host.stat("/missing"), Err(OpenError::NoEntry));
+
+        let file = host.open("/script").unwrap();
+        assert_eq!(file.read_at(5, 100), Ok(("hi\n".to_string(), 3)));
+
+        host.mkdir("/dir").unwrap();
+        host.create("/dir/new").unwrap();
+        host.open("/dir/new")
+            .unwrap()
+            .write_at(0, "written")
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.0.join("dir/new")).unwrap(),
+            "written"
+        );
+
+        host.symlink("../script", "/dir/link").unwrap();
+        assert_eq!(host.readlink("/dir/link"), Ok("../script".to_string()));
+
+        let names: Vec<String> = host
+            .readdir("/dir")
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["link", "new"]);
+
+        assert_eq!(host.rmdir("/dir"), Err(OpenError::NotEmpty));
+        host.rename("/dir/new", "/moved").unwrap();
+        host.unlink("/dir/link").unwrap();
+        host.rmdir("/dir").unwrap();
+        assert!(dir.0.join("moved").exists());
+    }
+
+    #[test]
+    fn split_chars() {
+        let dir = TempDir::new("split_chars");
+        fs::write(dir.0.join("file"), "aé").unwrap();
+
+        let file = HostFs::new(dir.0.to_str().unwrap())
+            .unwrap()
+            .open("/file")
+            .unwrap();
+
+        assert_eq!(file.read_at(0, 2), Ok(("a".to_string(), 1)));
+        assert_eq!(file.read_at(1, 2), Ok(("é".to_string(), 2)));
+        assert_eq!(file.read_at(1, 1), Ok(("é".to_string(), 2)));
+        assert_eq!(file.read_at(3, 2), Err(IOError::ChannelClosed));
+    }
+}
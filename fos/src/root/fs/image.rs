@@ -0,0 +1,1308 @@
+// A filesystem stored in blocks of a `BlockDevice`, so it can be saved and loaded as an image.
+//
+// All numbers are little endian. The image is made of `BLOCK_SIZE` blocks:
+//
+//   block 0        superblock: `MAGIC`, version, block size, block count, inode count
+//                  and the first block of the bitmap, the inode table and the data, all u32
+//   bitmap         one bit for every block of the image, set when the block is used
+//   inode table    `INODE_SIZE` bytes for every inode, inode 0 is unused and 1 is the root
+//   data           contents of files, targets of links and entries of directories
+//
+// An inode is its kind (u8, 0 when free), one unused byte, mode (u16), links (u32),
+// size in bytes (u32), created, modified and accessed time (u64 milliseconds),
+// `DIRECT` block numbers, the number of a block with `POINTERS` more block numbers,
+// owner and group (u32), and unused bytes up to `INODE_SIZE`.
+// Block 0 stands for a block that was never written and reads as zeros.
+//
+// A directory is a list of `ENTRY_SIZE` entries: inode (u32, 0 when free),
+// length of the name (u8) and the name. Removed entries are reused.
+
+use std::{collections::BTreeMap, rc::Rc};
+
+use super::{
+    block::BlockDevice,
+    components,
+    descriptor::IOError,
+    file::{utf8_prefix, FileHandle},
+    memory::{DIRECTORY_MODE, FILE_MODE, SYMLINK_MODE},
+    now, split_parent, FileSystem, Gid, Kind, OpenError, Stat, Uid,
+};
+
+pub const BLOCK_SIZE: usize = 1024;
+
+const MAGIC: &[u8; 8] = b"FOSIMAGE";
+// 2 added owners to the inodes
+const VERSION: u32 = 2;
+
+const INODE_SIZE: usize = 128;
+const DIRECT: usize = 6;
+const POINTERS: usize = BLOCK_SIZE / 4;
+// about 262 KiB
+pub const MAX_FILE_SIZE: usize = (DIRECT + POINTERS) * BLOCK_SIZE;
+
+const ENTRY_SIZE: usize = 64;
+pub const NAME_MAX: usize = ENTRY_SIZE - 5;
+
+const ROOT_INO: u32 = 1;
+
+// Where the parts of the image start, derived from the number of blocks and inodes
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Superblock {
+    blocks: u32,
+    inodes: u32,
+    bitmap: u32,
+    inode_table: u32,
+    data: u32,
+}
+
+impl Superblock {
+    fn new(blocks: u32, inodes: u32) -> Self {
+        let bitmap_blocks = blocks.div_ceil(BLOCK_SIZE as u32 * 8);
+        let inode_blocks = (inodes as usize * INODE_SIZE).div_ceil(BLOCK_SIZE) as u32;
+
+        Superblock {
+            blocks,
+            inodes,
+            bitmap: 1,
+            inode_table: 1 + bitmap_blocks,
+            data: 1 + bitmap_blocks + inode_blocks,
+        }
+    }
+
+    fn decode(block: &[u8]) -> Result<Self, String> {
+        if &block[0..8] != MAGIC {
+            return Err("not a fos image".to_string());
+        }
+
+        if u32_at(block, 8) != VERSION {
+            return Err(format!("unknown version {}", u32_at(block, 8)));
+        }
+
+        if u32_at(block, 12) as usize != BLOCK_SIZE {
+            return Err(format!("unsupported block size {}", u32_at(block, 12)));
+        }
+
+        let superblock = Superblock {
+            blocks: u32_at(block, 16),
+            inodes: u32_at(block, 20),
+            bitmap: u32_at(block, 24),
+            inode_table: u32_at(block, 28),
+            data: u32_at(block, 32),
+        };
+
+        if superblock != Superblock::new(superblock.blocks, superblock.inodes) {
+            return Err("the layout doesn't match the block and inode count".to_string());
+        }
+
+        Ok(superblock)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut block = vec![0; BLOCK_SIZE];
+
+        block[0..8].copy_from_slice(MAGIC);
+        put_u32(&mut block, 8, VERSION);
+        put_u32(&mut block, 12, BLOCK_SIZE as u32);
+        put_u32(&mut block, 16, self.blocks);
+        put_u32(&mut block, 20, self.inodes);
+        put_u32(&mut block, 24, self.bitmap);
+        put_u32(&mut block, 28, self.inode_table);
+        put_u32(&mut block, 32, self.data);
+
+        block
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Inode {
+    // `None` when the inode is free
+    kind: Option<Kind>,
+    mode: u16,
+    links: u32,
+    size: usize,
+    created: u64,
+    modified: u64,
+    accessed: u64,
+    direct: [u32; DIRECT],
+    indirect: u32,
+    uid: Uid,
+    gid: Gid,
+}
+
+impl Inode {
+    fn decode(bytes: &[u8]) -> Self {
+        let kind = match bytes[0] {
+            1 => Some(Kind::File),
+            2 => Some(Kind::Directory),
+            3 => Some(Kind::Symlink),
+            _ => None,
+        };
+
+        Inode {
+            kind,
+            mode: u16::from_le_bytes([bytes[2], bytes[3]]),
+            links: u32_at(bytes, 4),
+            size: u32_at(bytes, 8) as usize,
+            created: u64_at(bytes, 12),
+            modified: u64_at(bytes, 20),
+            accessed: u64_at(bytes, 28),
+            direct: std::array::from_fn(|index| u32_at(bytes, 36 + index * 4)),
+            indirect: u32_at(bytes, 60),
+            uid: u32_at(bytes, 64),
+            gid: u32_at(bytes, 68),
+        }
+    }
+
+    fn encode(&self, bytes: &mut [u8]) {
+        // devices can't be stored
+        bytes[0] = match self.kind {
+            Some(Kind::File) => 1,
+            Some(Kind::Directory) => 2,
+            Some(Kind::Symlink) => 3,
+            Some(Kind::BlockDevice | Kind::CharDevice | Kind::Fifo | Kind::Socket) | None => 0,
+        };
+
+        bytes[1] = 0;
+        bytes[2..4].copy_from_slice(&self.mode.to_le_bytes());
+        put_u32(bytes, 4, self.links);
+        put_u32(bytes, 8, self.size as u32);
+        put_u64(bytes, 12, self.created);
+        put_u64(bytes, 20, self.modified);
+        put_u64(bytes, 28, self.accessed);
+
+        for (index, block) in self.direct.iter().enumerate() {
+            put_u32(bytes, 36 + index * 4, *block);
+        }
+
+        put_u32(bytes, 60, self.indirect);
+        put_u32(bytes, 64, self.uid);
+        put_u32(bytes, 68, self.gid);
+        bytes[72..].fill(0);
+    }
+}
+
+// The image and the device it's stored on, shared with the open files
+struct Image {
+    device: Rc<dyn BlockDevice>,
+    superblock: Superblock,
+}
+
+impl Image {
+    fn read(&self, block: u32) -> Result<Vec<u8>, OpenError> {
+        let mut buffer = vec![0; BLOCK_SIZE];
+
+        self.device
+            .read_block(block as usize, &mut buffer)
+            .map_err(|_| OpenError::DeviceError)?;
+
+        Ok(buffer)
+    }
+
+    fn write(&self, block: u32, data: &[u8]) -> Result<(), OpenError> {
+        self.device
+            .write_block(block as usize, data)
+            .map_err(|_| OpenError::DeviceError)
+    }
+
+    // The block of the inode table with inode `ino` and where it starts in it
+    fn inode_position(&self, ino: u32) -> Result<(u32, usize), OpenError> {
+        if ino == 0 || ino >= self.superblock.inodes {
+            return Err(OpenError::DeviceError);
+        }
+
+        let offset = ino as usize * INODE_SIZE;
+
+        Ok((
+            self.superblock.inode_table + (offset / BLOCK_SIZE) as u32,
+            offset % BLOCK_SIZE,
+        ))
+    }
+
+    fn inode(&self, ino: u32) -> Result<Inode, OpenError> {
+        let (block, offset) = self.inode_position(ino)?;
+
+        Ok(Inode::decode(
+            &self.read(block)?[offset..offset + INODE_SIZE],
+        ))
+    }
+
+    fn set_inode(&self, ino: u32, inode: &Inode) -> Result<(), OpenError> {
+        let (block, offset) = self.inode_position(ino)?;
+        let mut data = self.read(block)?;
+
+        inode.encode(&mut data[offset..offset + INODE_SIZE]);
+        self.write(block, &data)
+    }
+
+    // Changes an inode in place
+    fn update(&self, ino: u32, change: impl FnOnce(&mut Inode)) -> Result<(), OpenError> {
+        let mut inode = self.inode(ino)?;
+        change(&mut inode);
+
+        self.set_inode(ino, &inode)
+    }
+
+    fn set_used(&self, block: u32, used: bool) -> Result<(), OpenError> {
+        let bit = block as usize;
+        let bitmap = self.superblock.bitmap + (bit / 8 / BLOCK_SIZE) as u32;
+
+        let mut data = self.read(bitmap)?;
+        let byte = bit / 8 % BLOCK_SIZE;
+
+        if used {
+            data[byte] |= 1 << (bit % 8);
+        } else {
+            data[byte] &= !(1 << (bit % 8));
+        }
+
+        self.write(bitmap, &data)
+    }
+
+    // A new block filled with zeros
+    fn alloc_block(&self) -> Result<u32, OpenError> {
+        let Superblock {
+            bitmap,
+            inode_table,
+            data,
+            blocks,
+            ..
+        } = self.superblock;
+
+        for bitmap_block in bitmap..inode_table {
+            let bits = self.read(bitmap_block)?;
+
+            for (byte, value) in bits.iter().enumerate().filter(|(_, value)| **value != 0xff) {
+                let bit = value.trailing_ones();
+                let block =
+                    (((bitmap_block - bitmap) as usize * BLOCK_SIZE + byte) * 8) as u32 + bit;
+
+                if block < data || block >= blocks {
+                    continue;
+                }
+
+                self.set_used(block, true)?;
+                self.write(block, &[0; BLOCK_SIZE])?;
+
+                return Ok(block);
+            }
+        }
+
+        Err(OpenError::NoSpace)
+    }
+
+    fn alloc_inode(&self, kind: Kind, mode: u16, links: u32) -> Result<u32, OpenError> {
+        for ino in ROOT_INO..self.superblock.inodes {
+            if self.inode(ino)?.kind.is_some() {
+                continue;
+            }
+
+            let now = now();
+
+            self.set_inode(
+                ino,
+                &Inode {
+                    kind: Some(kind),
+                    mode,
+                    links,
+                    created: now,
+                    modified: now,
+                    accessed: now,
+                    ..Inode::default()
+                },
+            )?;
+
+            return Ok(ino);
+        }
+
+        Err(OpenError::NoSpace)
+    }
+
+    // The block with byte `index * BLOCK_SIZE` of an inode, 0 when it was never written
+    fn block_of(&self, inode: &Inode, index: usize) -> Result<u32, OpenError> {
+        if index < DIRECT {
+            return Ok(inode.direct[index]);
+        }
+
+        if index >= DIRECT + POINTERS || inode.indirect == 0 {
+            return Ok(0);
+        }
+
+        Ok(u32_at(&self.read(inode.indirect)?, (index - DIRECT) * 4))
+    }
+
+    // Like `block_of`, allocating the block if it's missing
+    fn alloc_block_of(&self, inode: &mut Inode, index: usize) -> Result<u32, OpenError> {
+        if index < DIRECT {
+            if inode.direct[index] == 0 {
+                inode.direct[index] = self.alloc_block()?;
+            }
+
+            return Ok(inode.direct[index]);
+        }
+
+        if index >= DIRECT + POINTERS {
+            return Err(OpenError::NoSpace);
+        }
+
+        if inode.indirect == 0 {
+            inode.indirect = self.alloc_block()?;
+        }
+
+        let mut pointers = self.read(inode.indirect)?;
+        let mut block = u32_at(&pointers, (index - DIRECT) * 4);
+
+        if block == 0 {
+            block = self.alloc_block()?;
+            put_u32(&mut pointers, (index - DIRECT) * 4, block);
+            self.write(inode.indirect, &pointers)?;
+        }
+
+        Ok(block)
+    }
+
+    // Every block of an inode, including the one with more block numbers
+    fn blocks(&self, inode: &Inode) -> Result<Vec<u32>, OpenError> {
+        let mut blocks: Vec<u32> = inode.direct.to_vec();
+
+        if inode.indirect != 0 {
+            let pointers = self.read(inode.indirect)?;
+
+            blocks.push(inode.indirect);
+            blocks.extend((0..POINTERS).map(|index| u32_at(&pointers, index * 4)));
+        }
+
+        blocks.retain(|block| *block != 0);
+
+        Ok(blocks)
+    }
+
+    fn read_data(&self, inode: &Inode, offset: usize, len: usize) -> Result<Vec<u8>, OpenError> {
+        let end = offset.saturating_add(len).min(inode.size);
+        let mut data = Vec::with_capacity(end.saturating_sub(offset));
+        let mut position = offset;
+
+        while position < end {
+            let (index, start) = (position / BLOCK_SIZE, position % BLOCK_SIZE);
+            let count = (BLOCK_SIZE - start).min(end - position);
+
+            match self.block_of(inode, index)? {
+                0 => data.resize(data.len() + count, 0),
+                block => data.extend_from_slice(&self.read(block)?[start..start + count]),
+            }
+
+            position += count;
+        }
+
+        Ok(data)
+    }
+
+    // The inode has to be saved even when this fails, it may have new blocks
+    fn write_data(&self, inode: &mut Inode, offset: usize, data: &[u8]) -> Result<(), OpenError> {
+        let end = offset + data.len();
+
+        if end > MAX_FILE_SIZE {
+            return Err(OpenError::NoSpace);
+        }
+
+        let mut position = offset;
+
+        while position < end {
+            let (index, start) = (position / BLOCK_SIZE, position % BLOCK_SIZE);
+            let count = (BLOCK_SIZE - start).min(end - position);
+
+            let block = self.alloc_block_of(inode, index)?;
+            let mut buffer = self.read(block)?;
+
+            buffer[start..start + count].copy_from_slice(&data[position - offset..][..count]);
+            self.write(block, &buffer)?;
+
+            inode.size = inode.size.max(position + count);
+            position += count;
+        }
+
+        Ok(())
+    }
+
+    // Growing leaves a gap that reads as zeros, shrinking frees the blocks after the end
+    fn resize(&self, inode: &mut Inode, size: usize) -> Result<(), OpenError> {
+        if size > MAX_FILE_SIZE {
+            return Err(OpenError::NoSpace);
+        }
+
+        if size < inode.size {
+            let keep = size.div_ceil(BLOCK_SIZE);
+
+            for block in inode.direct.iter_mut().skip(keep) {
+                if *block != 0 {
+                    self.set_used(*block, false)?;
+                    *block = 0;
+                }
+            }
+
+            if inode.indirect != 0 {
+                let mut pointers = self.read(inode.indirect)?;
+
+                for index in keep.saturating_sub(DIRECT)..POINTERS {
+                    let block = u32_at(&pointers, index * 4);
+
+                    if block != 0 {
+                        self.set_used(block, false)?;
+                        put_u32(&mut pointers, index * 4, 0);
+                    }
+                }
+
+                if keep <= DIRECT {
+                    self.set_used(inode.indirect, false)?;
+                    inode.indirect = 0;
+                } else {
+                    self.write(inode.indirect, &pointers)?;
+                }
+            }
+
+            // what is left of the last block reads as zeros when the file grows again
+            let block = self.block_of(inode, size / BLOCK_SIZE)?;
+            let tail = size % BLOCK_SIZE;
+
+            if tail > 0 && block != 0 {
+                let mut data = self.read(block)?;
+                data[tail..].fill(0);
+                self.write(block, &data)?;
+            }
+        }
+
+        inode.size = size;
+
+        Ok(())
+    }
+
+    // The used entries of a directory with where they are stored
+    fn entries(&self, directory: &Inode) -> Result<Vec<(usize, String, u32)>, OpenError> {
+        let data = self.read_data(directory, 0, directory.size)?;
+
+        Ok(data
+            .chunks_exact(ENTRY_SIZE)
+            .enumerate()
+            .map(|(index, entry)| {
+                let len = (entry[4] as usize).min(NAME_MAX);
+                let name = String::from_utf8_lossy(&entry[5..5 + len]).into_owned();
+
+                (index * ENTRY_SIZE, name, u32_at(entry, 0))
+            })
+            .filter(|(_, _, ino)| *ino != 0)
+            .collect())
+    }
+
+    fn find(&self, directory: u32, name: &str) -> Result<Option<u32>, OpenError> {
+        let entries = self.entries(&self.inode(directory)?)?;
+
+        Ok(entries
+            .into_iter()
+            .find(|(_, entry, _)| entry == name)
+            .map(|(_, _, ino)| ino))
+    }
+
+    fn insert(&self, directory: u32, name: &str, ino: u32) -> Result<(), OpenError> {
+        if name.len() > NAME_MAX {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let mut inode = self.inode(directory)?;
+        let data = self.read_data(&inode, 0, inode.size)?;
+
+        // the first free entry or a new one at the end
+        let offset = data
+            .chunks_exact(ENTRY_SIZE)
+            .position(|entry| u32_at(entry, 0) == 0)
+            .map_or(inode.size, |index| index * ENTRY_SIZE);
+
+        let mut entry = [0; ENTRY_SIZE];
+        put_u32(&mut entry, 0, ino);
+        entry[4] = name.len() as u8;
+        entry[5..5 + name.len()].copy_from_slice(name.as_bytes());
+
+        let result = self.write_data(&mut inode, offset, &entry);
+        inode.modified = now();
+        self.set_inode(directory, &inode)?;
+
+        result
+    }
+
+    fn remove(&self, directory: u32, name: &str) -> Result<(), OpenError> {
+        let mut inode = self.inode(directory)?;
+
+        let (offset, _, _) = self
+            .entries(&inode)?
+            .into_iter()
+            .find(|(_, entry, _)| entry == name)
+            .ok_or(OpenError::NoEntry)?;
+
+        self.write_data(&mut inode, offset, &[0; ENTRY_SIZE])?;
+        inode.modified = now();
+        self.set_inode(directory, &inode)
+    }
+
+    // Frees an inode with all of its blocks
+    fn release(&self, ino: u32) -> Result<(), OpenError> {
+        let mut inode = self.inode(ino)?;
+        self.resize(&mut inode, 0)?;
+
+        self.set_inode(ino, &Inode::default())
+    }
+
+    // Removes one name of an inode, which is freed with the last one
+    fn unlink_inode(&self, ino: u32) -> Result<(), OpenError> {
+        let mut inode = self.inode(ino)?;
+        inode.links = inode.links.saturating_sub(1);
+
+        if inode.links == 0 {
+            return self.release(ino);
+        }
+
+        self.set_inode(ino, &inode)
+    }
+
+    // Links are not followed, the VFS already did
+    fn lookup(&self, path: &str) -> Result<u32, OpenError> {
+        let mut ino = ROOT_INO;
+
+        for name in components(path) {
+            if self.inode(ino)?.kind != Some(Kind::Directory) {
+                return Err(OpenError::NotADirectory);
+            }
+
+            ino = self.find(ino, &name)?.ok_or(OpenError::NoEntry)?;
+        }
+
+        Ok(ino)
+    }
+
+    // The directory containing the entry at `path` and the name of the entry
+    fn parent<'a>(&self, path: &'a str) -> Result<(u32, &'a str), OpenError> {
+        let (dirname, name) = split_parent(path.trim_end_matches('/'));
+
+        // `/` has no parent
+        if name.is_empty() {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let directory = self.lookup(dirname)?;
+
+        if self.inode(directory)?.kind != Some(Kind::Directory) {
+            return Err(OpenError::NotADirectory);
+        }
+
+        Ok((directory, name))
+    }
+
+    // The parent of a new entry, which must not exist yet
+    fn new_entry<'a>(&self, path: &'a str) -> Result<(u32, &'a str), OpenError> {
+        let (directory, name) = self.parent(path).map_err(|err| match err {
+            OpenError::InvalidArgument => OpenError::AlreadyExists,
+            err => err,
+        })?;
+
+        if self.find(directory, name)?.is_some() {
+            return Err(OpenError::AlreadyExists);
+        }
+
+        Ok((directory, name))
+    }
+
+    // A new inode with a name in its parent
+    fn add(&self, path: &str, kind: Kind, mode: u16, links: u32) -> Result<u32, OpenError> {
+        let (directory, name) = self.new_entry(path)?;
+        let ino = self.alloc_inode(kind, mode, links)?;
+
+        if let Err(err) = self.insert(directory, name, ino) {
+            self.release(ino)?;
+            return Err(err);
+        }
+
+        Ok(ino)
+    }
+
+    fn stat(&self, ino: u32) -> Result<Stat, OpenError> {
+        let inode = self.inode(ino)?;
+        let kind = inode.kind.ok_or(OpenError::DeviceError)?;
+
+        let size = match kind {
+            Kind::Directory => self.entries(&inode)?.len(),
+            _ => inode.size,
+        };
+
+        Ok(Stat {
+            ino: ino as u64,
+            kind,
+            size,
+            mode: inode.mode,
+            uid: inode.uid,
+            gid: inode.gid,
+            links: inode.links,
+            created: inode.created,
+            modified: inode.modified,
+            accessed: inode.accessed,
+            device: 0,
+        })
+    }
+}
+
+// The filesystem of an image, changes are written to the device right away
+pub struct ImageFs {
+    image: Rc<Image>,
+}
+
+impl ImageFs {
+    // Writes an empty image with room for `inodes` inodes over the whole device
+    pub fn format(device: Rc<dyn BlockDevice>, inodes: u32) -> Result<Self, OpenError> {
+        if device.block_size() != BLOCK_SIZE {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let superblock = Superblock::new(device.block_count() as u32, inodes + 1);
+
+        if superblock.data >= superblock.blocks {
+            return Err(OpenError::NoSpace);
+        }
+
+        let image = Image { device, superblock };
+
+        for block in 0..superblock.data {
+            image.write(block, &[0; BLOCK_SIZE])?;
+        }
+
+        image.write(0, &superblock.encode())?;
+
+        for block in 0..superblock.data {
+            image.set_used(block, true)?;
+        }
+
+        let ino = image.alloc_inode(Kind::Directory, DIRECTORY_MODE, 2)?;
+        debug_assert_eq!(ino, ROOT_INO);
+
+        Ok(ImageFs {
+            image: Rc::new(image),
+        })
+    }
+
+    // An image made by `format`, possibly in an earlier run
+    pub fn load(device: Rc<dyn BlockDevice>) -> Result<Self, OpenError> {
+        let image = Image {
+            superblock: read_superblock(device.as_ref()).map_err(|_| OpenError::InvalidArgument)?,
+            device,
+        };
+
+        Ok(ImageFs {
+            image: Rc::new(image),
+        })
+    }
+
+    // Replaces the content of a file with bytes that may not be text, used by `mkfs`
+    pub fn write_bytes(&self, path: &str, data: &[u8]) -> Result<(), OpenError> {
+        let ino = self.image.lookup(path)?;
+        let mut inode = self.image.inode(ino)?;
+
+        if inode.kind != Some(Kind::File) {
+            return Err(OpenError::IsDirectory);
+        }
+
+        self.image.resize(&mut inode, 0)?;
+        let result = self.image.write_data(&mut inode, 0, data);
+        inode.modified = now();
+        self.image.set_inode(ino, &inode)?;
+
+        result
+    }
+
+    // Sets the modification time in milliseconds, used by `mkfs` to keep the one of the host
+    pub fn set_modified(&self, path: &str, time: u64) -> Result<(), OpenError> {
+        let ino = self.image.lookup(path)?;
+
+        self.image.update(ino, |inode| inode.modified = time)
+    }
+}
+
+impl FileSystem for ImageFs {
+    fn name(&self) -> &str {
+        "imgfs"
+    }
+
+    fn sync(&self) -> Result<(), OpenError> {
+        self.image
+            .device
+            .flush()
+            .map_err(|_| OpenError::DeviceError)
+    }
+
+    fn stat(&self, path: &str) -> Result<Stat, OpenError> {
+        self.image.stat(self.image.lookup(path)?)
+    }
+
+    fn open(&self, path: &str) -> Result<Rc<dyn FileHandle>, OpenError> {
+        let ino = self.image.lookup(path)?;
+
+        match self.image.inode(ino)?.kind {
+            Some(Kind::File) => Ok(Rc::new(ImageFile {
+                image: Rc::clone(&self.image),
+                ino,
+            })),
+            _ => Err(OpenError::IsDirectory),
+        }
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<(String, Stat)>, OpenError> {
+        let directory = self.image.inode(self.image.lookup(path)?)?;
+
+        if directory.kind != Some(Kind::Directory) {
+            return Err(OpenError::NotADirectory);
+        }
+
+        let mut entries = BTreeMap::new();
+
+        for (_, name, ino) in self.image.entries(&directory)? {
+            entries.insert(name, self.image.stat(ino)?);
+        }
+
+        Ok(entries.into_iter().collect())
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, OpenError> {
+        let link = self.image.inode(self.image.lookup(path)?)?;
+
+        if link.kind != Some(Kind::Symlink) {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let target = self.image.read_data(&link, 0, link.size)?;
+
+        Ok(String::from_utf8_lossy(&target).into_owned())
+    }
+
+    fn chmod(&self, path: &str, mode: u16) -> Result<(), OpenError> {
+        let ino = self.image.lookup(path)?;
+
+        self.image.update(ino, |inode| inode.mode = mode)
+    }
+
+    fn chown(&self, path: &str, uid: Uid, gid: Gid) -> Result<(), OpenError> {
+        let ino = self.image.lookup(path)?;
+
+        self.image.update(ino, |inode| {
+            inode.uid = uid;
+            inode.gid = gid;
+        })
+    }
+
+    fn create(&self, path: &str) -> Result<(), OpenError> {
+        self.image.add(path, Kind::File, FILE_MODE, 1)?;
+
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), OpenError> {
+        self.image.add(path, Kind::Directory, DIRECTORY_MODE, 2)?;
+
+        // the `..` of the new directory
+        let (parent, _) = self.image.parent(path)?;
+        self.image.update(parent, |parent| parent.links += 1)
+    }
+
+    fn symlink(&self, target: &str, path: &str) -> Result<(), OpenError> {
+        let ino = self.image.add(path, Kind::Symlink, SYMLINK_MODE, 1)?;
+
+        let mut link = self.image.inode(ino)?;
+        let result = self.image.write_data(&mut link, 0, target.as_bytes());
+        self.image.set_inode(ino, &link)?;
+
+        result
+    }
+
+    // Directories can't be linked
+    fn link(&self, existing: &str, path: &str) -> Result<(), OpenError> {
+        let ino = self.image.lookup(existing)?;
+
+        if self.image.inode(ino)?.kind != Some(Kind::File) {
+            return Err(OpenError::IsDirectory);
+        }
+
+        let (directory, name) = self.image.new_entry(path)?;
+
+        self.image.insert(directory, name, ino)?;
+        self.image.update(ino, |file| file.links += 1)
+    }
+
+    // The file is freed with its last name, even when it's still open
+    fn unlink(&self, path: &str) -> Result<(), OpenError> {
+        let (directory, name) = self.image.parent(path).map_err(|err| match err {
+            OpenError::InvalidArgument => OpenError::IsDirectory,
+            err => err,
+        })?;
+
+        let ino = self
+            .image
+            .find(directory, name)?
+            .ok_or(OpenError::NoEntry)?;
+
+        if self.image.inode(ino)?.kind == Some(Kind::Directory) {
+            return Err(OpenError::IsDirectory);
+        }
+
+        self.image.remove(directory, name)?;
+        self.image.unlink_inode(ino)
+    }
+
+    // Removes an empty directory
+    fn rmdir(&self, path: &str) -> Result<(), OpenError> {
+        let (parent, name) = self.image.parent(path)?;
+        let ino = self.image.find(parent, name)?.ok_or(OpenError::NoEntry)?;
+        let directory = self.image.inode(ino)?;
+
+        if directory.kind != Some(Kind::Directory) {
+            return Err(OpenError::NotADirectory);
+        }
+
+        if !self.image.entries(&directory)?.is_empty() {
+            return Err(OpenError::NotEmpty);
+        }
+
+        self.image.remove(parent, name)?;
+        self.image.release(ino)?;
+        self.image.update(parent, |parent| parent.links -= 1)
+    }
+
+    // Moves an entry, replacing a file or an empty directory at `to`
+    fn rename(&self, from: &str, to: &str) -> Result<(), OpenError> {
+        let (from_parent, from_name) = self.image.parent(from)?;
+        let (to_parent, to_name) = self.image.parent(to)?;
+
+        let ino = self
+            .image
+            .find(from_parent, from_name)?
+            .ok_or(OpenError::NoEntry)?;
+
+        // a directory can't be moved inside of itself
+        let from = from.trim_end_matches('/');
+        if to.trim_end_matches('/') == from {
+            return Ok(());
+        }
+        if to.starts_with(&format!("{from}/")) {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let is_directory = self.image.inode(ino)?.kind == Some(Kind::Directory);
+
+        if let Some(replaced) = self.image.find(to_parent, to_name)? {
+            let inode = self.image.inode(replaced)?;
+
+            match (is_directory, inode.kind == Some(Kind::Directory)) {
+                (true, true) if !self.image.entries(&inode)?.is_empty() => {
+                    return Err(OpenError::NotEmpty)
+                }
+                (true, true) => {
+                    self.image.remove(to_parent, to_name)?;
+                    self.image.release(replaced)?;
+                    self.image.update(to_parent, |parent| parent.links -= 1)?;
+                }
+                (true, false) => return Err(OpenError::NotADirectory),
+                (false, true) => return Err(OpenError::IsDirectory),
+                (false, false) => {
+                    self.image.remove(to_parent, to_name)?;
+                    self.image.unlink_inode(replaced)?;
+                }
+            }
+        }
+
+        self.image.remove(from_parent, from_name)?;
+        self.image.insert(to_parent, to_name, ino)?;
+
+        // the `..` of a directory changes parent
+        if is_directory {
+            self.image.update(from_parent, |parent| parent.links -= 1)?;
+            self.image.update(to_parent, |parent| parent.links += 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Offsets are in bytes, invalid UTF-8 is replaced
+struct ImageFile {
+    image: Rc<Image>,
+    ino: u32,
+}
+
+impl ImageFile {
+    // Fails once the file was removed
+    fn inode(&self) -> Result<Inode, IOError> {
+        match self.image.inode(self.ino) {
+            Ok(inode) if inode.kind == Some(Kind::File) => Ok(inode),
+            _ => Err(IOError::DeviceError),
+        }
+    }
+}
+
+impl FileHandle for ImageFile {
+    fn read_at(&self, offset: usize, len: usize) -> Result<(String, usize), IOError> {
+        let mut inode = self.inode()?;
+
+        if offset >= inode.size {
+            return Err(IOError::ChannelClosed);
+        }
+
+        // enough for a whole char
+        let data = self
+            .image
+            .read_data(&inode, offset, len.max(4))
+            .map_err(io_error)?;
+
+        inode.accessed = now();
+        self.image.set_inode(self.ino, &inode).map_err(io_error)?;
+
+        Ok(utf8_prefix(&data, len))
+    }
+
+    fn write_at(&self, offset: usize, data: &str) -> Result<(), IOError> {
+        let mut inode = self.inode()?;
+
+        let result = self.image.write_data(&mut inode, offset, data.as_bytes());
+        inode.modified = now();
+        self.image.set_inode(self.ino, &inode).map_err(io_error)?;
+
+        result.map_err(io_error)
+    }
+
+    fn size(&self) -> usize {
+        self.inode().map_or(0, |inode| inode.size)
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), IOError> {
+        let mut inode = self.inode()?;
+
+        self.image.resize(&mut inode, size).map_err(io_error)?;
+        inode.modified = now();
+        self.image.set_inode(self.ino, &inode).map_err(io_error)
+    }
+}
+
+fn io_error(err: OpenError) -> IOError {
+    match err {
+        OpenError::NoSpace => IOError::NoSpace,
+        _ => IOError::DeviceError,
+    }
+}
+
+fn read_superblock(device: &dyn BlockDevice) -> Result<Superblock, String> {
+    if device.block_size() != BLOCK_SIZE {
+        return Err(format!("unsupported block size {}", device.block_size()));
+    }
+
+    let mut block = vec![0; BLOCK_SIZE];
+    device
+        .read_block(0, &mut block)
+        .map_err(|err| format!("cannot read the superblock: {err:?}"))?;
+
+    let superblock = Superblock::decode(&block)?;
+
+    if superblock.blocks as usize > device.block_count() {
+        return Err(format!(
+            "the image has {} blocks but the device only {}",
+            superblock.blocks,
+            device.block_count()
+        ));
+    }
+
+    Ok(superblock)
+}
+
+// Checks that an image is consistent, returns what is wrong with it
+pub fn check(device: Rc<dyn BlockDevice>) -> Vec<String> {
+    let superblock = match read_superblock(device.as_ref()) {
+        Ok(superblock) => superblock,
+        Err(err) => return vec![err],
+    };
+
+    let image = Image { device, superblock };
+    let mut problems = vec![];
+
+    if let Err(err) = check_tree(&image, &mut problems) {
+        problems.push(format!("cannot read the image: {err:?}"));
+    }
+
+    problems
+}
+
+fn check_tree(image: &Image, problems: &mut Vec<String>) -> Result<(), OpenError> {
+    let Superblock {
+        blocks,
+        inodes,
+        data,
+        ..
+    } = image.superblock;
+
+    // names of every inode reached from the root and the directories inside of each directory
+    let mut names: BTreeMap<u32, u32> = BTreeMap::from([(ROOT_INO, 1)]);
+    let mut subdirectories: BTreeMap<u32, u32> = BTreeMap::new();
+    let mut owners: BTreeMap<u32, u32> = BTreeMap::new();
+
+    let mut pending = vec![ROOT_INO];
+
+    while let Some(ino) = pending.pop() {
+        let inode = image.inode(ino)?;
+
+        let Some(kind) = inode.kind else {
+            problems.push(format!("inode {ino} is named but free"));
+            continue;
+        };
+
+        if inode.size > MAX_FILE_SIZE {
+            problems.push(format!("inode {ino} is too large"));
+            continue;
+        }
+
+        for block in image.blocks(&inode)? {
+            if block < data || block >= blocks {
+                problems.push(format!(
+                    "inode {ino} uses block {block} outside of the data"
+                ));
+            } else if let Some(owner) = owners.insert(block, ino) {
+                problems.push(format!("block {block} is used by inode {owner} and {ino}"));
+            }
+        }
+
+        if kind != Kind::Directory {
+            continue;
+        }
+
+        for (_, name, entry) in image.entries(&inode)? {
+            if entry >= inodes {
+                problems.push(format!(
+                    "{name:?} in inode {ino} names the invalid inode {entry}"
+                ));
+                continue;
+            }
+
+            if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+                problems.push(format!(
+                    "inode {ino} has an entry with the invalid name {name:?}"
+                ));
+            }
+
+            let count = names.entry(entry).or_insert(0);
+            *count += 1;
+
+            if image.inode(entry)?.kind == Some(Kind::Directory) {
+                *subdirectories.entry(ino).or_insert(0) += 1;
+
+                // a directory with two names would make a loop
+                if *count > 1 || entry == ROOT_INO {
+                    problems.push(format!("directory {entry} has more than one name"));
+                    continue;
+                }
+            }
+
+            if *count == 1 {
+                pending.push(entry);
+            }
+        }
+    }
+
+    for ino in ROOT_INO..inodes {
+        let inode = image.inode(ino)?;
+
+        let Some(kind) = inode.kind else {
+            continue;
+        };
+
+        let Some(count) = names.get(&ino) else {
+            problems.push(format!("inode {ino} is used but has no name"));
+            continue;
+        };
+
+        let expected = match kind {
+            Kind::Directory => 2 + subdirectories.get(&ino).copied().unwrap_or(0),
+            _ => *count,
+        };
+
+        if inode.links != expected {
+            problems.push(format!(
+                "inode {ino} has {} links instead of {expected}",
+                inode.links
+            ));
+        }
+    }
+
+    for block in 0..blocks {
+        let bitmap = image.read(image.superblock.bitmap + block / 8 / BLOCK_SIZE as u32)?;
+        let marked = bitmap[block as usize / 8 % BLOCK_SIZE] & (1 << (block % 8)) != 0;
+        let used = block < data || owners.contains_key(&block);
+
+        match (marked, used) {
+            (true, false) => problems.push(format!("block {block} is marked used but is free")),
+            (false, true) => problems.push(format!("block {block} is used but marked free")),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap_or_default())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap_or_default())
+}
+
+fn put_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(bytes: &mut [u8], offset: usize, value: u64) {
+    bytes[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::block::RamDisk;
+
+    fn image() -> (Rc<RamDisk>, ImageFs) {
+        let disk = Rc::new(RamDisk::new(BLOCK_SIZE, 256));
+        let fs = ImageFs::format(Rc::clone(&disk) as Rc<dyn BlockDevice>, 64).unwrap();
+
+        fs.mkdir("/etc").unwrap();
+        fs.create("/etc/profile").unwrap();
+        fs.open("/etc/profile")
+            .unwrap()
+            .write_at(0, "alias l=ls\n")
+            .unwrap();
+
+        (disk, fs)
+    }
+
+    #[test]
+    fn format() {
+        let (disk, fs) = image();
+        fs.chown("/etc/profile", 1000, 100).unwrap();
+        fs.set_modified("/etc/profile", 5000).unwrap();
+
+        // a small device has no room for the inode table
+        let small = Rc::new(RamDisk::new(BLOCK_SIZE, 4));
+        assert_eq!(ImageFs::format(small, 64).err(), Some(OpenError::NoSpace));
+
+        let garbage = Rc::new(RamDisk::new(BLOCK_SIZE, 16));
+        assert_eq!(
+            ImageFs::load(garbage).err(),
+            Some(OpenError::InvalidArgument)
+        );
+
+        // saved and loaded again
+        let copy = Rc::new(RamDisk::from_bytes(BLOCK_SIZE, disk.bytes()));
+        let fs = ImageFs::load(copy).unwrap();
+
+        assert_eq!(fs.stat("/etc").map(|stat| stat.kind), Ok(Kind::Directory));
+        assert_eq!(
+            fs.open("/etc/profile").unwrap().read_at(0, 100),
+            Ok(("alias l=ls\n".to_string(), 11))
+        );
+
+        // the owners and times are stored too
+        let stat = fs.stat("/etc/profile").unwrap();
+        assert_eq!((stat.uid, stat.gid, stat.modified), (1000, 100, 5000));
+        assert_eq!(fs.stat("/etc").map(|stat| stat.uid), Ok(0));
+        assert!(check(disk).is_empty());
+    }
+
+    #[test]
+    fn large_files() {
+        let (disk, fs) = image();
+
+        fs.create("/big").unwrap();
+        let file = fs.open("/big").unwrap();
+
+        // past the direct blocks, the gap reads as zeros
+        file.write_at(10 * BLOCK_SIZE, "end").unwrap();
+        assert_eq!(file.size(), 10 * BLOCK_SIZE + 3);
+        assert_eq!(file.read_at(BLOCK_SIZE, 2), Ok(("\0\0".to_string(), 2)));
+        assert_eq!(
+            file.read_at(10 * BLOCK_SIZE, 100),
+            Ok(("end".to_string(), 3))
+        );
+        assert_eq!(file.write_at(MAX_FILE_SIZE, "x"), Err(IOError::NoSpace));
+
+        file.truncate(1).unwrap();
+        file.write_at(3, "b").unwrap();
+        assert_eq!(file.read_at(0, 100), Ok(("\0\0\0b".to_string(), 4)));
+        assert!(check(Rc::clone(&disk) as Rc<dyn BlockDevice>).is_empty());
+
+        fs.unlink("/big").unwrap();
+        assert!(check(disk).is_empty());
+    }
+
+    #[test]
+    fn directories() {
+        let (disk, fs) = image();
+
+        fs.mkdir("/home").unwrap();
+        fs.mkdir("/home/fritz").unwrap();
+        fs.symlink("/etc/profile", "/home/fritz/.profile").unwrap();
+        fs.link("/etc/profile", "/etc/hard").unwrap();
+
+        assert_eq!(
+            fs.readlink("/home/fritz/.profile"),
+            Ok("/etc/profile".to_string())
+        );
+        assert_eq!(fs.stat("/etc/profile").map(|stat| stat.links), Ok(2));
+        assert_eq!(fs.stat("/").map(|stat| (stat.size, stat.links)), Ok((2, 4)));
+        assert_eq!(fs.mkdir("/home"), Err(OpenError::AlreadyExists));
+        assert_eq!(fs.rmdir("/home"), Err(OpenError::NotEmpty));
+        assert_eq!(
+            fs.create(&format!("/{}", "x".repeat(NAME_MAX + 1))),
+            Err(OpenError::InvalidArgument)
+        );
+
+        assert_eq!(
+            fs.rename("/home", "/home/fritz/home"),
+            Err(OpenError::InvalidArgument)
+        );
+        assert_eq!(fs.rename("/etc/hard", "/etc"), Err(OpenError::IsDirectory));
+        fs.rename("/home/fritz", "/etc/fritz").unwrap();
+        fs.rename("/etc/hard", "/etc/profile").unwrap();
+
+        let names: Vec<String> = fs
+            .readdir("/etc")
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["fritz", "profile"]);
+        assert_eq!(fs.stat("/etc/profile").map(|stat| stat.links), Ok(1));
+
+        fs.rmdir("/home").unwrap();
+        assert!(check(disk).is_empty());
+    }
+
+    #[test]
+    fn corruption() {
+        let (disk, fs) = image();
+        fs.create("/file").unwrap();
+        fs.open("/file").unwrap().write_at(0, "data").unwrap();
+
+        let ino = fs.stat("/file").unwrap().ino as u32;
+        let image = &fs.image;
+
+        // a lost name, and a block marked free while it's used
+        image.update(ino, |inode| inode.links = 2).unwrap();
+        let block = image.inode(ino).unwrap().direct[0];
+        image.set_used(block, false).unwrap();
+
+        let problems = check(disk);
+        assert_eq!(
+            problems,
+            [
+                format!("inode {ino} has 2 links instead of 1"),
+                format!("block {block} is used but marked free"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,669 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    rc::Rc,
+};
+
+use super::{
+    components, descriptor::IOError, file::FileHandle, now, split_parent, FileSystem, Kind,
+    OpenError, Stat,
+};
+use crate::{Gid, Uid};
+
+// Directories keep their entries sorted by name
+pub struct Directory {
+    pub entries: RefCell<BTreeMap<String, Inode>>,
+    pub meta: Metadata,
+}
+
+impl Directory {
+    pub fn new(meta: Metadata) -> Rc<Self> {
+        Rc::new(Directory {
+            entries: RefCell::new(BTreeMap::new()),
+            meta,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<Inode> {
+        self.entries.borrow().get(name).cloned()
+    }
+
+    pub fn insert(&self, name: &str, inode: Inode) {
+        self.entries.borrow_mut().insert(name.to_string(), inode);
+    }
+
+    pub fn remove(&self, name: &str) -> Option<Inode> {
+        self.entries.borrow_mut().remove(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+}
+
+pub struct File {
+    pub content: RefCell<String>,
+    pub meta: Metadata,
+}
+
+impl File {
+    pub fn new(content: &str, meta: Metadata) -> Rc<Self> {
+        Rc::new(File {
+            content: RefCell::new(content.to_string()),
+            meta,
+        })
+    }
+}
+
+// Offsets are in bytes, a char is never split
+impl FileHandle for File {
+    fn read_at(&self, offset: usize, len: usize) -> Result<(String, usize), IOError> {
+        let content = self.content.borrow();
+        self.meta.accessed.set(now());
+
+        if offset >= content.len() {
+            return Err(IOError::ChannelClosed);
+        }
+
+        let start = floor_char_boundary(&content, offset);
+        let mut end = floor_char_boundary(&content, offset.saturating_add(len));
+
+        // always return at least one char
+        if end <= start {
+            end = start + content[start..].chars().next().map_or(0, char::len_utf8);
+        }
+
+        Ok((content[start..end].to_string(), end - offset))
+    }
+
+    fn write_at(&self, offset: usize, data: &str) -> Result<(), IOError> {
+        let mut content = self.content.borrow_mut();
+        self.meta.modified.set(now());
+
+        // the gap after the end of the file is filled with zeros
+        if offset > content.len() {
+            let gap = offset - content.len();
+            content.extend(std::iter::repeat_n('\0', gap));
+        }
+
+        // the text can't start inside a char, one it ends in is overwritten whole
+        if !content.is_char_boundary(offset) {
+            return Err(IOError::InvalidOffset);
+        }
+
+        let end = ceil_char_boundary(&content, offset + data.len());
+
+        content.replace_range(offset..end, data);
+        Ok(())
+    }
+
+    fn size(&self) -> usize {
+        self.content.borrow().len()
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), IOError> {
+        let mut content = self.content.borrow_mut();
+        self.meta.modified.set(now());
+
+        let size = floor_char_boundary(&content, size);
+        content.truncate(size);
+        Ok(())
+    }
+}
+
+fn floor_char_boundary(str: &str, mut index: usize) -> usize {
+    if index >= str.len() {
+        return str.len();
+    }
+
+    while !str.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    index
+}
+
+fn ceil_char_boundary(str: &str, mut index: usize) -> usize {
+    if index >= str.len() {
+        return str.len();
+    }
+
+    while !str.is_char_boundary(index) {
+        index += 1;
+    }
+
+    index
+}
+
+// A path to another inode, relative to the directory of the link
+pub struct Symlink {
+    pub target: String,
+    pub meta: Metadata,
+}
+
+// A special file, the VFS finds the device by its number
+pub struct Device {
+    pub kind: Kind,
+    pub number: u32,
+    pub meta: Metadata,
+}
+
+// Times are in milliseconds since the unix epoch
+pub struct Metadata {
+    pub ino: u64,
+    // permission bits, like 0o644
+    pub mode: Cell<u16>,
+    // everything belongs to root until given away
+    pub uid: Cell<Uid>,
+    pub gid: Cell<Gid>,
+    // number of names of a file, a directory is also named by `.` and the `..` of its children
+    pub links: Cell<u32>,
+    pub created: u64,
+    pub modified: Cell<u64>,
+    pub accessed: Cell<u64>,
+}
+
+impl Metadata {
+    pub fn new(ino: u64, mode: u16, links: u32, now: u64) -> Self {
+        Metadata {
+            ino,
+            mode: Cell::new(mode),
+            uid: Cell::new(0),
+            gid: Cell::new(0),
+            links: Cell::new(links),
+            created: now,
+            modified: Cell::new(now),
+            accessed: Cell::new(now),
+        }
+    }
+}
+
+// Inodes are shared, a lookup hands out a new reference to the same file or directory
+#[derive(Clone)]
+pub enum Inode {
+    File(Rc<File>),
+    Directory(Rc<Directory>),
+    Symlink(Rc<Symlink>),
+    Device(Rc<Device>),
+}
+
+impl Inode {
+    pub fn meta(&self) -> &Metadata {
+        match self {
+            Inode::File(file) => &file.meta,
+            Inode::Directory(directory) => &directory.meta,
+            Inode::Symlink(link) => &link.meta,
+            Inode::Device(device) => &device.meta,
+        }
+    }
+
+    pub fn stat(&self) -> Stat {
+        let (kind, size, device) = match self {
+            Inode::File(file) => (Kind::File, file.content.borrow().len(), 0),
+            Inode::Directory(directory) => (Kind::Directory, directory.entries.borrow().len(), 0),
+            Inode::Symlink(link) => (Kind::Symlink, link.target.len(), 0),
+            Inode::Device(device) => (device.kind, 0, device.number),
+        };
+
+        let meta = self.meta();
+
+        Stat {
+            ino: meta.ino,
+            kind,
+            size,
+            mode: meta.mode.get(),
+            uid: meta.uid.get(),
+            gid: meta.gid.get(),
+            links: meta.links.get(),
+            created: meta.created,
+            modified: meta.modified.get(),
+            accessed: meta.accessed.get(),
+            device,
+        }
+    }
+}
+
+pub const FILE_MODE: u16 = 0o644;
+pub const DIRECTORY_MODE: u16 = 0o755;
+pub const SYMLINK_MODE: u16 = 0o777;
+pub const DEVICE_MODE: u16 = 0o660;
+
+// A filesystem living only in memory, lost when fos stops
+pub struct MemoryFs {
+    root: Rc<Directory>,
+    next_ino: Cell<u64>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        MemoryFs {
+            root: Directory::new(Metadata::new(1, DIRECTORY_MODE, 2, now())),
+            next_ino: Cell::new(2),
+        }
+    }
+
+    fn metadata(&self, mode: u16, links: u32) -> Metadata {
+        let ino = self.next_ino.get();
+        self.next_ino.set(ino + 1);
+
+        Metadata::new(ino, mode, links, now())
+    }
+
+    // Links are not followed, the VFS already did
+    pub fn lookup(&self, path: &str) -> Result<Inode, OpenError> {
+        let mut inode = Inode::Directory(Rc::clone(&self.root));
+
+        for name in components(path) {
+            let Inode::Directory(directory) = inode else {
+                return Err(OpenError::NotADirectory);
+            };
+
+            inode = directory.get(&name).ok_or(OpenError::NoEntry)?;
+        }
+
+        Ok(inode)
+    }
+
+    // The directory containing the entry at `path` and the name of the entry
+    fn parent<'a>(&self, path: &'a str) -> Result<(Rc<Directory>, &'a str), OpenError> {
+        let (dirname, name) = split_parent(path.trim_end_matches('/'));
+
+        // `/` has no parent
+        if name.is_empty() {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        match self.lookup(dirname)? {
+            Inode::Directory(directory) => Ok((directory, name)),
+            _ => Err(OpenError::NotADirectory),
+        }
+    }
+
+    // The parent of a new entry, which must not exist yet
+    fn new_entry<'a>(&self, path: &'a str) -> Result<(Rc<Directory>, &'a str), OpenError> {
+        let (directory, name) = self.parent(path).map_err(|err| match err {
+            OpenError::InvalidArgument => OpenError::AlreadyExists,
+            err => err,
+        })?;
+
+        if directory.get(name).is_some() {
+            return Err(OpenError::AlreadyExists);
+        }
+
+        directory.meta.modified.set(now());
+
+        Ok((directory, name))
+    }
+}
+
+impl Default for MemoryFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn name(&self) -> &str {
+        "tmpfs"
+    }
+
+    fn stat(&self, path: &str) -> Result<Stat, OpenError> {
+        Ok(self.lookup(path)?.stat())
+    }
+
+    fn open(&self, path: &str) -> Result<Rc<dyn FileHandle>, OpenError> {
+        match self.lookup(path)? {
+            Inode::File(file) => Ok(file),
+            _ => Err(OpenError::IsDirectory),
+        }
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<(String, Stat)>, OpenError> {
+        match self.lookup(path)? {
+            Inode::Directory(directory) => Ok(directory
+                .entries
+                .borrow()
+                .iter()
+                .map(|(name, inode)| (name.clone(), inode.stat()))
+                .collect()),
+            _ => Err(OpenError::NotADirectory),
+        }
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, OpenError> {
+        match self.lookup(path)? {
+            Inode::Symlink(link) => Ok(link.target.clone()),
+            _ => Err(OpenError::InvalidArgument),
+        }
+    }
+
+    fn create(&self, path: &str) -> Result<(), OpenError> {
+        let (directory, name) = self.new_entry(path)?;
+
+        let file = File::new("", self.metadata(FILE_MODE, 1));
+        directory.insert(name, Inode::File(file));
+
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), OpenError> {
+        let (parent, name) = self.new_entry(path)?;
+
+        let directory = Directory::new(self.metadata(DIRECTORY_MODE, 2));
+        parent.insert(name, Inode::Directory(directory));
+
+        // the `..` of the new directory
+        parent.meta.links.set(parent.meta.links.get() + 1);
+
+        Ok(())
+    }
+
+    fn symlink(&self, target: &str, path: &str) -> Result<(), OpenError> {
+        let (directory, name) = self.new_entry(path)?;
+
+        let link = Symlink {
+            target: target.to_string(),
+            meta: self.metadata(SYMLINK_MODE, 1),
+        };
+
+        directory.insert(name, Inode::Symlink(Rc::new(link)));
+
+        Ok(())
+    }
+
+    fn chmod(&self, path: &str, mode: u16) -> Result<(), OpenError> {
+        self.lookup(path)?.meta().mode.set(mode);
+
+        Ok(())
+    }
+
+    fn chown(&self, path: &str, uid: Uid, gid: Gid) -> Result<(), OpenError> {
+        let inode = self.lookup(path)?;
+
+        inode.meta().uid.set(uid);
+        inode.meta().gid.set(gid);
+
+        Ok(())
+    }
+
+    fn mknod(&self, path: &str, kind: Kind, number: u32) -> Result<(), OpenError> {
+        let (directory, name) = self.new_entry(path)?;
+
+        // FIFOs and sockets are made by anyone, like files
+        let mode = match kind {
+            Kind::Fifo | Kind::Socket => FILE_MODE,
+            _ => DEVICE_MODE,
+        };
+
+        let device = Device {
+            kind,
+            number,
+            meta: self.metadata(mode, 1),
+        };
+
+        directory.insert(name, Inode::Device(Rc::new(device)));
+
+        Ok(())
+    }
+
+    // Directories can't be linked
+    fn link(&self, existing: &str, path: &str) -> Result<(), OpenError> {
+        let file = match self.lookup(existing)? {
+            Inode::File(file) => file,
+            _ => return Err(OpenError::IsDirectory),
+        };
+
+        let (directory, name) = self.new_entry(path)?;
+
+        file.meta.links.set(file.meta.links.get() + 1);
+        directory.insert(name, Inode::File(file));
+
+        Ok(())
+    }
+
+    fn unlink(&self, path: &str) -> Result<(), OpenError> {
+        let (directory, name) = self.parent(path).map_err(|err| match err {
+            OpenError::InvalidArgument => OpenError::IsDirectory,
+            err => err,
+        })?;
+
+        match directory.get(name) {
+            Some(Inode::Directory(_)) => Err(OpenError::IsDirectory),
+            // the file itself stays as long as it has other names or is open
+            Some(inode) => {
+                directory.remove(name);
+                directory.meta.modified.set(now());
+                inode.meta().links.set(inode.meta().links.get() - 1);
+
+                Ok(())
+            }
+            None => Err(OpenError::NoEntry),
+        }
+    }
+
+    // Removes an empty directory
+    fn rmdir(&self, path: &str) -> Result<(), OpenError> {
+        let (parent, name) = self.parent(path)?;
+
+        match parent.get(name) {
+            Some(Inode::Directory(directory)) if !directory.is_empty() => Err(OpenError::NotEmpty),
+            Some(Inode::Directory(directory)) => {
+                parent.remove(name);
+                parent.meta.links.set(parent.meta.links.get() - 1);
+                parent.meta.modified.set(now());
+                directory.meta.links.set(0);
+
+                Ok(())
+            }
+            Some(_) => Err(OpenError::NotADirectory),
+            None => Err(OpenError::NoEntry),
+        }
+    }
+
+    // Moves an entry, replacing a file or an empty directory at `to`
+    fn rename(&self, from: &str, to: &str) -> Result<(), OpenError> {
+        let (from_parent, from_name) = self.parent(from)?;
+        let (to_parent, to_name) = self.parent(to)?;
+
+        let inode = from_parent.get(from_name).ok_or(OpenError::NoEntry)?;
+
+        // a directory can't be moved inside of itself
+        let from = from.trim_end_matches('/');
+        if to.trim_end_matches('/') == from {
+            return Ok(());
+        }
+        if to.starts_with(&format!("{from}/")) {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        match (&inode, to_parent.get(to_name)) {
+            (_, None) => {}
+            (Inode::Directory(_), Some(Inode::Directory(directory))) if directory.is_empty() => {
+                directory.meta.links.set(0);
+                to_parent.meta.links.set(to_parent.meta.links.get() - 1);
+            }
+            (Inode::Directory(_), Some(Inode::Directory(_))) => return Err(OpenError::NotEmpty),
+            (Inode::Directory(_), Some(_)) => return Err(OpenError::NotADirectory),
+            (_, Some(Inode::Directory(_))) => return Err(OpenError::IsDirectory),
+            (_, Some(replaced)) => {
+                replaced.meta().links.set(replaced.meta().links.get() - 1);
+            }
+        }
+
+        from_parent.remove(from_name);
+        to_parent.insert(to_name, inode.clone());
+
+        // the `..` of a directory changes parent
+        if let Inode::Directory(_) = inode {
+            from_parent.meta.links.set(from_parent.meta.links.get() - 1);
+            to_parent.meta.links.set(to_parent.meta.links.get() + 1);
+        }
+
+        from_parent.meta.modified.set(now());
+        to_parent.meta.modified.set(now());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::set_clock;
+
+    fn fs() -> MemoryFs {
+        let fs = MemoryFs::new();
+
+        fs.mkdir("/etc").unwrap();
+        fs.mkdir("/home").unwrap();
+        fs.mkdir("/home/fritz").unwrap();
+        fs.create("/file").unwrap();
+        fs.open("/file").unwrap().write_at(0, "content").unwrap();
+
+        fs
+    }
+
+    #[test]
+    fn lookup() {
+        let fs = fs();
+
+        assert!(matches!(fs.lookup("/"), Ok(Inode::Directory(_))));
+        assert!(matches!(fs.lookup("/home/fritz"), Ok(Inode::Directory(_))));
+        assert!(matches!(fs.lookup("/missing"), Err(OpenError::NoEntry)));
+        assert!(matches!(
+            fs.lookup("/file/x"),
+            Err(OpenError::NotADirectory)
+        ));
+    }
+
+    #[test]
+    fn directories() {
+        let fs = fs();
+
+        assert!(fs.mkdir("/docs").is_ok());
+        assert!(fs.create("/docs/notes").is_ok());
+        assert_eq!(fs.create("/docs/notes"), Err(OpenError::AlreadyExists));
+        assert_eq!(fs.mkdir("/docs"), Err(OpenError::AlreadyExists));
+        assert_eq!(fs.mkdir("/missing/docs"), Err(OpenError::NoEntry));
+        assert_eq!(fs.mkdir("/file/docs"), Err(OpenError::NotADirectory));
+
+        assert_eq!(fs.rmdir("/docs"), Err(OpenError::NotEmpty));
+        assert_eq!(fs.rmdir("/docs/notes"), Err(OpenError::NotADirectory));
+        assert_eq!(fs.unlink("/docs"), Err(OpenError::IsDirectory));
+        assert_eq!(fs.unlink("/docs/notes"), Ok(()));
+        assert_eq!(fs.rmdir("/docs"), Ok(()));
+        assert!(matches!(fs.lookup("/docs"), Err(OpenError::NoEntry)));
+    }
+
+    #[test]
+    fn rename() {
+        let fs = fs();
+
+        fs.mkdir("/folder").unwrap();
+        fs.create("/folder/sub").unwrap();
+
+        assert_eq!(fs.rename("/file", "/etc/moved"), Ok(()));
+        assert!(matches!(fs.lookup("/file"), Err(OpenError::NoEntry)));
+        assert_eq!(
+            fs.open("/etc/moved").unwrap().read_at(0, 100),
+            Ok(("content".to_string(), 7))
+        );
+
+        assert_eq!(fs.rename("/etc/moved", "/folder/sub"), Ok(()));
+        assert_eq!(
+            fs.rename("/folder/sub", "/etc"),
+            Err(OpenError::IsDirectory)
+        );
+        assert_eq!(
+            fs.rename("/etc", "/folder/sub"),
+            Err(OpenError::NotADirectory)
+        );
+        assert_eq!(fs.rename("/etc", "/folder"), Err(OpenError::NotEmpty));
+        assert_eq!(
+            fs.rename("/home", "/home/fritz/home"),
+            Err(OpenError::InvalidArgument)
+        );
+        assert_eq!(fs.rename("/home", "/users"), Ok(()));
+        assert!(matches!(fs.lookup("/users/fritz"), Ok(Inode::Directory(_))));
+    }
+
+    #[test]
+    fn metadata() {
+        set_clock(|| 1000);
+
+        let fs = fs();
+        let root = fs.stat("/").unwrap();
+
+        fs.mkdir("/docs").unwrap();
+        fs.create("/docs/notes").unwrap();
+
+        let stat = fs.stat("/docs").unwrap();
+        assert_eq!(
+            (stat.kind, stat.size, stat.mode, stat.links),
+            (Kind::Directory, 1, 0o755, 2)
+        );
+        assert_eq!((stat.created, stat.modified), (1000, 1000));
+        assert_ne!(stat.ino, root.ino);
+        assert_eq!(fs.stat("/").unwrap().links, root.links + 1);
+
+        let stat = fs.stat("/docs/notes").unwrap();
+        assert_eq!(
+            (stat.kind, stat.size, stat.mode, stat.links),
+            (Kind::File, 0, 0o644, 1)
+        );
+
+        fs.chmod("/docs/notes", 0o600).unwrap();
+        fs.chown("/docs/notes", 1000, 100).unwrap();
+        let stat = fs.stat("/docs/notes").unwrap();
+        assert_eq!((stat.mode, stat.uid, stat.gid), (0o600, 1000, 100));
+
+        fs.rename("/docs", "/etc/docs").unwrap();
+        assert_eq!(fs.stat("/").unwrap().links, root.links);
+        assert_eq!(fs.stat("/etc").unwrap().links, 3);
+    }
+
+    #[test]
+    fn links() {
+        let fs = fs();
+
+        fs.symlink("/etc/profile", "/profile").unwrap();
+        assert_eq!(fs.stat("/profile").unwrap().kind, Kind::Symlink);
+        assert_eq!(fs.readlink("/profile"), Ok("/etc/profile".to_string()));
+        assert_eq!(fs.readlink("/file"), Err(OpenError::InvalidArgument));
+        assert_eq!(fs.symlink("x", "/profile"), Err(OpenError::AlreadyExists));
+        assert_eq!(fs.unlink("/profile"), Ok(()));
+
+        fs.link("/file", "/etc/hard").unwrap();
+        assert_eq!(fs.stat("/file").unwrap().links, 2);
+        assert_eq!(fs.link("/etc", "/etc2"), Err(OpenError::IsDirectory));
+        fs.unlink("/file").unwrap();
+
+        let stat = fs.stat("/etc/hard").unwrap();
+        assert_eq!((stat.links, stat.size), (1, "content".len()));
+    }
+
+    #[test]
+    fn offsets() {
+        let file = File::new("hello world", Metadata::new(1, FILE_MODE, 1, 0));
+
+        assert_eq!(file.read_at(6, 100), Ok(("world".to_string(), 5)));
+        assert_eq!(file.read_at(11, 100), Err(IOError::ChannelClosed));
+
+        file.write_at(6, "there").unwrap();
+        file.write_at(13, "!").unwrap();
+        assert_eq!(*file.content.borrow(), "hello there\0\0!");
+
+        file.truncate(5).unwrap();
+        assert_eq!(*file.content.borrow(), "hello");
+
+        // never splits a char
+        let file = File::new("äöü", Metadata::new(1, FILE_MODE, 1, 0));
+        assert_eq!(file.read_at(0, 3), Ok(("ä".to_string(), 2)));
+        assert_eq!(file.read_at(2, 1), Ok(("ö".to_string(), 2)));
+        assert_eq!(file.write_at(1, "a"), Err(IOError::InvalidOffset));
+        file.write_at(2, "o").unwrap();
+        assert_eq!(*file.content.borrow(), "äoü");
+        file.write_at(0, "abc").unwrap();
+        assert_eq!(*file.content.borrow(), "abcü");
+    }
+}
@@ -161,7 +161,17 @@ impl ReadableDescriptor for PipeReader {
     }
 
     fn read_char_sync(&self) -> Result<char, IOError> {
-        todo!()
+        let mut shared = self.shared.as_ref().borrow_mut();
+
+        if !shared.buffer.is_empty() {
+            Ok(shared.buffer.remove(0))
+        } else {
+
+            // If there are no writers left we will never have anything to read.
+            shared.is_open()?;
+
+            Err(IOError::Empty)
+        }
     }
 }
 
@@ -185,10 +195,210 @@ pub fn new_pipe() -> (PipeReader, PipeWriter) {
     (reader, writer)
 }
 
+// The pipe behind a FIFO, everyone who opens it shares the same buffer.
+// Opening one side waits until the other one is open too, see `FifoEnd::opened`.
+// A reader sees the end once every writer left, writing fails once every reader left.
+pub struct Fifo {
+    state: RefCell<FifoState>,
+}
+
+#[derive(Default)]
+struct FifoState {
+    buffer: String,
+    readers: usize,
+    writers: usize,
+    // opened since the pipe was last closed on both sides
+    read: bool,
+    written: bool,
+}
+
+impl Fifo {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Fifo {
+            state: RefCell::new(FifoState::default()),
+        })
+    }
+
+    pub fn open(self: &Rc<Self>, read: bool, write: bool) -> FifoEnd {
+        let mut state = self.state.borrow_mut();
+
+        if read {
+            state.readers += 1;
+            state.read = true;
+        }
+
+        if write {
+            state.writers += 1;
+            state.written = true;
+        }
+
+        FifoEnd {
+            fifo: Rc::clone(self),
+            read,
+            write,
+        }
+    }
+
+    fn poll_read(&self, len: u32) -> Poll<Result<String, IOError>> {
+        let mut state = self.state.borrow_mut();
+
+        if !state.buffer.is_empty() {
+            let mut end = (len as usize).min(state.buffer.len());
+
+            while !state.buffer.is_char_boundary(end) {
+                end += 1;
+            }
+
+            return Poll::Ready(Ok(state.buffer.drain(..end).collect()));
+        }
+
+        // a reader opened before the first writer waits for it
+        if state.writers == 0 && state.written {
+            Poll::Ready(Err(IOError::ChannelClosed))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+// One side of a FIFO, or both when it was opened for reading and writing
+pub struct FifoEnd {
+    fifo: Rc<Fifo>,
+    read: bool,
+    write: bool,
+}
+
+impl FifoEnd {
+    // Waits until the other side was opened too, both sides at once never wait
+    pub fn opened(&self) -> Pin<Box<dyn Future<Output = ()>>> {
+        Box::pin(OpeningTask {
+            fifo: Rc::clone(&self.fifo),
+            read: self.read,
+            write: self.write,
+        })
+    }
+}
+
+struct OpeningTask {
+    fifo: Rc<Fifo>,
+    read: bool,
+    write: bool,
+}
+
+impl Future for OpeningTask {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        let state = self.fifo.state.borrow();
+
+        // a writer that already left still counts, its data may be waiting
+        if (!self.read || state.written) && (!self.write || state.read) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+struct FifoReadingTask {
+    fifo: Rc<Fifo>,
+    len: u32,
+}
+
+impl Future for FifoReadingTask {
+    type Output = Result<String, IOError>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<String, IOError>> {
+        self.fifo.poll_read(self.len)
+    }
+}
+
+impl ReadableDescriptor for FifoEnd {
+    fn read(&self, len: u32) -> Pin<Box<dyn Future<Output = Result<String, IOError>>>> {
+        if !self.read {
+            return Box::pin(std::future::ready(Err(IOError::BadDescriptor)));
+        }
+
+        Box::pin(FifoReadingTask {
+            fifo: Rc::clone(&self.fifo),
+            len,
+        })
+    }
+
+    fn read_char(&self) -> Pin<Box<dyn Future<Output = Result<char, IOError>>>> {
+        let read = self.read(1);
+
+        Box::pin(async move { read.await?.chars().next().ok_or(IOError::ChannelClosed) })
+    }
+
+    fn read_sync(&self, len: u32) -> Result<String, IOError> {
+        if !self.read {
+            return Err(IOError::BadDescriptor);
+        }
+
+        match self.fifo.poll_read(len) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(IOError::Empty),
+        }
+    }
+
+    fn read_char_sync(&self) -> Result<char, IOError> {
+        let data = self.read_sync(1)?;
+
+        data.chars().next().ok_or(IOError::ChannelClosed)
+    }
+}
+
+impl WritableDescriptor for FifoEnd {
+    fn write(&self, data: &str) -> Result<(), IOError> {
+        if !self.write {
+            return Err(IOError::BadDescriptor);
+        }
+
+        let mut state = self.fifo.state.borrow_mut();
+
+        // nobody would ever read it
+        if state.readers == 0 {
+            return Err(IOError::ChannelClosed);
+        }
+
+        state.buffer.push_str(data);
+        Ok(())
+    }
+
+    fn write_char(&self, data: char) -> Result<(), IOError> {
+        self.write(data.encode_utf8(&mut [0; 4]))
+    }
+
+    fn clone(&self) -> Result<Self, IOError> {
+        Ok(self.fifo.open(self.read, self.write))
+    }
+}
+
+impl Drop for FifoEnd {
+    fn drop(&mut self) {
+        let mut state = self.fifo.state.borrow_mut();
+
+        if self.read {
+            state.readers -= 1;
+        }
+
+        if self.write {
+            state.writers -= 1;
+        }
+
+        // the next one to open it starts over
+        if state.readers == 0 && state.writers == 0 {
+            *state = FifoState::default();
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::fc::future::Executor;
     use super::*;
+    use std::cell::Cell;
 
     const STR_A: &str = "a";
     const STR_B: &str = "b";
@@ -254,6 +464,21 @@ mod test {
         assert_eq!(recv2, Err(IOError::ChannelClosed));
     }
 
+    #[test]
+    fn read_char_sync() {
+        let (rx, tx) = new_pipe();
+
+        assert_eq!(rx.read_char_sync(), Err(IOError::Empty));
+
+        tx.write("äb").unwrap();
+        assert_eq!(rx.read_char_sync(), Ok('ä'));
+
+        // the rest is still there
+        drop(tx);
+        assert_eq!(rx.read_char_sync(), Ok('b'));
+        assert_eq!(rx.read_char_sync(), Err(IOError::ChannelClosed));
+    }
+
     #[test]
     fn multiple_senders() {
         let (rx, tx) = new_pipe();
@@ -285,6 +510,64 @@ mod test {
         assert_eq!(recv, Ok(STR_AB.to_string()));
     }
 
+    #[test]
+    fn fifo() {
+        let fifo = Fifo::new();
+
+        // the reader waits for a writer instead of seeing the end
+        let reader = fifo.open(true, false);
+        assert_eq!(reader.read_sync(READ_SIZE), Err(IOError::Empty));
+
+        let writer = fifo.open(false, true);
+        writer.write(STR_AB).unwrap();
+        assert_eq!(Executor::block(reader.read(1)), Ok(STR_A.to_string()));
+        assert_eq!(reader.read_sync(READ_SIZE), Ok(STR_B.to_string()));
+        assert_eq!(writer.read_sync(READ_SIZE), Err(IOError::BadDescriptor));
+
+        drop(writer);
+        assert_eq!(Executor::block(reader.read(READ_SIZE)), Err(IOError::ChannelClosed));
+
+        let writer = fifo.open(false, true);
+        drop(reader);
+        assert_eq!(writer.write(STR_A), Err(IOError::ChannelClosed));
+    }
+
+    #[test]
+    fn fifo_opening() {
+        let fifo = Fifo::new();
+        let executor = Executor::new();
+        let opened = Rc::new(Cell::new(false));
+
+        // the writer waits for a reader and can't write until then
+        let writer = Rc::new(fifo.open(false, true));
+        assert_eq!(writer.write(STR_A), Err(IOError::ChannelClosed));
+
+        let waiting = Rc::clone(&writer);
+        let done = Rc::clone(&opened);
+        executor.add_task(async move {
+            waiting.opened().await;
+            waiting.write(STR_A).unwrap();
+            done.set(true);
+        });
+
+        executor.execute();
+        assert!(!opened.get());
+
+        let reader = fifo.open(true, false);
+        executor.execute();
+        assert!(opened.get());
+
+        // the reader was opened once a writer came, even if it left already
+        drop(writer);
+        Executor::block(reader.opened());
+        assert_eq!(reader.read_sync(READ_SIZE), Ok(STR_A.to_string()));
+        assert_eq!(reader.read_sync(READ_SIZE), Err(IOError::ChannelClosed));
+
+        // both sides at once are open already
+        drop(reader);
+        Executor::block(fifo.open(true, true).opened());
+    }
+
     #[test]
     fn read_char() {
         let (rx, tx) = new_pipe();
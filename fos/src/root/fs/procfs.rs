@@ -0,0 +1,411 @@
+// The processes and the state of the kernel as files in `/proc`, made when they are read.
+//
+// Every process has a directory named after its pid with `cmdline`, `status`, `environ`,
+// its working directory as the link `cwd` and its standard streams as links in `fd/`.
+// The files `uptime`, `tasks` and `mounts` are about the whole system.
+
+use std::rc::Rc;
+
+use super::{
+    descriptor::IOError,
+    file::{utf8_prefix, FileHandle},
+    memory::SYMLINK_MODE,
+    now, FileSystem, Kind, Mount, OpenError, Stat,
+};
+use crate::{Credentials, Pid, Proc, Process, ROOT};
+
+const GLOBALS: [&str; 3] = ["mounts", "tasks", "uptime"];
+const INFOS: [&str; 3] = ["cmdline", "environ", "status"];
+
+// Everything can be read, nothing written
+const DIRECTORY_MODE: u16 = 0o555;
+const FILE_MODE: u16 = 0o444;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Node {
+    Root,
+    // a file about the whole system
+    Global(&'static str),
+    Process(Pid),
+    // a file about a process
+    Info(Pid, &'static str),
+    Cwd(Pid),
+    Fds(Pid),
+    Fd(Pid, u32),
+}
+
+impl Node {
+    fn kind(self) -> Kind {
+        match self {
+            Node::Root | Node::Process(_) | Node::Fds(_) => Kind::Directory,
+            Node::Global(_) | Node::Info(..) => Kind::File,
+            Node::Cwd(_) | Node::Fd(..) => Kind::Symlink,
+        }
+    }
+
+    // Fixed by the pid and the name, so they don't change between reads
+    fn ino(self) -> u64 {
+        let index = |names: &[&str], name| names.iter().position(|other| *other == name);
+
+        match self {
+            Node::Root => 1,
+            Node::Global(name) => 2 + index(&GLOBALS, name).unwrap_or(0) as u64,
+            Node::Process(pid) => (pid as u64 + 1) * 16,
+            Node::Info(pid, name) => {
+                (pid as u64 + 1) * 16 + 1 + index(&INFOS, name).unwrap_or(0) as u64
+            }
+            Node::Cwd(pid) => (pid as u64 + 1) * 16 + 4,
+            Node::Fds(pid) => (pid as u64 + 1) * 16 + 5,
+            Node::Fd(pid, fd) => (pid as u64 + 1) * 16 + 6 + fd as u64,
+        }
+    }
+}
+
+// The text of a file as it was when it was opened
+struct Snapshot(String);
+
+impl FileHandle for Snapshot {
+    fn read_at(&self, offset: usize, len: usize) -> Result<(String, usize), IOError> {
+        match self.0.as_bytes().get(offset..) {
+            Some(bytes) if !bytes.is_empty() => Ok(utf8_prefix(bytes, len)),
+            _ => Err(IOError::ChannelClosed),
+        }
+    }
+
+    fn write_at(&self, _offset: usize, _data: &str) -> Result<(), IOError> {
+        Err(IOError::BadDescriptor)
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+
+    fn truncate(&self, _size: usize) -> Result<(), IOError> {
+        Err(IOError::BadDescriptor)
+    }
+}
+
+pub struct ProcFs;
+
+impl ProcFs {
+    fn resolve(&self, path: &str) -> Result<Node, OpenError> {
+        let names: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+
+        let node = match names[..] {
+            [] => Node::Root,
+            [name] => match GLOBALS.iter().find(|global| **global == name) {
+                Some(global) => Node::Global(global),
+                None => Node::Process(parse(name)?),
+            },
+            [pid, "cwd"] => Node::Cwd(parse(pid)?),
+            [pid, "fd"] => Node::Fds(parse(pid)?),
+            [pid, name] => match INFOS.iter().find(|info| **info == name) {
+                Some(info) => Node::Info(parse(pid)?, info),
+                None => return Err(OpenError::NoEntry),
+            },
+            [pid, "fd", fd] => Node::Fd(parse(pid)?, parse(fd)?),
+            _ => return Err(OpenError::NoEntry),
+        };
+
+        // the process has to be alive, and the stream open
+        match node {
+            Node::Root | Node::Global(_) => {}
+            Node::Fd(pid, fd) => {
+                let process = find(pid)?;
+
+                if !streams(process.get_proc())
+                    .iter()
+                    .any(|(other, _)| *other == fd)
+                {
+                    return Err(OpenError::NoEntry);
+                }
+            }
+            Node::Process(pid) | Node::Info(pid, _) | Node::Cwd(pid) | Node::Fds(pid) => {
+                find(pid)?;
+            }
+        }
+
+        Ok(node)
+    }
+
+    fn node_stat(&self, node: Node) -> Result<Stat, OpenError> {
+        let (size, mode, links) = match node.kind() {
+            Kind::Directory => (0, DIRECTORY_MODE, 2),
+            Kind::Symlink => (self.target(node)?.len(), SYMLINK_MODE, 1),
+            // the content is only known when it's read
+            _ => (0, FILE_MODE, 1),
+        };
+
+        // the files of a process belong to its user
+        let credentials = match node {
+            Node::Root | Node::Global(_) => Credentials::root(),
+            Node::Process(pid)
+            | Node::Info(pid, _)
+            | Node::Cwd(pid)
+            | Node::Fds(pid)
+            | Node::Fd(pid, _) => find(pid)?.get_proc().credentials(),
+        };
+
+        let time = now();
+
+        Ok(Stat {
+            ino: node.ino(),
+            kind: node.kind(),
+            size,
+            mode,
+            uid: credentials.uid,
+            gid: credentials.gid,
+            links,
+            created: time,
+            modified: time,
+            accessed: time,
+            device: 0,
+        })
+    }
+
+    fn target(&self, node: Node) -> Result<String, OpenError> {
+        match node {
+            Node::Cwd(pid) => Ok(find(pid)?.get_proc().cwd.borrow().clone()),
+            Node::Fd(pid, fd) => streams(find(pid)?.get_proc())
+                .into_iter()
+                .find(|(other, _)| *other == fd)
+                .map(|(_, target)| target)
+                .ok_or(OpenError::NoEntry),
+            _ => Err(OpenError::InvalidArgument),
+        }
+    }
+
+    fn content(&self, node: Node) -> Result<String, OpenError> {
+        let content = match node {
+            Node::Global("uptime") => uptime(ROOT.uptime()),
+            Node::Global("tasks") => format!("{}\n", ROOT.executor.task_count()),
+            Node::Global(_) => mounts(&ROOT.fs.mounts()),
+            Node::Info(pid, name) => {
+                let process = find(pid)?;
+
+                match name {
+                    "cmdline" => cmdline(process.get_proc()),
+                    "environ" => environ(process.get_proc()),
+                    _ => status(process.as_ref()),
+                }
+            }
+            _ => return Err(OpenError::IsDirectory),
+        };
+
+        Ok(content)
+    }
+}
+
+impl FileSystem for ProcFs {
+    fn name(&self) -> &str {
+        "proc"
+    }
+
+    fn stat(&self, path: &str) -> Result<Stat, OpenError> {
+        self.node_stat(self.resolve(path)?)
+    }
+
+    fn open(&self, path: &str) -> Result<Rc<dyn FileHandle>, OpenError> {
+        let content = self.content(self.resolve(path)?)?;
+
+        Ok(Rc::new(Snapshot(content)))
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<(String, Stat)>, OpenError> {
+        let node = self.resolve(path)?;
+
+        let children: Vec<(String, Node)> = match node {
+            Node::Root => {
+                let mut pids: Vec<Pid> = ROOT
+                    .spawner
+                    .processes()
+                    .iter()
+                    .map(|process| process.get_proc().pid)
+                    .collect();
+                pids.sort();
+
+                pids.into_iter()
+                    .map(|pid| (pid.to_string(), Node::Process(pid)))
+                    .chain(GLOBALS.map(|name| (name.to_string(), Node::Global(name))))
+                    .collect()
+            }
+            Node::Process(pid) => vec![
+                ("cmdline".to_string(), Node::Info(pid, "cmdline")),
+                ("cwd".to_string(), Node::Cwd(pid)),
+                ("environ".to_string(), Node::Info(pid, "environ")),
+                ("fd".to_string(), Node::Fds(pid)),
+                ("status".to_string(), Node::Info(pid, "status")),
+            ],
+            Node::Fds(pid) => streams(find(pid)?.get_proc())
+                .into_iter()
+                .map(|(fd, _)| (fd.to_string(), Node::Fd(pid, fd)))
+                .collect(),
+            _ => return Err(OpenError::NotADirectory),
+        };
+
+        children
+            .into_iter()
+            .map(|(name, child)| Ok((name, self.node_stat(child)?)))
+            .collect()
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, OpenError> {
+        self.target(self.resolve(path)?)
+    }
+}
+
+fn parse(name: &str) -> Result<u32, OpenError> {
+    name.parse().map_err(|_| OpenError::NoEntry)
+}
+
+fn find(pid: Pid) -> Result<Rc<dyn Process>, OpenError> {
+    ROOT.spawner.process(pid).ok_or(OpenError::NoEntry)
+}
+
+// The standard streams that are open, with what they are connected to
+fn streams(proc: &Proc) -> Vec<(u32, String)> {
+    [
+        (0, proc.stdin.share().map(|reader| reader.source())),
+        (1, proc.stdout.share().map(|writer| writer.target())),
+        (2, proc.stderr.share().map(|writer| writer.target())),
+    ]
+    .into_iter()
+    .filter_map(|(fd, target)| Some((fd, target?)))
+    .collect()
+}
+
+// Seconds with two decimals, like `12.34`
+fn uptime(millis: u64) -> String {
+    format!("{}.{:02}\n", millis / 1000, millis % 1000 / 10)
+}
+
+// Like `/etc/fstab`: the source, where it's mounted, the type and the options
+fn mounts(mounts: &[Mount]) -> String {
+    mounts
+        .iter()
+        .map(|mount| {
+            let options = if mount.read_only { "ro" } else { "rw" };
+
+            format!(
+                "{} {} {} {options} 0 0\n",
+                mount.source,
+                mount.path,
+                mount.fs.name()
+            )
+        })
+        .collect()
+}
+
+// One argument per line
+fn cmdline(proc: &Proc) -> String {
+    proc.cmdline
+        .borrow()
+        .iter()
+        .map(|arg| format!("{arg}\n"))
+        .collect()
+}
+
+fn environ(proc: &Proc) -> String {
+    proc.env
+        .borrow()
+        .iter()
+        .map(|(name, value)| format!("{name}={value}\n"))
+        .collect()
+}
+
+// A process that exited stays a zombie until its parent waits for it
+fn status(process: &dyn Process) -> String {
+    let proc = process.get_proc();
+
+    let state = match proc.exit_code() {
+        None => "R (running)",
+        Some(_) => "Z (zombie)",
+    };
+
+    let credentials = proc.credentials();
+
+    format!(
+        "Name:\t{}\nState:\t{state}\nPid:\t{}\nPPid:\t{}\nUid:\t{}\nGid:\t{}\n",
+        process.get_process_name(),
+        proc.pid,
+        proc.ppid.get(),
+        credentials.uid,
+        credentials.gid
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::MemoryFs;
+
+    struct Sleeper {
+        proc: Proc,
+    }
+
+    impl Process for Sleeper {
+        fn new(proc: Proc) -> Self {
+            Sleeper { proc }
+        }
+
+        fn get_process_name(&self) -> &str {
+            "sleep"
+        }
+
+        fn get_proc(&self) -> &Proc {
+            &self.proc
+        }
+
+        fn main(self: Rc<Self>, _args: Vec<&str>) {}
+    }
+
+    #[test]
+    fn process_files() {
+        let sleeper = Sleeper::new(Proc::new(4));
+        sleeper.proc.ppid.set(2);
+        *sleeper.proc.cmdline.borrow_mut() = vec!["sleep".to_string(), "10".to_string()];
+        sleeper
+            .proc
+            .env
+            .borrow_mut()
+            .insert("HOME".to_string(), "/root".to_string());
+
+        assert_eq!(cmdline(&sleeper.proc), "sleep\n10\n");
+        assert_eq!(environ(&sleeper.proc), "HOME=/root\n");
+        assert_eq!(
+            status(&sleeper),
+            "Name:\tsleep\nState:\tR (running)\nPid:\t4\nPPid:\t2\nUid:\t0\nGid:\t0\n"
+        );
+
+        assert_eq!(
+            streams(&sleeper.proc),
+            [
+                (0, "pipe".to_string()),
+                (1, "pipe".to_string()),
+                (2, "pipe".to_string())
+            ]
+        );
+
+        sleeper.proc.exit(3);
+        assert!(status(&sleeper).contains("Z (zombie)"));
+        assert_eq!(streams(&sleeper.proc), []);
+    }
+
+    #[test]
+    fn system_files() {
+        assert_eq!(uptime(61_234), "61.23\n");
+
+        let mount = Mount {
+            path: "/mnt".to_string(),
+            source: "/dev/ram0".to_string(),
+            fs: Rc::new(MemoryFs::new()),
+            read_only: true,
+        };
+        assert_eq!(mounts(&[mount]), "/dev/ram0 /mnt tmpfs ro 0 0\n");
+
+        let file = Snapshot("status".to_string());
+        assert_eq!(file.read_at(2, 3), Ok(("atu".to_string(), 3)));
+        assert_eq!(file.read_at(6, 3), Err(IOError::ChannelClosed));
+        assert_eq!(file.write_at(0, "x"), Err(IOError::BadDescriptor));
+    }
+}
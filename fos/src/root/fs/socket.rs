@@ -0,0 +1,279 @@
+// Local sockets, found by the path of the special file they are bound to.
+//
+// A stream socket listens for connections, every accepted one is a pair of pipes going
+// both ways. A datagram socket receives whole messages with the path of their sender.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    pin::Pin,
+    rc::Rc,
+};
+
+use crate::fc::future::{Context, Future, Poll};
+
+use super::{
+    descriptor::{IOError, ReadableDescriptor, WritableDescriptor},
+    pipe::{new_pipe, PipeReader, PipeWriter},
+    OpenError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SocketKind {
+    // connections carrying a stream of bytes
+    Stream,
+    // single messages to any bound socket
+    Datagram,
+}
+
+// The path of the sender and the text
+pub type Message = (String, String);
+
+pub struct Socket {
+    kind: SocketKind,
+    // where it's bound, empty until then
+    path: RefCell<String>,
+    listening: Cell<bool>,
+    // connections that weren't accepted yet
+    backlog: RefCell<VecDeque<Connection>>,
+    messages: RefCell<VecDeque<Message>>,
+}
+
+impl Socket {
+    pub fn new(kind: SocketKind) -> Rc<Self> {
+        Rc::new(Socket {
+            kind,
+            path: RefCell::new(String::new()),
+            listening: Cell::new(false),
+            backlog: RefCell::new(VecDeque::new()),
+            messages: RefCell::new(VecDeque::new()),
+        })
+    }
+
+    pub fn kind(&self) -> SocketKind {
+        self.kind
+    }
+
+    pub fn path(&self) -> String {
+        self.path.borrow().clone()
+    }
+
+    // Called by the VFS once the special file exists, a socket is bound only once
+    pub(super) fn bind(&self, path: &str) -> Result<(), OpenError> {
+        let mut bound = self.path.borrow_mut();
+
+        if !bound.is_empty() {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        *bound = path.to_string();
+        Ok(())
+    }
+
+    // Accepts connections from now on, the socket has to be bound
+    pub fn listen(&self) -> Result<(), OpenError> {
+        if self.kind != SocketKind::Stream || self.path.borrow().is_empty() {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        self.listening.set(true);
+        Ok(())
+    }
+
+    // Waits for the next connection
+    pub fn accept(self: &Rc<Self>) -> Pin<Box<dyn Future<Output = Result<Connection, IOError>>>> {
+        Box::pin(AcceptingTask {
+            socket: Rc::clone(self),
+        })
+    }
+
+    // Waits for the next message, with the path of the socket that sent it
+    pub fn receive(self: &Rc<Self>) -> Pin<Box<dyn Future<Output = Result<Message, IOError>>>> {
+        Box::pin(ReceivingTask {
+            socket: Rc::clone(self),
+        })
+    }
+
+    // The end of a new connection for the client, the other one waits to be accepted
+    pub(super) fn connect(&self) -> Result<Connection, OpenError> {
+        if !self.listening.get() {
+            return Err(OpenError::ConnectionRefused);
+        }
+
+        let (client, server) = Connection::pair();
+        self.backlog.borrow_mut().push_back(server);
+
+        Ok(client)
+    }
+
+    pub(super) fn deliver(&self, from: &str, message: &str) -> Result<(), OpenError> {
+        if self.kind != SocketKind::Datagram {
+            return Err(OpenError::ConnectionRefused);
+        }
+
+        self.messages
+            .borrow_mut()
+            .push_back((from.to_string(), message.to_string()));
+
+        Ok(())
+    }
+}
+
+struct AcceptingTask {
+    socket: Rc<Socket>,
+}
+
+impl Future for AcceptingTask {
+    type Output = Result<Connection, IOError>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<Connection, IOError>> {
+        if !self.socket.listening.get() {
+            return Poll::Ready(Err(IOError::BadDescriptor));
+        }
+
+        match self.socket.backlog.borrow_mut().pop_front() {
+            Some(connection) => Poll::Ready(Ok(connection)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+struct ReceivingTask {
+    socket: Rc<Socket>,
+}
+
+impl Future for ReceivingTask {
+    type Output = Result<Message, IOError>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<Message, IOError>> {
+        if self.socket.kind != SocketKind::Datagram {
+            return Poll::Ready(Err(IOError::BadDescriptor));
+        }
+
+        match self.socket.messages.borrow_mut().pop_front() {
+            Some(message) => Poll::Ready(Ok(message)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+// One end of a stream connection, it ends when the other one is dropped.
+// Clones share the end, like a duplicated descriptor.
+pub struct Connection {
+    reader: Rc<PipeReader>,
+    writer: PipeWriter,
+}
+
+impl Connection {
+    fn pair() -> (Connection, Connection) {
+        let (first_reader, second_writer) = new_pipe();
+        let (second_reader, first_writer) = new_pipe();
+
+        let first = Connection {
+            reader: Rc::new(first_reader),
+            writer: first_writer,
+        };
+
+        let second = Connection {
+            reader: Rc::new(second_reader),
+            writer: second_writer,
+        };
+
+        (first, second)
+    }
+}
+
+impl ReadableDescriptor for Connection {
+    fn read(&self, len: u32) -> Pin<Box<dyn Future<Output = Result<String, IOError>>>> {
+        self.reader.read(len)
+    }
+
+    fn read_char(&self) -> Pin<Box<dyn Future<Output = Result<char, IOError>>>> {
+        self.reader.read_char()
+    }
+
+    fn read_sync(&self, len: u32) -> Result<String, IOError> {
+        self.reader.read_sync(len)
+    }
+
+    fn read_char_sync(&self) -> Result<char, IOError> {
+        self.reader.read_char_sync()
+    }
+}
+
+impl WritableDescriptor for Connection {
+    fn write(&self, str: &str) -> Result<(), IOError> {
+        self.writer.write(str)
+    }
+
+    fn write_char(&self, char: char) -> Result<(), IOError> {
+        self.writer.write_char(char)
+    }
+
+    fn clone(&self) -> Result<Self, IOError> {
+        Ok(Connection {
+            reader: Rc::clone(&self.reader),
+            writer: self.writer.clone()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fc::future::Executor;
+
+    #[test]
+    fn stream() {
+        let server = Socket::new(SocketKind::Stream);
+        assert_eq!(server.listen(), Err(OpenError::InvalidArgument));
+
+        server.bind("/server").unwrap();
+        assert_eq!(server.bind("/other"), Err(OpenError::InvalidArgument));
+        assert_eq!(server.connect().err(), Some(OpenError::ConnectionRefused));
+
+        server.listen().unwrap();
+        let client = server.connect().unwrap();
+        let accepted = Executor::block(server.accept()).unwrap();
+
+        // both ways
+        client.write("ping").unwrap();
+        assert_eq!(Executor::block(accepted.read(100)), Ok("ping".to_string()));
+        accepted.write("pong").unwrap();
+        assert_eq!(Executor::block(client.read(100)), Ok("pong".to_string()));
+
+        // one char at a time without waiting
+        assert_eq!(accepted.read_char_sync(), Err(IOError::Empty));
+        client.write("ok").unwrap();
+        assert_eq!(accepted.read_char_sync(), Ok('o'));
+        assert_eq!(accepted.read_sync(100), Ok("k".to_string()));
+
+        drop(accepted);
+        assert_eq!(client.read_sync(100), Err(IOError::ChannelClosed));
+        assert_eq!(client.write("lost"), Err(IOError::ChannelClosed));
+    }
+
+    #[test]
+    fn datagram() {
+        let socket = Socket::new(SocketKind::Datagram);
+        socket.bind("/log").unwrap();
+        assert_eq!(socket.listen(), Err(OpenError::InvalidArgument));
+        assert_eq!(socket.connect().err(), Some(OpenError::ConnectionRefused));
+
+        socket.deliver("/client", "first").unwrap();
+        socket.deliver("", "second").unwrap();
+
+        // messages keep their boundaries
+        let received = Executor::block(socket.receive());
+        assert_eq!(received, Ok(("/client".to_string(), "first".to_string())));
+        let received = Executor::block(socket.receive());
+        assert_eq!(received, Ok((String::new(), "second".to_string())));
+
+        let stream = Socket::new(SocketKind::Stream);
+        assert_eq!(stream.deliver("", "x"), Err(OpenError::ConnectionRefused));
+        assert_eq!(
+            Executor::block(stream.receive()),
+            Err(IOError::BadDescriptor)
+        );
+    }
+}
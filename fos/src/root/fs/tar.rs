@@ -0,0 +1,358 @@
+// Archives in the ustar format of `tar`, the initial files of fos come from one.
+//
+// An archive is a list of members: a header of `BLOCK_SIZE` bytes followed by the data,
+// padded to whole blocks. Two blocks of zeros end it. Numbers in a header are octal text.
+// A path longer than 100 bytes is split at a `/` into a prefix and a name.
+// The extended headers of pax and GNU tar are skipped, long names in them are lost.
+
+use super::{FileSystem, Kind, OpenError};
+use crate::{Gid, Uid};
+
+const BLOCK_SIZE: usize = 512;
+
+const NAME: usize = 100;
+const PREFIX: usize = 155;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Content {
+    File(Vec<u8>),
+    Directory,
+    Symlink(String),
+    // a hard link to an earlier member
+    Link(String),
+    BlockDevice(u32),
+    CharDevice(u32),
+    Fifo,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    // relative to the root of the archive, without a `/` at the end
+    pub path: String,
+    pub mode: u16,
+    pub uid: Uid,
+    pub gid: Gid,
+    // milliseconds since the unix epoch, tar only keeps seconds
+    pub modified: u64,
+    pub content: Content,
+}
+
+pub fn read(archive: &[u8]) -> Result<Vec<Member>, OpenError> {
+    let mut members = vec![];
+    let mut offset = 0;
+
+    while let Some(header) = archive.get(offset..offset + BLOCK_SIZE) {
+        if header.iter().all(|&byte| byte == 0) {
+            break;
+        }
+
+        // GNU tar writes `ustar  ` instead of `ustar\0`
+        if &header[257..262] != b"ustar" || number(&header[148..156])? != checksum(header) {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        let size = number(&header[124..136])? as usize;
+        let start = offset + BLOCK_SIZE;
+        let data = archive
+            .get(start..start + size)
+            .ok_or(OpenError::InvalidArgument)?;
+
+        offset = start + size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+
+        let path = match text(&header[345..500]) {
+            prefix if prefix.is_empty() => text(&header[..NAME]),
+            prefix => format!("{prefix}/{}", text(&header[..NAME])),
+        };
+
+        let content = match header[156] {
+            b'0' | 0 | b'7' => Content::File(data.to_vec()),
+            b'1' => Content::Link(normalize(&text(&header[157..257]))),
+            b'2' => Content::Symlink(text(&header[157..257])),
+            b'3' => Content::CharDevice(number(&header[337..345])? as u32),
+            b'4' => Content::BlockDevice(number(&header[337..345])? as u32),
+            b'5' => Content::Directory,
+            b'6' => Content::Fifo,
+            // extended headers
+            _ => continue,
+        };
+
+        let path = normalize(&path);
+
+        // the root itself, and members that would be outside of it
+        if path.is_empty() || path.split('/').any(|name| name == "..") {
+            continue;
+        }
+
+        members.push(Member {
+            path,
+            mode: (number(&header[100..108])? & 0o7777) as u16,
+            uid: number(&header[108..116])? as Uid,
+            gid: number(&header[116..124])? as Gid,
+            modified: number(&header[136..148])? * 1000,
+            content,
+        });
+    }
+
+    Ok(members)
+}
+
+pub fn write(members: &[Member]) -> Result<Vec<u8>, OpenError> {
+    let mut archive = vec![];
+
+    for member in members {
+        let mut header = [0; BLOCK_SIZE];
+
+        let (kind, data, link): (u8, &[u8], &str) = match &member.content {
+            Content::File(data) => (b'0', data, ""),
+            Content::Link(existing) => (b'1', &[], existing),
+            Content::Symlink(target) => (b'2', &[], target),
+            Content::CharDevice(_) => (b'3', &[], ""),
+            Content::BlockDevice(_) => (b'4', &[], ""),
+            Content::Directory => (b'5', &[], ""),
+            Content::Fifo => (b'6', &[], ""),
+        };
+
+        let path = match member.content {
+            Content::Directory => format!("{}/", member.path),
+            _ => member.path.clone(),
+        };
+        let (prefix, name) = split(&path)?;
+
+        if link.len() > NAME {
+            return Err(OpenError::InvalidArgument);
+        }
+
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        put_number(&mut header[100..108], member.mode as u64)?;
+        put_number(&mut header[108..116], member.uid as u64)?;
+        put_number(&mut header[116..124], member.gid as u64)?;
+        put_number(&mut header[124..136], data.len() as u64)?;
+        put_number(&mut header[136..148], member.modified / 1000)?;
+        header[156] = kind;
+        header[157..157 + link.len()].copy_from_slice(link.as_bytes());
+        header[257..265].copy_from_slice(b"ustar\x0000");
+
+        // only the name of root is known everywhere
+        if member.uid == 0 {
+            header[265..269].copy_from_slice(b"root");
+        }
+        if member.gid == 0 {
+            header[297..301].copy_from_slice(b"root");
+        }
+
+        if let Content::BlockDevice(number) | Content::CharDevice(number) = member.content {
+            put_number(&mut header[329..337], 0)?;
+            put_number(&mut header[337..345], number as u64)?;
+        }
+
+        header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+        let checksum = format!("{:06o}\0 ", checksum(&header));
+        header[148..156].copy_from_slice(checksum.as_bytes());
+
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(data);
+        archive.resize(archive.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+    }
+
+    archive.resize(archive.len() + 2 * BLOCK_SIZE, 0);
+
+    Ok(archive)
+}
+
+// Creates the members inside of `fs`, directories that are already there are kept
+pub fn extract(fs: &dyn FileSystem, members: &[Member]) -> Result<(), OpenError> {
+    for member in members {
+        let path = format!("/{}", member.path);
+
+        match &member.content {
+            Content::File(data) => {
+                fs.create(&path)?;
+                fs.open(&path)?
+                    .write_at(0, &String::from_utf8_lossy(data))
+                    .map_err(|_| OpenError::NoSpace)?;
+            }
+            Content::Directory => match fs.mkdir(&path) {
+                Ok(()) | Err(OpenError::AlreadyExists) => {}
+                Err(err) => return Err(err),
+            },
+            Content::Symlink(target) => fs.symlink(target, &path)?,
+            Content::Link(existing) => fs.link(&format!("/{existing}"), &path)?,
+            Content::BlockDevice(number) => fs.mknod(&path, Kind::BlockDevice, *number)?,
+            Content::CharDevice(number) => fs.mknod(&path, Kind::CharDevice, *number)?,
+            Content::Fifo => fs.mknod(&path, Kind::Fifo, 0)?,
+        }
+
+        // links have no mode, and not every filesystem has owners
+        if !matches!(member.content, Content::Symlink(_) | Content::Link(_)) {
+            ignore_unsupported(fs.chmod(&path, member.mode))?;
+        }
+        ignore_unsupported(fs.chown(&path, member.uid, member.gid))?;
+    }
+
+    Ok(())
+}
+
+fn ignore_unsupported(result: Result<(), OpenError>) -> Result<(), OpenError> {
+    match result {
+        Err(OpenError::NotSupported) => Ok(()),
+        result => result,
+    }
+}
+
+fn normalize(path: &str) -> String {
+    path.split('/')
+        .filter(|name| !name.is_empty() && *name != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// The prefix and the name fields of a path
+fn split(path: &str) -> Result<(&str, &str), OpenError> {
+    if path.len() <= NAME {
+        return Ok(("", path));
+    }
+
+    path.match_indices('/')
+        .map(|(index, _)| (&path[..index], &path[index + 1..]))
+        .find(|(prefix, name)| prefix.len() <= PREFIX && !name.is_empty() && name.len() <= NAME)
+        .ok_or(OpenError::InvalidArgument)
+}
+
+// Up to the first 0
+fn text(field: &[u8]) -> String {
+    let end = field
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(field.len());
+
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn number(field: &[u8]) -> Result<u64, OpenError> {
+    let text = text(field);
+    let digits = text.trim_matches(' ');
+
+    if digits.is_empty() {
+        return Ok(0);
+    }
+
+    u64::from_str_radix(digits, 8).map_err(|_| OpenError::InvalidArgument)
+}
+
+// Octal digits and a 0 at the end
+fn put_number(field: &mut [u8], value: u64) -> Result<(), OpenError> {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+
+    if digits.len() >= field.len() {
+        return Err(OpenError::InvalidArgument);
+    }
+
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+
+    Ok(())
+}
+
+// The sum of the bytes of the header, with the checksum itself as spaces
+fn checksum(header: &[u8]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(index, &byte)| match index {
+            148..156 => b' ' as u64,
+            _ => byte as u64,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::MemoryFs;
+
+    fn member(path: &str, content: Content) -> Member {
+        Member {
+            path: path.to_string(),
+            mode: 0o644,
+            uid: 1000,
+            gid: 100,
+            modified: 1_700_000_000_000,
+            content,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let long = format!("{}/{}", "directory".repeat(12), "file".repeat(20));
+
+        let members = vec![
+            member("etc", Content::Directory),
+            member("etc/profile", Content::File(b"alias l=ls\n".to_vec())),
+            member("etc/link", Content::Symlink("profile".to_string())),
+            member("etc/hard", Content::Link("etc/profile".to_string())),
+            member("ram", Content::BlockDevice(3)),
+            member("null", Content::CharDevice(0)),
+            member("pipe", Content::Fifo),
+            member(&long, Content::File(vec![b'x'; 1000])),
+        ];
+
+        let archive = write(&members).unwrap();
+        assert_eq!(archive.len(), 13 * BLOCK_SIZE);
+        assert_eq!(read(&archive), Ok(members));
+
+        let too_long = member(&"a".repeat(300), Content::Directory);
+        assert_eq!(write(&[too_long]), Err(OpenError::InvalidArgument));
+    }
+
+    #[test]
+    fn paths() {
+        let members = vec![
+            member(".", Content::Directory),
+            member("./etc", Content::Directory),
+            member("../outside", Content::File(vec![])),
+        ];
+
+        let archive = write(&members).unwrap();
+        let paths: Vec<String> = read(&archive)
+            .unwrap()
+            .into_iter()
+            .map(|member| member.path)
+            .collect();
+        assert_eq!(paths, ["etc"]);
+
+        let mut corrupted = archive.clone();
+        corrupted[0] = b'X';
+        assert_eq!(read(&corrupted), Err(OpenError::InvalidArgument));
+
+        // the data of the last member is missing
+        let archive = write(&[member("file", Content::File(vec![1; 600]))]).unwrap();
+        assert_eq!(
+            read(&archive[..BLOCK_SIZE * 2]),
+            Err(OpenError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn extract_members() {
+        let fs = MemoryFs::new();
+
+        let members = vec![
+            member("home", Content::Directory),
+            member("home/notes", Content::File(b"todo".to_vec())),
+            member("home", Content::Directory),
+            member("home/link", Content::Symlink("notes".to_string())),
+        ];
+
+        extract(&fs, &members).unwrap();
+
+        assert_eq!(
+            fs.open("/home/notes").unwrap().read_at(0, 10),
+            Ok(("todo".to_string(), 4))
+        );
+        assert_eq!(fs.readlink("/home/link"), Ok("notes".to_string()));
+
+        let stat = fs.stat("/home/notes").unwrap();
+        assert_eq!((stat.mode, stat.uid, stat.gid), (0o644, 1000, 100));
+    }
+}
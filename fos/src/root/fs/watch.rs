@@ -0,0 +1,155 @@
+// Watches on paths, told by the VFS about every change it makes.
+//
+// A watch on a file sees the file itself, one on a directory also sees the entries
+// directly inside of it. Events wait in the watch until its process takes them.
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    pin::Pin,
+    rc::{Rc, Weak},
+};
+
+use super::split_parent;
+use crate::fc::future::{Context, Future, Poll};
+
+// What happened, with the absolute paths of the entries after resolving links
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    Created(String),
+    // the content was written or truncated
+    Modified(String),
+    Deleted(String),
+    // from the first path to the second one
+    Renamed(String, String),
+}
+
+impl Event {
+    // Whether a watch on `path` sees it
+    fn concerns(&self, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        let seen = |other: &str| {
+            other.trim_end_matches('/') == path
+                || split_parent(other).0.trim_end_matches('/') == path
+        };
+
+        match self {
+            Event::Created(other) | Event::Modified(other) | Event::Deleted(other) => seen(other),
+            Event::Renamed(from, to) => seen(from) || seen(to),
+        }
+    }
+}
+
+// Stops watching when it's dropped
+pub struct Watch {
+    path: String,
+    events: RefCell<VecDeque<Event>>,
+}
+
+impl Watch {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // Waits for the next change
+    pub fn next(self: &Rc<Self>) -> Pin<Box<dyn Future<Output = Event>>> {
+        Box::pin(WatchingTask {
+            watch: Rc::clone(self),
+        })
+    }
+
+    // The next change if there was one already
+    pub fn try_next(&self) -> Option<Event> {
+        self.events.borrow_mut().pop_front()
+    }
+}
+
+struct WatchingTask {
+    watch: Rc<Watch>,
+}
+
+impl Future for WatchingTask {
+    type Output = Event;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Event> {
+        match self.watch.try_next() {
+            Some(event) => Poll::Ready(event),
+            None => Poll::Pending,
+        }
+    }
+}
+
+// Every watch of the VFS
+#[derive(Default)]
+pub struct Watchers {
+    watches: RefCell<Vec<Weak<Watch>>>,
+}
+
+impl Watchers {
+    // `path` has to be absolute
+    pub fn add(&self, path: &str) -> Rc<Watch> {
+        let watch = Rc::new(Watch {
+            path: path.to_string(),
+            events: RefCell::new(VecDeque::new()),
+        });
+
+        self.watches.borrow_mut().push(Rc::downgrade(&watch));
+
+        watch
+    }
+
+    pub fn notify(&self, event: Event) {
+        let mut watches = self.watches.borrow_mut();
+
+        // the dropped ones are forgotten
+        watches.retain(|watch| watch.strong_count() > 0);
+
+        for watch in watches.iter().filter_map(Weak::upgrade) {
+            if event.concerns(&watch.path) {
+                watch.events.borrow_mut().push_back(event.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fc::future::Executor;
+
+    #[test]
+    fn events() {
+        let path = |path: &str| path.to_string();
+
+        let watchers = Watchers::default();
+        let directory = watchers.add("/etc");
+        let file = watchers.add("/etc/profile");
+
+        watchers.notify(Event::Created(path("/etc/hosts")));
+        watchers.notify(Event::Modified(path("/etc/profile")));
+        // too deep for the directory
+        watchers.notify(Event::Deleted(path("/etc/init.d/rc")));
+        watchers.notify(Event::Renamed(path("/tmp/profile"), path("/etc/profile")));
+
+        let renamed = Event::Renamed(path("/tmp/profile"), path("/etc/profile"));
+
+        assert_eq!(
+            directory.try_next(),
+            Some(Event::Created(path("/etc/hosts")))
+        );
+        assert_eq!(
+            directory.try_next(),
+            Some(Event::Modified(path("/etc/profile")))
+        );
+        assert_eq!(directory.try_next(), Some(renamed.clone()));
+        assert_eq!(directory.try_next(), None);
+
+        assert_eq!(file.try_next(), Some(Event::Modified(path("/etc/profile"))));
+        assert_eq!(Executor::block(file.next()), renamed);
+
+        // nobody is told after dropping the watch
+        drop(file);
+        watchers.notify(Event::Deleted(path("/etc/profile")));
+        assert_eq!(watchers.watches.borrow().len(), 1);
+    }
+}
@@ -4,13 +4,21 @@
 
 // Every process has a root which has the usefull all functions
 
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    pin::Pin,
+    rc::Rc,
+};
 
-use crate::descriptor::ReadableWritablePipe;
+use crate::descriptor::{ReadableWritablePipe, SharedReader, SharedWriter, WritableDescriptor};
+use crate::fc::future::{Context, Future, Poll};
 use crate::fc::table::Table;
-use crate::root::pipe::{new_pipe, PipeReader, PipeWriter};
+use crate::root::pipe::new_pipe;
+use crate::{Credentials, ROOT};
 
 pub type Pid = u32;
+pub type ExitCode = u8;
 
 pub trait Process {
     fn new(proc: Proc) -> Self
@@ -26,17 +34,29 @@ pub trait Process {
 
 pub struct Proc {
     pub pid: Pid,
+    // root is its own parent
+    pub ppid: Cell<Pid>,
+    // the command and its arguments, empty for processes started by the kernel
+    pub cmdline: RefCell<Vec<String>>,
     pub children: RefCell<Vec<Rc<dyn Process>>>,
     // pub descriptor_table: Table<RawHandler>,
-    pub stdin: PipeReader,
-    pub stdout: PipeWriter,
-    pub handler: ReadableWritablePipe
+    pub stdin: SharedReader,
+    pub stdout: SharedWriter,
+    pub stderr: SharedWriter,
+    pub handler: ReadableWritablePipe,
+    // absolute path of the working directory
+    pub cwd: RefCell<String>,
+    pub env: RefCell<BTreeMap<String, String>>,
+    // changed only by the kernel, like when switching users
+    credentials: RefCell<Credentials>,
+    exit_code: Cell<Option<ExitCode>>,
 }
 
 impl Proc {
     pub fn new(pid: Pid) -> Self {
         let (stdin_reader, stdin_writer) = new_pipe();
         let (stdout_reader, stdout_writer) = new_pipe();
+        let stdout_writer = Rc::new(stdout_writer);
 
 
         // let descriptor_table = Table::new();
@@ -45,15 +65,70 @@ impl Proc {
 
         Proc {
             pid,
+            ppid: Cell::new(0),
+            cmdline: RefCell::new(vec![]),
             children: RefCell::new(vec![]),
             // descriptor_table,
-            stdin: stdin_reader,
-            stdout: stdout_writer,
-            handler
+            stdin: SharedReader::new(Rc::new(stdin_reader)),
+            // errors go to the same place as the output until redirected
+            stderr: SharedWriter::new(Rc::clone(&stdout_writer) as Rc<dyn WritableDescriptor>),
+            stdout: SharedWriter::new(stdout_writer),
+            handler,
+            cwd: RefCell::new("/".to_string()),
+            env: RefCell::new(BTreeMap::new()),
+            credentials: RefCell::new(Credentials::root()),
+            exit_code: Cell::new(None),
         }
     }
 
-    pub fn exit(&self) {
-        // Exit?
+    // Set once the process exits, until then it's running
+    pub fn exit_code(&self) -> Option<ExitCode> {
+        self.exit_code.get()
+    }
+
+    // The user and groups the process acts as
+    pub fn credentials(&self) -> Credentials {
+        self.credentials.borrow().clone()
+    }
+
+    pub(super) fn set_credentials(&self, credentials: Credentials) {
+        *self.credentials.borrow_mut() = credentials;
+    }
+
+    pub fn exit(&self, code: ExitCode) {
+        self.exit_code.set(Some(code));
+
+        // Dropping our streams lets the reader of stdout see the end
+        self.stdout.close();
+        self.stderr.close();
+        self.stdin.close();
+    }
+
+    // Waits for a child to exit and removes it from the process tree
+    pub async fn wait(&self, child: &Rc<dyn Process>) -> ExitCode {
+        let proc = child.get_proc();
+        let code = ExitTask { proc }.await;
+
+        self.children
+            .borrow_mut()
+            .retain(|other| other.get_proc().pid != proc.pid);
+        ROOT.spawner.remove(proc.pid);
+
+        code
+    }
+}
+
+struct ExitTask<'a> {
+    proc: &'a Proc,
+}
+
+impl Future for ExitTask<'_> {
+    type Output = ExitCode;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<ExitCode> {
+        match self.proc.exit_code.get() {
+            Some(code) => Poll::Ready(code),
+            None => Poll::Pending,
+        }
     }
 }
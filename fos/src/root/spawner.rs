@@ -30,6 +30,26 @@ impl Spawner {
         child
     }
 
+    // Processes that didn't exit or weren't waited for yet, by pid
+    pub fn processes(&self) -> Vec<Rc<dyn Process>> {
+        self.processes
+            .entries()
+            .into_iter()
+            .filter_map(|(_, process)| process.upgrade())
+            .collect()
+    }
+
+    pub fn process(&self, pid: Pid) -> Option<Rc<dyn Process>> {
+        self.processes()
+            .into_iter()
+            .find(|process| process.get_proc().pid == pid)
+    }
+
+    // Frees the pid of a process that exited
+    pub fn remove(&self, pid: Pid) {
+        let _ = self.processes.remove(pid as usize);
+    }
+
     pub fn spawn_root() -> Rc<Root> {
 
         let child_proc = Proc::new(0);
@@ -50,6 +70,17 @@ impl Proc {
         let mut children = self.children.borrow_mut();
 
         let child = ROOT.spawner.spawn::<Child>();
+
+        // children inherit the user, working directory, environment and streams of their parent
+        let proc = child.get_proc();
+        proc.ppid.set(self.pid);
+        proc.set_credentials(self.credentials());
+        *proc.cwd.borrow_mut() = self.cwd.borrow().clone();
+        *proc.env.borrow_mut() = self.env.borrow().clone();
+        proc.stdin.replace(self.stdin.share());
+        proc.stdout.replace(self.stdout.share());
+        proc.stderr.replace(self.stderr.share());
+
         let child_clone = Rc::clone(&child);
         children.push(child_clone);
 
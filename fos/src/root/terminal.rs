@@ -1,24 +1,187 @@
-use crate::Tekenen;
-use std::cell::RefCell;
+use crate::{Keycode, Tekenen};
+use std::cell::{Cell, RefCell};
+
+// Escape sequences sent to the shell for keys that have no char.
+pub fn key_sequence(keycode: &Keycode) -> Option<&'static str> {
+    match keycode {
+        Keycode::Temp => None,
+        Keycode::Backspace => Some("\x7f"),
+        Keycode::Delete => Some("\x1b[3~"),
+        Keycode::Tab => Some("\t"),
+        Keycode::Up => Some("\x1b[A"),
+        Keycode::Down => Some("\x1b[B"),
+        Keycode::Right => Some("\x1b[C"),
+        Keycode::Left => Some("\x1b[D"),
+        Keycode::Home => Some("\x1b[H"),
+        Keycode::End => Some("\x1b[F"),
+    }
+}
 
 pub struct Terminal {
-    buffer: RefCell<String>,
+    lines: RefCell<Vec<Vec<char>>>,
+    // (line, column)
+    cursor: Cell<(usize, usize)>,
+    // Escape sequence being read, without the leading ESC
+    escape: RefCell<Option<String>>,
 }
 
 impl Terminal {
     pub fn new() -> Terminal {
         Terminal {
-            buffer: RefCell::new(String::new()),
+            lines: RefCell::new(vec![vec![]]),
+            cursor: Cell::new((0, 0)),
+            escape: RefCell::new(None),
         }
     }
 
     pub fn write(&self, string: &str) {
-        self.buffer.borrow_mut().push_str(string)
+        for char in string.chars() {
+            self.write_char(char);
+        }
+    }
+
+    fn write_char(&self, char: char) {
+        let mut escape = self.escape.borrow_mut();
+
+        if let Some(sequence) = escape.as_mut() {
+            sequence.push(char);
+
+            // A CSI sequence ends with a letter, everything else is unsupported
+            if sequence.len() == 1 && char != '[' {
+                *escape = None;
+            } else if char.is_ascii_alphabetic() || char == '~' {
+                let sequence = escape.take().unwrap();
+                self.execute(&sequence[1..]);
+            }
+
+            return;
+        }
+
+        let mut lines = self.lines.borrow_mut();
+        let (line, column) = self.cursor.get();
+
+        match char {
+            '\x1b' => *escape = Some(String::new()),
+            '\n' => {
+                lines.insert(line + 1, vec![]);
+                self.cursor.set((line + 1, 0));
+            }
+            '\r' => self.cursor.set((line, 0)),
+            '\x08' => self.cursor.set((line, column.saturating_sub(1))),
+            '\t' => {
+                drop(lines);
+                drop(escape);
+
+                let spaces = 4 - column % 4;
+                self.write(&" ".repeat(spaces));
+            }
+            char if char.is_control() => {}
+            char => {
+                let current = &mut lines[line];
+
+                if column < current.len() {
+                    current[column] = char;
+                } else {
+                    current.push(char);
+                }
+
+                self.cursor.set((line, column + 1));
+            }
+        }
+    }
+
+    // Executes a CSI sequence, `sequence` is the part after `ESC [`.
+    fn execute(&self, sequence: &str) {
+        let (arguments, command) = sequence.split_at(sequence.len() - 1);
+        let count = arguments.parse::<usize>().unwrap_or(1);
+
+        let mut lines = self.lines.borrow_mut();
+        let (line, column) = self.cursor.get();
+
+        match command {
+            "C" => self.cursor.set((line, (column + count).min(lines[line].len()))),
+            "D" => self.cursor.set((line, column.saturating_sub(count))),
+            "K" => lines[line].truncate(column),
+            "J" if arguments == "2" => {
+                *lines = vec![vec![]];
+                self.cursor.set((0, 0));
+            }
+            _ => {}
+        }
     }
 }
 
 impl Terminal {
     pub fn render(&self, renderer: &mut Tekenen, time: u64) {
-        renderer.draw_terminal(&self.buffer.borrow(), time);
+        let lines = self.lines.borrow();
+        let (line, column) = self.cursor.get();
+
+        let buffer = lines
+            .iter()
+            .map(|chars| chars.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        // every line before the cursor also has a newline
+        let cursor = lines[..line].iter().map(|chars| chars.len() + 1).sum::<usize>() + column;
+
+        renderer.draw_terminal(&buffer, cursor, time);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn content(terminal: &Terminal) -> Vec<String> {
+        terminal
+            .lines
+            .borrow()
+            .iter()
+            .map(|line| line.iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn write_lines() {
+        let terminal = Terminal::new();
+
+        terminal.write("ab\ncd");
+
+        assert_eq!(content(&terminal), vec!["ab", "cd"]);
+        assert_eq!(terminal.cursor.get(), (1, 2));
+    }
+
+    #[test]
+    fn carriage_return_overwrites() {
+        let terminal = Terminal::new();
+
+        terminal.write("abc\rx");
+
+        assert_eq!(content(&terminal), vec!["xbc"]);
+        assert_eq!(terminal.cursor.get(), (0, 1));
+    }
+
+    #[test]
+    fn clear_to_end_of_line() {
+        let terminal = Terminal::new();
+
+        terminal.write("abcd\x1b[2D\x1b[K");
+
+        assert_eq!(content(&terminal), vec!["ab"]);
+        assert_eq!(terminal.cursor.get(), (0, 2));
+    }
+
+    #[test]
+    fn cursor_movement() {
+        let terminal = Terminal::new();
+
+        terminal.write("abcd\x1b[3D\x1b[C\x08");
+
+        assert_eq!(content(&terminal), vec!["abcd"]);
+        assert_eq!(terminal.cursor.get(), (0, 1));
+
+        terminal.write("\x1b[10C");
+        assert_eq!(terminal.cursor.get(), (0, 4));
     }
 }
@@ -0,0 +1,328 @@
+// Users and groups, as listed in `/etc/passwd` and `/etc/group`. The hashes of the
+// passwords are in `/etc/shadow`, which only root may read.
+//
+// A process runs as a user and the groups it's in, every inode has an owner and a group.
+// The mode of an inode has read, write and execute bits for its owner, its group and the
+// others. The user 0 is root, who may read and write everything.
+
+use super::hash::{hex, pbkdf2_sha256};
+use crate::{descriptor::ReadableDescriptor, file::OpenFlags, Kind, OpenError, Proc, Stat, ROOT};
+
+pub type Uid = u32;
+pub type Gid = u32;
+
+pub const PASSWD: &str = "/etc/passwd";
+pub const GROUP: &str = "/etc/group";
+pub const SHADOW: &str = "/etc/shadow";
+
+// Rounds of PBKDF2 for new hashes, every guess of a password has to go through all of them
+const ROUNDS: u32 = 10_000;
+
+// Bits of the mode, shifted to the owner, the group or the others
+pub const MAY_READ: u16 = 0o4;
+pub const MAY_WRITE: u16 = 0o2;
+pub const MAY_EXECUTE: u16 = 0o1;
+
+// Who a process acts as
+#[derive(Clone, Debug, PartialEq)]
+pub struct Credentials {
+    pub uid: Uid,
+    pub gid: Gid,
+    // the other groups the user is a member of
+    pub groups: Vec<Gid>,
+}
+
+impl Credentials {
+    pub fn root() -> Self {
+        Credentials {
+            uid: 0,
+            gid: 0,
+            groups: vec![],
+        }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.uid == 0
+    }
+
+    pub fn in_group(&self, gid: Gid) -> bool {
+        self.gid == gid || self.groups.contains(&gid)
+    }
+
+    // Whether the bits of `access` are all allowed on the inode
+    pub fn permits(&self, stat: &Stat, access: u16) -> bool {
+        if self.is_root() {
+            // root only executes what someone can execute
+            return access & MAY_EXECUTE == 0
+                || stat.kind == Kind::Directory
+                || stat.mode & 0o111 != 0;
+        }
+
+        let bits = if self.uid == stat.uid {
+            stat.mode >> 6
+        } else if self.in_group(stat.gid) {
+            stat.mode >> 3
+        } else {
+            stat.mode
+        };
+
+        bits & access == access
+    }
+}
+
+// A line of `/etc/passwd`: `name:password:uid:gid:info:home:shell`
+#[derive(Clone, Debug, PartialEq)]
+pub struct User {
+    pub name: String,
+    // `x` when the hash is in `/etc/shadow`, empty when there is no password
+    pub password: String,
+    pub uid: Uid,
+    pub gid: Gid,
+    pub info: String,
+    pub home: String,
+    pub shell: String,
+}
+
+impl User {
+    // What the processes of the user act as, in the groups listing it as a member
+    pub fn credentials(&self, groups: &[Group]) -> Credentials {
+        Credentials {
+            uid: self.uid,
+            gid: self.gid,
+            groups: groups
+                .iter()
+                .filter(|group| group.members.contains(&self.name))
+                .map(|group| group.gid)
+                .collect(),
+        }
+    }
+}
+
+// A line of `/etc/group`: `name:password:gid:member,member`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group {
+    pub name: String,
+    pub gid: Gid,
+    pub members: Vec<String>,
+}
+
+// Lines that can't be read are skipped
+pub fn parse_users(text: &str) -> Vec<User> {
+    text.lines()
+        .filter_map(|line| match line.split(':').collect::<Vec<_>>()[..] {
+            [name, password, uid, gid, info, home, shell] => Some(User {
+                name: name.to_string(),
+                password: password.to_string(),
+                uid: uid.parse().ok()?,
+                gid: gid.parse().ok()?,
+                info: info.to_string(),
+                home: home.to_string(),
+                shell: shell.to_string(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn parse_groups(text: &str) -> Vec<Group> {
+    text.lines()
+        .filter_map(|line| match line.split(':').collect::<Vec<_>>()[..] {
+            [name, _, gid, members] => Some(Group {
+                name: name.to_string(),
+                gid: gid.parse().ok()?,
+                members: members
+                    .split(',')
+                    .filter(|member| !member.is_empty())
+                    .map(|member| member.to_string())
+                    .collect(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+// Lines of `/etc/shadow` start with `name:hash`, the hash is empty when there is no password
+pub fn parse_shadow(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+
+            Some((name.to_string(), fields.next()?.to_string()))
+        })
+        .collect()
+}
+
+// `$pbkdf2-sha256$rounds$salt$hash`, the hash in hex. A new salt for every password makes
+// equal passwords look different.
+pub fn hash_password(password: &str, salt: &str) -> String {
+    let hash = pbkdf2_sha256(password.as_bytes(), salt.as_bytes(), ROUNDS);
+
+    format!("$pbkdf2-sha256${ROUNDS}${salt}${}", hex(&hash))
+}
+
+// Whether `hash` was made from `password`, hashes of other formats never match
+pub fn check_password(hash: &str, password: &str) -> bool {
+    let ["", "pbkdf2-sha256", rounds, salt, expected] = hash.split('$').collect::<Vec<_>>()[..]
+    else {
+        return false;
+    };
+
+    match rounds.parse() {
+        Ok(rounds) => hex(&pbkdf2_sha256(password.as_bytes(), salt.as_bytes(), rounds)) == expected,
+        Err(_) => false,
+    }
+}
+
+// The kernel reads them without checking the permissions
+fn read(path: &str) -> String {
+    ROOT.fs
+        .open(path, OpenFlags::READ)
+        .and_then(|file| file.read_sync(u32::MAX).map_err(|_| OpenError::NoEntry))
+        .unwrap_or_default()
+}
+
+impl Proc {
+    pub fn users(&self) -> Vec<User> {
+        parse_users(&read(PASSWD))
+    }
+
+    pub fn groups(&self) -> Vec<Group> {
+        parse_groups(&read(GROUP))
+    }
+
+    // `None` when the user can't be switched to with a password
+    fn password_hash(&self, user: &User) -> Option<String> {
+        if user.password != "x" {
+            return Some(user.password.clone());
+        }
+
+        parse_shadow(&read(SHADOW))
+            .into_iter()
+            .find(|(name, _)| *name == user.name)
+            .map(|(_, hash)| hash)
+    }
+
+    // Whether switching to `user` asks for a password
+    pub fn needs_password(&self, user: &User) -> bool {
+        self.password_hash(user).as_deref() != Some("")
+    }
+
+    // Becomes the user `name` in the groups it's a member of, only root doesn't need the
+    // password. The environment follows, the working directory stays.
+    pub fn switch_user(&self, name: &str, password: &str) -> Result<User, OpenError> {
+        let user = self
+            .users()
+            .into_iter()
+            .find(|user| user.name == name)
+            .ok_or(OpenError::NoEntry)?;
+
+        let known = match self.password_hash(&user) {
+            Some(hash) => hash.is_empty() || check_password(&hash, password),
+            None => false,
+        };
+
+        if !self.credentials().is_root() && !known {
+            return Err(OpenError::PermissionDenied);
+        }
+
+        self.set_credentials(user.credentials(&self.groups()));
+
+        let mut env = self.env.borrow_mut();
+        env.insert("USER".to_string(), user.name.clone());
+        env.insert("LOGNAME".to_string(), user.name.clone());
+        env.insert("HOME".to_string(), user.home.clone());
+        env.insert("SHELL".to_string(), user.shell.clone());
+
+        Ok(user)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stat(mode: u16, uid: Uid, gid: Gid) -> Stat {
+        Stat {
+            ino: 2,
+            kind: Kind::File,
+            size: 0,
+            mode,
+            uid,
+            gid,
+            links: 1,
+            created: 0,
+            modified: 0,
+            accessed: 0,
+            device: 0,
+        }
+    }
+
+    #[test]
+    fn permissions() {
+        let fritz = Credentials {
+            uid: 1000,
+            gid: 1000,
+            groups: vec![6],
+        };
+
+        // owner, group and others
+        assert!(fritz.permits(&stat(0o600, 1000, 0), MAY_READ | MAY_WRITE));
+        assert!(!fritz.permits(&stat(0o600, 0, 1000), MAY_READ));
+        assert!(fritz.permits(&stat(0o060, 0, 6), MAY_WRITE));
+        assert!(fritz.permits(&stat(0o604, 0, 0), MAY_READ));
+        assert!(!fritz.permits(&stat(0o644, 0, 0), MAY_WRITE));
+
+        // the owner doesn't fall back to the bits of the others
+        assert!(!fritz.permits(&stat(0o067, 1000, 0), MAY_READ));
+
+        let root = Credentials::root();
+        assert!(root.permits(&stat(0o000, 1000, 1000), MAY_READ | MAY_WRITE));
+        assert!(!root.permits(&stat(0o644, 1000, 1000), MAY_EXECUTE));
+        assert!(root.permits(&stat(0o100, 1000, 1000), MAY_EXECUTE));
+    }
+
+    #[test]
+    fn files() {
+        let users =
+            parse_users("root:x:0:0:root:/root:/bin/sh\nbroken:x\nguest::1001:1001::/:/bin/sh\n");
+
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].home, "/root");
+        assert_eq!(users[0].password, "x");
+        assert_eq!(users[1].password, "");
+
+        let shadow = parse_shadow("root:$pbkdf2-sha256$1$salt$00:19000::::::\nbroken\n");
+        assert_eq!(
+            shadow,
+            [("root".to_string(), "$pbkdf2-sha256$1$salt$00".to_string())]
+        );
+
+        let groups = parse_groups("disk::6:fritz,guest\nfritz::1000:\n");
+
+        assert_eq!(groups[0].gid, 6);
+        assert_eq!(groups[0].members, ["fritz", "guest"]);
+        assert!(groups[1].members.is_empty());
+
+        assert_eq!(users[1].credentials(&groups).groups, [6]);
+    }
+
+    #[test]
+    fn passwords() {
+        let hash = hash_password("secret", "0123abcd");
+
+        assert!(hash.starts_with("$pbkdf2-sha256$10000$0123abcd$"));
+        assert!(check_password(&hash, "secret"));
+        assert!(!check_password(&hash, "Secret"));
+        assert_ne!(hash, hash_password("secret", "4567abcd"));
+
+        // the hashes of the root filesystem, hidden from everyone else
+        let shadow = parse_shadow(&read(SHADOW));
+        assert!(check_password(&shadow[0].1, "root"));
+        assert!(check_password(&shadow[1].1, "fritz"));
+        assert_eq!(ROOT.fs.stat(SHADOW).map(|stat| stat.mode), Ok(0o600));
+
+        // the FNV-1a hashes used before don't work anymore
+        assert!(!check_password("a354fd1ff0c467c5", "root"));
+    }
+}
@@ -1,8 +1,12 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    rc::Rc,
+};
 
 use crate::{
-    root::{Proc, Process, descriptor::{WritableDescriptor, ReadableDescriptor, ReadableWritablePipe}},
-    ROOT,
+    root::{ExitCode, Proc, Process, descriptor::{WritableDescriptor, ReadableDescriptor, ReadableWritablePipe}, file::OpenFlags},
+    Kind, ROOT,
 };
 
 mod echo;
@@ -14,12 +18,156 @@ use ps_tree::PsTreeProgram;
 mod cat;
 use cat::CatProgram;
 
+mod chmod;
+use chmod::ChmodProgram;
+
+mod chown;
+use chown::ChownProgram;
+
+mod id;
+use id::IdProgram;
+
+mod ln;
+use ln::LnProgram;
+
+mod login;
+use login::LoginProgram;
+
+mod losetup;
+use losetup::LosetupProgram;
+
 mod ls;
 use ls::LsProgram;
 
+mod mkdir;
+use mkdir::MkdirProgram;
+
+mod mkfifo;
+use mkfifo::MkfifoProgram;
+
+mod mkfs;
+use mkfs::MkfsProgram;
+
+mod mount;
+use mount::MountProgram;
+
+mod mv;
+use mv::MvProgram;
+
+mod rm;
+use rm::RmProgram;
+
+mod rmdir;
+use rmdir::RmdirProgram;
+
+mod stat;
+use stat::StatProgram;
+
+mod su;
+use su::SuProgram;
+
+mod tail;
+use tail::TailProgram;
+
+mod tar;
+use tar::TarProgram;
+
+mod touch;
+use touch::TouchProgram;
+
+mod umount;
+use umount::UmountProgram;
+
+mod whoami;
+use whoami::WhoamiProgram;
+
+mod line_editor;
+use line_editor::{Key, KeyDecoder, LineEditor};
+
+mod history;
+use history::{ExpansionError, History, ReverseSearch};
+
+mod completion;
+use completion::{candidates, common_prefix, split_path, word_start};
+
+mod lexer;
+mod parser;
+use parser::{parse_with_aliases, FunctionBody};
+
+mod interpreter;
+use interpreter::Flow;
+
+mod test;
+
+mod prompt;
+use prompt::{expand_prompt, PromptInfo};
+
+// Kept in the home directory
+const HISTORY_FILE: &str = ".history";
+
+const DEFAULT_PS1: &str = "\\u@\\h:\\w\\$ ";
+const DEFAULT_PS2: &str = "> ";
+
+// Run by every interactive shell before the rc file in the home directory
+const PROFILE: &str = "/etc/profile";
+const RC_FILE: &str = ".shrc";
+
+type Spawn = fn(&Proc) -> Rc<dyn Process>;
+
+fn spawn<Program: Process + 'static>(proc: &Proc) -> Rc<dyn Process> {
+    proc.spawn::<Program>()
+}
+
+// Programs that can be started from the shell
+const PROGRAMS: [(&str, Spawn); 25] = [
+    ("cat", spawn::<CatProgram>),
+    ("chmod", spawn::<ChmodProgram>),
+    ("chown", spawn::<ChownProgram>),
+    ("echo", spawn::<EchoProgram>),
+    ("id", spawn::<IdProgram>),
+    ("ln", spawn::<LnProgram>),
+    ("login", spawn::<LoginProgram>),
+    ("losetup", spawn::<LosetupProgram>),
+    ("ls", spawn::<LsProgram>),
+    ("mkdir", spawn::<MkdirProgram>),
+    ("mkfifo", spawn::<MkfifoProgram>),
+    ("mkfs", spawn::<MkfsProgram>),
+    ("mount", spawn::<MountProgram>),
+    ("mv", spawn::<MvProgram>),
+    ("pstree", spawn::<PsTreeProgram>),
+    ("rm", spawn::<RmProgram>),
+    ("rmdir", spawn::<RmdirProgram>),
+    ("sh", spawn::<Shell>),
+    ("stat", spawn::<StatProgram>),
+    ("su", spawn::<SuProgram>),
+    ("tail", spawn::<TailProgram>),
+    ("tar", spawn::<TarProgram>),
+    ("touch", spawn::<TouchProgram>),
+    ("umount", spawn::<UmountProgram>),
+    ("whoami", spawn::<WhoamiProgram>),
+];
+
+// Commands executed by the shell itself
+const BUILTINS: [&str; 15] = [
+    ".", ":", "[", "alias", "break", "cd", "continue", "exit", "false", "history", "return",
+    "source", "test", "true", "unalias",
+];
+
 pub struct Shell {
     pub proc: Proc,
-    buffer: RefCell<String>,
+    editor: RefCell<LineEditor>,
+    decoder: RefCell<KeyDecoder>,
+    history: RefCell<History>,
+    search: RefCell<Option<ReverseSearch>>,
+    // exit code of the last command, `$?`
+    status: Cell<ExitCode>,
+    // `$0` followed by the arguments of the script or function
+    args: RefCell<Vec<String>>,
+    functions: RefCell<BTreeMap<String, Rc<FunctionBody>>>,
+    aliases: RefCell<BTreeMap<String, String>>,
+    flow: Cell<Option<Flow>>,
+    // start of a command that continues on the next line
+    pending: RefCell<String>,
 }
 
 impl Process for Shell {
@@ -29,7 +177,16 @@ impl Process for Shell {
     {
         Shell {
             proc,
-            buffer: RefCell::new(String::new()),
+            editor: RefCell::new(LineEditor::new()),
+            decoder: RefCell::new(KeyDecoder::new()),
+            history: RefCell::new(History::new()),
+            search: RefCell::new(None),
+            status: Cell::new(0),
+            args: RefCell::new(vec!["sh".to_string()]),
+            functions: RefCell::new(BTreeMap::new()),
+            aliases: RefCell::new(BTreeMap::new()),
+            flow: Cell::new(None),
+            pending: RefCell::new(String::new()),
         }
     }
 
@@ -41,67 +198,280 @@ impl Process for Shell {
         &self.proc
     }
 
-    fn main(self: Rc<Self>, _: Vec<&str>) {
+    fn main(self: Rc<Self>, args: Vec<&str>) {
         let self_clone = Rc::clone(&self);
-        let message = "fritz@tekenen:~$ ".to_string();
 
-        self_clone.proc.stdout.write(&message);
+        // With arguments we run a script instead of reading commands
+        if let Some((path, args)) = args.split_first() {
+            let path = path.to_string();
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+            ROOT.executor.add_task(async move {
+                let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+                let code = self_clone.run_script(&path, &args).await;
+
+                self_clone.proc.exit(code);
+            });
+
+            return;
+        }
+
+        self.load_history();
 
         ROOT.executor.add_task(async move {
+            self_clone.run_startup().await;
+
+            let mut prompt = self_clone.prompt();
+            self_clone.proc.stdout.write(&prompt);
+
             loop {
-                let char = self_clone.proc.stdin.read_char().await.unwrap();
+                let Ok(char) = self_clone.proc.stdin.read_char().await else {
+                    break;
+                };
+
+                let key = self_clone.decoder.borrow_mut().feed(char);
 
-                let mut buffer = self_clone.buffer.borrow_mut();
+                let Some(key) = key else {
+                    continue;
+                };
 
-                if char == '\n' {
-                    self_clone.proc.stdout.write_char(char);
+                // only the last line of the prompt is drawn again while editing
+                let last_line = prompt.rsplit('\n').next().unwrap_or_default();
 
-                    // process command
+                if let Some(line) = self_clone.handle_key(key, last_line) {
+                    self_clone.execute(line).await;
 
-                    let mut strings: Vec<&str> = vec![];
-                    for string in buffer.split_whitespace() {
-                        strings.push(string);
+                    if self_clone.flow.get() == Some(Flow::Exit) {
+                        break;
                     }
 
-                    if !strings.is_empty() {
-                        let command = strings.remove(0);
-                        println!("{}", command);
-
-                        let program: Option<Rc<dyn Process>> = match command {
-                            "echo" => Some(self_clone.proc.spawn::<EchoProgram>()),
-                            "pstree" => Some(self_clone.proc.spawn::<PsTreeProgram>()),
-                            "ls" => Some(self_clone.proc.spawn::<LsProgram>()),
-                            "cat" => Some(self_clone.proc.spawn::<CatProgram>()),
-                            _ => {
-                                self_clone.proc.stdout.write("Invalid command!\n");
-                                None
-                            }
-                        };
-
-                        if let Some(program) = program {
-                            // pipe shell stdout to terminal
-                            let self_clone_clone = Rc::clone(&self_clone);
-                            let program_clone = Rc::clone(&program);
-                            ROOT.executor.add_task(async move {
-                                loop {
-                                    let str =
-                                    program_clone.get_proc().handler.read(50).await.unwrap();
-                                    self_clone_clone.proc.stdout.write(&str);
-                                }
-                            });
-
-                            program.main(strings);
-                        }
+                    prompt = self_clone.prompt();
+                    self_clone.proc.stdout.write(&prompt);
+                }
+            }
+
+            self_clone.proc.exit(self_clone.status.get());
+        })
+    }
+}
+
+impl Shell {
+    // `PS1` or `PS2` when a command continues on the next line
+    fn prompt(&self) -> String {
+        let env = self.proc.env.borrow();
+        let variable = |name: &str| env.get(name).map_or("", |value| value.as_str());
+
+        let ps = if self.pending.borrow().is_empty() {
+            env.get("PS1").map_or(DEFAULT_PS1, |ps| ps.as_str())
+        } else {
+            env.get("PS2").map_or(DEFAULT_PS2, |ps| ps.as_str())
+        };
+
+        let info = PromptInfo {
+            user: variable("USER"),
+            host: variable("HOSTNAME"),
+            cwd: &self.proc.cwd.borrow(),
+            home: variable("HOME"),
+            root: self.proc.credentials().is_root(),
+            status: self.status.get(),
+            time: ROOT.now(),
+        };
+
+        expand_prompt(ps, &info)
+    }
+
+    // Applies a key to the line being edited, returns the line once it's submitted.
+    fn handle_key(&self, key: Key, prompt: &str) -> Option<String> {
+        let mut editor = self.editor.borrow_mut();
+        let mut history = self.history.borrow_mut();
+        let mut search = self.search.borrow_mut();
+
+        if let Some(active) = search.as_mut() {
+            match key {
+                Key::Char(char) => {
+                    active.query.push(char);
+                    active.update(&history);
+                }
+                Key::Backspace => {
+                    active.query.pop();
+                    active.update(&history);
+                }
+                Key::ReverseSearch => active.older(&history),
+                Key::Cancel => {
+                    *search = None;
+                    self.proc.stdout.write(&editor.render(prompt));
+                }
+                _ => {
+                    // Any other key accepts the match and is handled as usual
+                    if let Some(found) = active.found.and_then(|index| history.get(index)) {
+                        editor.set(found);
                     }
 
-                    self_clone.proc.stdout.write(&message);
+                    *search = None;
+                    self.proc.stdout.write(&editor.render(prompt));
+                }
+            }
+
+            if let Some(active) = search.as_ref() {
+                self.proc.stdout.write(&active.render(&history));
+                return None;
+            }
+
+            if key == Key::Cancel {
+                return None;
+            }
+        }
+
+        match key {
+            Key::Enter => {
+                self.proc.stdout.write_char('\n');
+                return Some(editor.take());
+            }
+            Key::Up => {
+                if let Some(entry) = history.previous(&editor.line()) {
+                    editor.set(entry);
+                }
+            }
+            Key::Down => {
+                if let Some(entry) = history.next() {
+                    editor.set(entry);
+                }
+            }
+            Key::Tab => {
+                self.complete(&mut editor, prompt);
+                return None;
+            }
+            Key::ReverseSearch => {
+                let active = ReverseSearch::new();
+                self.proc.stdout.write(&active.render(&history));
+                *search = Some(active);
+
+                return None;
+            }
+            key => {
+                if !editor.edit(&key) {
+                    return None;
+                }
+            }
+        }
+
+        self.proc.stdout.write(&editor.render(prompt));
+
+        None
+    }
+
+    // Completes the word before the cursor, the first word is a command and the others are paths
+    fn complete(&self, editor: &mut LineEditor, prompt: &str) {
+        let before = editor.before_cursor();
+        let start = word_start(&before);
+        let word = &before[start..];
+
+        let (candidates, typed) = if before[..start].trim().is_empty() {
+            let names = PROGRAMS.iter().map(|(name, _)| *name).chain(BUILTINS);
+
+            (candidates(word, names), word.len())
+        } else {
+            let (dirname, prefix) = split_path(word);
+
+            let Ok(entries) = self.proc.readdir(dirname) else {
+                return;
+            };
+
+            // directories are completed with a slash so the next entry can be typed
+            let names: Vec<String> = entries
+                .into_iter()
+                .map(|(name, stat)| match stat.kind {
+                    Kind::Directory => format!("{name}/"),
+                    _ => name,
+                })
+                .collect();
+
+            (candidates(prefix, names.iter().map(|name| name.as_str())), prefix.len())
+        };
+
+        match candidates.len() {
+            0 => return,
+            1 => {
+                let candidate = &candidates[0];
+                editor.insert(&candidate[typed..]);
+
+                if !candidate.ends_with('/') {
+                    editor.insert(" ");
+                }
+            }
+            _ => {
+                let common = common_prefix(&candidates);
 
-                    buffer.clear();
+                if common.len() > typed {
+                    editor.insert(&common[typed..]);
                 } else {
-                    buffer.push(char);
-                    self_clone.proc.stdout.write_char(char);
+                    // nothing to add, show the possibilities below the prompt
+                    self.proc.stdout.write(&format!("\n{}\n", candidates.join("  ")));
                 }
             }
-        })
+        }
+
+        self.proc.stdout.write(&editor.render(prompt));
+    }
+
+    fn history_path(&self) -> String {
+        let env = self.proc.env.borrow();
+        let home = env.get("HOME").map_or("", |home| home.trim_end_matches('/'));
+
+        format!("{home}/{HISTORY_FILE}")
+    }
+
+    fn load_history(&self) {
+        if let Ok(file) = self.proc.open(self.history_path(), OpenFlags::READ) {
+            if let Ok(content) = file.read_sync(u32::MAX) {
+                *self.history.borrow_mut() = History::load(&content);
+            }
+        }
+    }
+
+    fn save_history(&self) {
+        let flags = OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE;
+
+        if let Ok(file) = self.proc.open(self.history_path(), flags) {
+            file.write(&self.history.borrow().serialize());
+        }
+    }
+
+    async fn execute(self: &Rc<Self>, line: String) {
+        let expanded = self.history.borrow().expand(&line);
+
+        let buffer = match expanded {
+            Ok(expanded) => expanded,
+            Err(ExpansionError::EventNotFound(event)) => {
+                self.proc.stderr.write(&format!("{event}: event not found\n"));
+                return;
+            }
+        };
+
+        // Show what is actually executed
+        if buffer != line {
+            self.proc.stdout.write(&format!("{buffer}\n"));
+        }
+
+        self.history.borrow_mut().push(&buffer);
+        self.save_history();
+
+        let source = self.pending.take() + &buffer;
+
+        let parsed = parse_with_aliases(&source, &self.aliases.borrow());
+
+        match parsed {
+            Ok(list) => {
+                self.run(&list).await;
+            }
+            Err(err) if err.is_incomplete() => {
+                // wait for the rest of the command
+                *self.pending.borrow_mut() = source + "\n";
+            }
+            Err(err) => {
+                self.proc.stderr.write(&format!("sh: {err}\n"));
+                self.status.set(2);
+            }
+        }
     }
 }
@@ -1,10 +1,13 @@
 use std::rc::Rc;
 
 use crate::{
-    root::{Proc, Process, descriptor::{ReadableDescriptor, WritableDescriptor}},
+    root::{Proc, Process, descriptor::{ReadableDescriptor, WritableDescriptor}, file::OpenFlags},
     ROOT,
 };
 
+// Bytes read from a file at a time
+const CHUNK_SIZE: u32 = 4096;
+
 pub struct CatProgram {
     pub proc: Proc,
 }
@@ -26,29 +29,45 @@ impl Process for CatProgram {
     }
 
     fn main(self: Rc<Self>, args: Vec<&str>) {
-        assert!(args.len() == 1);
+        let self_clone = Rc::clone(&self);
+
+        if args.is_empty() {
+            ROOT.executor.add_task(async move {
+                // copy stdin until every writer closed it
+                while let Ok(content) = self_clone.proc.stdin.read(50).await {
+                    self_clone.proc.stdout.write(&content);
+                }
 
-        let file = args[0];
+                self_clone.proc.exit(0);
+            });
+
+            return;
+        }
 
-        let read = self.proc.open(file.to_string());
+        let paths: Vec<String> = args.iter().map(|path| path.to_string()).collect();
 
-        match read {
-            Ok(desc) => {
-                let self_clone = Rc::clone(&self);
-    
-                ROOT.executor.add_task(async move {
-                    loop {
-                        let content = desc.read(50).await.unwrap();
-                        self_clone.proc.stdout.write(&content);
+        ROOT.executor.add_task(async move {
+            let proc = &self_clone.proc;
+            let mut code = 0;
+
+            // one after the other, a missing file doesn't stop the rest
+            for path in paths {
+                let desc = match proc.open(path.clone(), OpenFlags::READ) {
+                    Ok(desc) => desc,
+                    Err(err) => {
+                        proc.stderr.write(&format!("cat: {path}: {err:?}\n"));
+                        code = 1;
+                        continue;
                     }
-                });
-            },
-            Err(err) => {
-                let err = format!("Error: {:?}", err);
-                self.proc.stdout.write(&err);
+                };
+
+                // a FIFO or a device may have to wait for data, a file never does
+                while let Ok(content) = desc.read(CHUNK_SIZE).await {
+                    proc.stdout.write(&content);
+                }
             }
-        }
 
-        self.proc.exit();
+            proc.exit(code);
+        });
     }
 }
@@ -0,0 +1,162 @@
+use std::rc::Rc;
+
+use crate::{
+    root::{descriptor::WritableDescriptor, Proc, Process},
+    Kind,
+};
+
+pub struct ChmodProgram {
+    pub proc: Proc,
+}
+
+impl Process for ChmodProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        ChmodProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "chmod"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        let Some((spec, paths)) = args.split_first().filter(|(_, paths)| !paths.is_empty()) else {
+            self.proc.stderr.write("usage: chmod mode file...\n");
+            self.proc.exit(1);
+            return;
+        };
+
+        // whether a mode is valid doesn't depend on the file
+        if parse_mode(spec, 0, false).is_none() {
+            self.proc
+                .stderr
+                .write(&format!("chmod: invalid mode: {spec}\n"));
+            self.proc.exit(1);
+            return;
+        }
+
+        let mut code = 0;
+
+        for path in paths {
+            // a symbolic mode changes the one the file has
+            let changed = self.proc.stat(path).and_then(|stat| {
+                let directory = stat.kind == Kind::Directory;
+                let mode = parse_mode(spec, stat.mode, directory).unwrap_or(stat.mode);
+
+                self.proc.chmod(path, mode)
+            });
+
+            if let Err(err) = changed {
+                self.proc.stderr.write(&format!("chmod: {path}: {err:?}\n"));
+                code = 1;
+            }
+        }
+
+        self.proc.exit(code);
+    }
+}
+
+// An octal mode like `755`, or symbolic ones like `u+x,go-w` and `a=rX` applied to `mode`.
+// `X` is execute only for directories and files someone can execute already.
+fn parse_mode(spec: &str, mode: u16, directory: bool) -> Option<u16> {
+    if !spec.is_empty() && spec.chars().all(|char| char.is_digit(8)) {
+        return u16::from_str_radix(spec, 8)
+            .ok()
+            .filter(|mode| *mode <= 0o7777);
+    }
+
+    let mut mode = mode;
+
+    for clause in spec.split(',') {
+        let end = clause
+            .find(|char| !"ugoa".contains(char))
+            .unwrap_or(clause.len());
+        let (who, mut actions) = clause.split_at(end);
+
+        // the bits of the classes, all of them when none is given
+        let mut mask = who.chars().fold(0, |mask, class| {
+            mask | match class {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                _ => 0o777,
+            }
+        });
+        if mask == 0 {
+            mask = 0o777;
+        }
+
+        if actions.is_empty() {
+            return None;
+        }
+
+        while let Some(operator) = actions.chars().next() {
+            if !"+-=".contains(operator) {
+                return None;
+            }
+
+            let end = actions[1..]
+                .find(|char| "+-=".contains(char))
+                .map_or(actions.len(), |index| index + 1);
+
+            let mut bits = 0;
+
+            for permission in actions[1..end].chars() {
+                bits |= match permission {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    'X' if directory || mode & 0o111 != 0 => 0o111,
+                    'X' => 0,
+                    _ => return None,
+                };
+            }
+
+            let bits = bits & mask;
+
+            mode = match operator {
+                '+' => mode | bits,
+                '-' => mode & !bits,
+                _ => mode & !mask | bits,
+            };
+
+            actions = &actions[end..];
+        }
+    }
+
+    Some(mode)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn modes() {
+        assert_eq!(parse_mode("755", 0o644, false), Some(0o755));
+        assert_eq!(parse_mode("0600", 0o644, false), Some(0o600));
+        assert_eq!(parse_mode("17777", 0o644, false), None);
+
+        assert_eq!(parse_mode("u+x", 0o644, false), Some(0o744));
+        assert_eq!(parse_mode("go-r", 0o644, false), Some(0o600));
+        assert_eq!(parse_mode("a=r,u+w", 0o777, false), Some(0o644));
+        assert_eq!(parse_mode("+x", 0o600, false), Some(0o711));
+        assert_eq!(parse_mode("u=rw-w+x", 0o000, false), Some(0o500));
+
+        // execute only where it makes sense
+        assert_eq!(parse_mode("a+X", 0o644, true), Some(0o755));
+        assert_eq!(parse_mode("a+X", 0o644, false), Some(0o644));
+        assert_eq!(parse_mode("g+X", 0o744, false), Some(0o754));
+
+        assert_eq!(parse_mode("", 0o644, false), None);
+        assert_eq!(parse_mode("u", 0o644, false), None);
+        assert_eq!(parse_mode("u+q", 0o644, false), None);
+        assert_eq!(parse_mode("8", 0o644, false), None);
+    }
+}
@@ -0,0 +1,113 @@
+use std::rc::Rc;
+
+use crate::{
+    root::{descriptor::WritableDescriptor, Proc, Process},
+    Gid, Group, Uid, User,
+};
+
+pub struct ChownProgram {
+    pub proc: Proc,
+}
+
+impl Process for ChownProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        ChownProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "chown"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        let Some((spec, paths)) = args.split_first().filter(|(_, paths)| !paths.is_empty()) else {
+            self.proc
+                .stderr
+                .write("usage: chown owner[:group] file...\n");
+            self.proc.exit(1);
+            return;
+        };
+
+        let Some((uid, gid)) = parse_owner(spec, &self.proc.users(), &self.proc.groups()) else {
+            self.proc
+                .stderr
+                .write(&format!("chown: invalid owner: {spec}\n"));
+            self.proc.exit(1);
+            return;
+        };
+
+        let mut code = 0;
+
+        for path in paths {
+            if let Err(err) = self.proc.chown(path, uid, gid) {
+                self.proc.stderr.write(&format!("chown: {path}: {err:?}\n"));
+                code = 1;
+            }
+        }
+
+        self.proc.exit(code);
+    }
+}
+
+// `user`, `user:group` or `:group` by name or by number, what is missing stays the same
+fn parse_owner(spec: &str, users: &[User], groups: &[Group]) -> Option<(Option<Uid>, Option<Gid>)> {
+    let (user, group) = spec.split_once(':').unwrap_or((spec, ""));
+
+    let uid = match user {
+        "" => None,
+        name => Some(
+            users
+                .iter()
+                .find(|user| user.name == name)
+                .map(|user| user.uid)
+                .or_else(|| name.parse().ok())?,
+        ),
+    };
+
+    let gid = match group {
+        "" => None,
+        name => Some(
+            groups
+                .iter()
+                .find(|group| group.name == name)
+                .map(|group| group.gid)
+                .or_else(|| name.parse().ok())?,
+        ),
+    };
+
+    // nothing to change
+    if uid.is_none() && gid.is_none() {
+        return None;
+    }
+
+    Some((uid, gid))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parse_groups, parse_users};
+
+    #[test]
+    fn owners() {
+        let users = parse_users("fritz::1000:1000::/home/fritz:/bin/sh\n");
+        let groups = parse_groups("disk::6:fritz\n");
+
+        let owner = |spec| parse_owner(spec, &users, &groups);
+
+        assert_eq!(owner("fritz"), Some((Some(1000), None)));
+        assert_eq!(owner("fritz:disk"), Some((Some(1000), Some(6))));
+        assert_eq!(owner(":disk"), Some((None, Some(6))));
+        assert_eq!(owner("0:0"), Some((Some(0), Some(0))));
+
+        assert_eq!(owner("nobody"), None);
+        assert_eq!(owner("fritz:nogroup"), None);
+        assert_eq!(owner(":"), None);
+    }
+}
@@ -0,0 +1,81 @@
+// Start of the word ending at the end of `line`
+pub fn word_start(line: &str) -> usize {
+    line.rfind(char::is_whitespace).map_or(0, |index| index + 1)
+}
+
+// Splits a path being typed in the directory to list and the prefix of the entry
+pub fn split_path(word: &str) -> (&str, &str) {
+    match word.rfind('/') {
+        Some(index) => (&word[..index + 1], &word[index + 1..]),
+        None => ("", word),
+    }
+}
+
+// Names starting with `prefix`, sorted and without duplicates
+pub fn candidates<'a>(prefix: &str, names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut candidates: Vec<String> = names
+        .filter(|name| name.starts_with(prefix))
+        .map(|name| name.to_string())
+        .collect();
+
+    candidates.sort();
+    candidates.dedup();
+
+    candidates
+}
+
+pub fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+
+    let mut prefix: Vec<char> = first.chars().collect();
+
+    for candidate in candidates[1..].iter() {
+        let common = prefix
+            .iter()
+            .zip(candidate.chars())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+
+        prefix.truncate(common);
+    }
+
+    prefix.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn words() {
+        assert_eq!(word_start("ca"), 0);
+        assert_eq!(word_start("cat mount"), 4);
+        assert_eq!(word_start("cat "), 4);
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(split_path("mount"), ("", "mount"));
+        assert_eq!(split_path("mount_folder/sub"), ("mount_folder/", "sub"));
+        assert_eq!(split_path("/"), ("/", ""));
+    }
+
+    #[test]
+    fn matching_candidates() {
+        let names = ["cat", "cd", "echo", "cat"];
+
+        assert_eq!(candidates("c", names.into_iter()), vec!["cat", "cd"]);
+        assert_eq!(candidates("x", names.into_iter()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn prefixes() {
+        let names = vec!["sub_file_1".to_string(), "sub_file_2".to_string()];
+
+        assert_eq!(common_prefix(&names), "sub_file_");
+        assert_eq!(common_prefix(&names[..1]), "sub_file_1");
+        assert_eq!(common_prefix(&[]), "");
+    }
+}
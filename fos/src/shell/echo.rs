@@ -23,12 +23,10 @@ impl Process for EchoProgram {
     }
 
     fn main(self: Rc<Self>, args: Vec<&str>) {
-        for arg in args {
-            self.proc.stdout.write(arg);
-        }
+        self.proc.stdout.write(&args.join(" "));
 
         self.proc.stdout.write_char('\n');
 
-        self.proc.exit();
+        self.proc.exit(0);
     }
 }
@@ -0,0 +1,286 @@
+#[derive(Debug, PartialEq)]
+pub enum ExpansionError {
+    EventNotFound(String),
+}
+
+pub struct History {
+    entries: Vec<String>,
+    // Entry shown while browsing with Up/Down, entries.len() when on the draft
+    position: usize,
+    // The line that was being edited before browsing started
+    draft: String,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History {
+            entries: vec![],
+            position: 0,
+            draft: String::new(),
+        }
+    }
+
+    // One entry per line, as written by `serialize`
+    pub fn load(content: &str) -> Self {
+        let mut history = History::new();
+
+        for line in content.lines() {
+            history.push(line);
+        }
+
+        history
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut content = String::new();
+
+        for entry in self.entries.iter() {
+            content.push_str(entry);
+            content.push('\n');
+        }
+
+        content
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim();
+
+        if !line.is_empty() && self.entries.last().map(|last| last.as_str()) != Some(line) {
+            self.entries.push(line.to_string());
+        }
+
+        self.position = self.entries.len();
+        self.draft.clear();
+    }
+
+    // Moves to the previous entry, `current` is the line in the editor
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        if self.position == 0 {
+            return None;
+        }
+
+        if self.position == self.entries.len() {
+            self.draft = current.to_string();
+        }
+
+        self.position -= 1;
+
+        Some(&self.entries[self.position])
+    }
+
+    pub fn next(&mut self) -> Option<&str> {
+        if self.position >= self.entries.len() {
+            return None;
+        }
+
+        self.position += 1;
+
+        if self.position == self.entries.len() {
+            Some(&self.draft)
+        } else {
+            Some(&self.entries[self.position])
+        }
+    }
+
+    // Index of the newest entry before `before` containing `query`
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(|entry| entry.as_str())
+    }
+
+    // Replaces `!!`, `!n`, `!-n` and `!prefix` with entries from the history
+    pub fn expand(&self, line: &str) -> Result<String, ExpansionError> {
+        let mut expanded = String::new();
+        let mut chars = line.chars().peekable();
+        let mut quoted = false;
+
+        while let Some(char) = chars.next() {
+            let next = chars.peek().copied();
+
+            // nothing is expanded in single quotes or after a backslash
+            if char == '\'' {
+                quoted = !quoted;
+            } else if char == '\\' && !quoted {
+                expanded.push(char);
+                expanded.extend(chars.next());
+                continue;
+            }
+
+            if char != '!' || quoted || next.is_none_or(|next| next.is_whitespace() || next == '=')
+            {
+                expanded.push(char);
+                continue;
+            }
+
+            let mut event = String::new();
+
+            if next == Some('!') {
+                event.push(chars.next().unwrap());
+            } else {
+                while let Some(&char) = chars.peek() {
+                    if char.is_whitespace() || char == '!' {
+                        break;
+                    }
+
+                    event.push(char);
+                    chars.next();
+                }
+            }
+
+            let entry = self.event(&event);
+            let entry = entry.ok_or_else(|| ExpansionError::EventNotFound(format!("!{event}")))?;
+
+            expanded.push_str(entry);
+        }
+
+        Ok(expanded)
+    }
+
+    fn event(&self, event: &str) -> Option<&str> {
+        let count = self.entries.len() as i64;
+
+        let index = if event == "!" {
+            count - 1
+        } else if let Ok(number) = event.parse::<i64>() {
+            // entries are numbered from 1, negative numbers count back from the end
+            if number < 0 {
+                count + number
+            } else {
+                number - 1
+            }
+        } else {
+            return self
+                .entries
+                .iter()
+                .rev()
+                .find(|entry| entry.starts_with(event))
+                .map(|entry| entry.as_str());
+        };
+
+        if index < 0 {
+            return None;
+        }
+
+        self.get(index as usize)
+    }
+}
+
+// State of a Ctrl-R reverse search
+pub struct ReverseSearch {
+    pub query: String,
+    // index of the current match
+    pub found: Option<usize>,
+}
+
+impl ReverseSearch {
+    pub fn new() -> Self {
+        ReverseSearch {
+            query: String::new(),
+            found: None,
+        }
+    }
+
+    // Searches again from the newest entry, used when the query changes
+    pub fn update(&mut self, history: &History) {
+        self.found = history.search(&self.query, history.entries().len());
+    }
+
+    // Searches for an older match, keeps the current one if there is none
+    pub fn older(&mut self, history: &History) {
+        let before = self.found.unwrap_or(history.entries().len());
+
+        if let Some(found) = history.search(&self.query, before) {
+            self.found = Some(found);
+        }
+    }
+
+    pub fn render(&self, history: &History) -> String {
+        let found = self
+            .found
+            .and_then(|index| history.get(index))
+            .unwrap_or("");
+
+        format!("\r(reverse-i-search)`{}': {found}\x1b[K", self.query)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn history() -> History {
+        History::load("ls\ncat file\necho hello\n")
+    }
+
+    #[test]
+    fn load_and_serialize() {
+        let mut history = history();
+
+        history.push("pstree");
+        history.push("pstree");
+        history.push("   ");
+
+        assert_eq!(history.entries().len(), 4);
+        assert_eq!(history.serialize(), "ls\ncat file\necho hello\npstree\n");
+    }
+
+    #[test]
+    fn browse() {
+        let mut history = history();
+
+        assert_eq!(history.next(), None);
+        assert_eq!(history.previous("draft"), Some("echo hello"));
+        assert_eq!(history.previous(""), Some("cat file"));
+        assert_eq!(history.previous(""), Some("ls"));
+        assert_eq!(history.previous(""), None);
+        assert_eq!(history.next(), Some("cat file"));
+        assert_eq!(history.next(), Some("echo hello"));
+        assert_eq!(history.next(), Some("draft"));
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn expand() {
+        let history = history();
+
+        assert_eq!(history.expand("!!"), Ok("echo hello".to_string()));
+        assert_eq!(history.expand("!1 -l"), Ok("ls -l".to_string()));
+        assert_eq!(history.expand("!-2"), Ok("cat file".to_string()));
+        assert_eq!(history.expand("x !ca"), Ok("x cat file".to_string()));
+        assert_eq!(history.expand("echo hi!"), Ok("echo hi!".to_string()));
+        assert_eq!(
+            history.expand("echo '#!/bin/sh' \\!!"),
+            Ok("echo '#!/bin/sh' \\!!".to_string())
+        );
+        assert_eq!(
+            history.expand("!9"),
+            Err(ExpansionError::EventNotFound("!9".to_string()))
+        );
+    }
+
+    #[test]
+    fn reverse_search() {
+        let mut history = history();
+        history.push("cat other");
+
+        let mut search = ReverseSearch::new();
+        search.query.push_str("cat");
+        search.update(&history);
+        assert_eq!(search.found, Some(3));
+
+        search.older(&history);
+        assert_eq!(search.found, Some(1));
+
+        search.older(&history);
+        assert_eq!(search.found, Some(1));
+    }
+}
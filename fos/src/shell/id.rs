@@ -0,0 +1,120 @@
+use std::rc::Rc;
+
+use crate::{
+    root::{descriptor::WritableDescriptor, Proc, Process},
+    Credentials, Group, User,
+};
+
+pub struct IdProgram {
+    pub proc: Proc,
+}
+
+impl Process for IdProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        IdProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "id"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        let (users, groups) = (self.proc.users(), self.proc.groups());
+
+        // our own credentials, or the ones a user gets when logging in
+        let credentials = match args[..] {
+            [] => self.proc.credentials(),
+            [name] => match users.iter().find(|user| user.name == name) {
+                Some(user) => user.credentials(&groups),
+                None => {
+                    self.proc
+                        .stderr
+                        .write(&format!("id: {name}: no such user\n"));
+                    self.proc.exit(1);
+                    return;
+                }
+            },
+            _ => {
+                self.proc.stderr.write("usage: id [user]\n");
+                self.proc.exit(1);
+                return;
+            }
+        };
+
+        self.proc
+            .stdout
+            .write(&format!("{}\n", format_id(&credentials, &users, &groups)));
+        self.proc.exit(0);
+    }
+}
+
+// Like `uid=1000(fritz) gid=1000(fritz) groups=1000(fritz),6(disk)`
+fn format_id(credentials: &Credentials, users: &[User], groups: &[Group]) -> String {
+    let group = |gid| match groups.iter().find(|group| group.gid == gid) {
+        Some(group) => format!("{gid}({})", group.name),
+        None => gid.to_string(),
+    };
+
+    let uid = credentials.uid;
+    let user = match users.iter().find(|user| user.uid == uid) {
+        Some(user) => format!("{uid}({})", user.name),
+        None => uid.to_string(),
+    };
+
+    // the primary group comes first
+    let mut gids = vec![credentials.gid];
+    gids.extend(
+        credentials
+            .groups
+            .iter()
+            .filter(|gid| **gid != credentials.gid),
+    );
+
+    let all: Vec<String> = gids.into_iter().map(group).collect();
+
+    format!(
+        "uid={user} gid={} groups={}",
+        group(credentials.gid),
+        all.join(",")
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parse_groups, parse_users};
+
+    #[test]
+    fn format() {
+        let users = parse_users("fritz::1000:1000::/home/fritz:/bin/sh\n");
+        let groups = parse_groups("disk::6:fritz\nfritz::1000:\n");
+
+        let credentials = Credentials {
+            uid: 1000,
+            gid: 1000,
+            groups: vec![6, 1000],
+        };
+        assert_eq!(
+            format_id(&credentials, &users, &groups),
+            "uid=1000(fritz) gid=1000(fritz) groups=1000(fritz),6(disk)"
+        );
+
+        // without names only the numbers are known
+        let credentials = Credentials {
+            uid: 7,
+            gid: 8,
+            groups: vec![],
+        };
+        assert_eq!(
+            format_id(&credentials, &users, &groups),
+            "uid=7 gid=8 groups=8"
+        );
+    }
+}
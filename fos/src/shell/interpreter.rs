@@ -0,0 +1,966 @@
+use std::{pin::Pin, rc::Rc};
+
+use crate::{
+    fc::future::{Context, Future, Poll},
+    root::{
+        descriptor::{ReadableDescriptor, WritableDescriptor},
+        file::OpenFlags,
+        pipe::new_pipe,
+        ExitCode, Proc, Process,
+    },
+    OpenError, MAY_EXECUTE,
+};
+
+use super::{
+    lexer::{RedirectKind, Word, WordPart},
+    parser::{
+        parse_with_aliases, AndOr, Command, Compound, List, Operator, Pipeline, Redirect,
+        SimpleCommand,
+    },
+    spawn,
+    test::test,
+    Shell, PROFILE, PROGRAMS, RC_FILE,
+};
+
+// The streams a command is started with
+#[derive(Clone)]
+struct Streams {
+    stdin: Option<Rc<dyn ReadableDescriptor>>,
+    stdout: Option<Rc<dyn WritableDescriptor>>,
+    stderr: Option<Rc<dyn WritableDescriptor>>,
+}
+
+impl Streams {
+    fn inherit(proc: &Proc) -> Self {
+        Streams {
+            stdin: proc.stdin.share(),
+            stdout: proc.stdout.share(),
+            stderr: proc.stderr.share(),
+        }
+    }
+
+    fn write(&self, string: &str) {
+        if let Some(stdout) = self.stdout.as_ref() {
+            stdout.write(string);
+        }
+    }
+
+    fn write_error(&self, string: &str) {
+        if let Some(stderr) = self.stderr.as_ref() {
+            stderr.write(string);
+        }
+    }
+
+    fn output(&mut self, fd: u8) -> &mut Option<Rc<dyn WritableDescriptor>> {
+        match fd {
+            2 => &mut self.stderr,
+            _ => &mut self.stdout,
+        }
+    }
+
+    // Replaces the streams a child inherited
+    fn apply(self, proc: &Proc) {
+        proc.stdin.replace(self.stdin);
+        proc.stdout.replace(self.stdout);
+        proc.stderr.replace(self.stderr);
+    }
+}
+
+enum Job {
+    Running(Rc<dyn Process>),
+    // builtins and assignments finish immediately
+    Done(ExitCode),
+}
+
+// Set by `break`, `continue`, `return` and `exit` to skip the rest of the commands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Flow {
+    Break,
+    Continue,
+    Return,
+    Exit,
+}
+
+// Lets the other tasks run once, a loop of builtins would block everything else
+struct YieldTask {
+    yielded: bool,
+}
+
+impl Future for YieldTask {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            Poll::Pending
+        }
+    }
+}
+
+// Polls every future until all of them are done, the outputs are in the same order
+struct JoinTask<'a, T> {
+    futures: Vec<Pin<Box<dyn Future<Output = T> + 'a>>>,
+    outputs: Vec<Option<T>>,
+}
+
+impl<'a, T> JoinTask<'a, T> {
+    fn new(futures: Vec<Pin<Box<dyn Future<Output = T> + 'a>>>) -> Self {
+        let outputs = futures.iter().map(|_| None).collect();
+
+        JoinTask { futures, outputs }
+    }
+}
+
+impl<T: Unpin> Future for JoinTask<'_, T> {
+    type Output = Vec<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Vec<T>> {
+        let join = &mut *self;
+
+        for (future, output) in join.futures.iter_mut().zip(join.outputs.iter_mut()) {
+            if output.is_none() {
+                if let Poll::Ready(value) = future.as_mut().poll(cx) {
+                    *output = Some(value);
+                }
+            }
+        }
+
+        if join.outputs.iter().any(Option::is_none) {
+            return Poll::Pending;
+        }
+
+        Poll::Ready(join.outputs.iter_mut().filter_map(Option::take).collect())
+    }
+}
+
+impl Shell {
+    // Runs a command line typed in the interactive shell
+    pub(super) async fn run(self: &Rc<Self>, list: &List) -> ExitCode {
+        let code = self.run_list(list, &Streams::inherit(&self.proc)).await;
+
+        // `break` or `return` outside of a loop or function only end the line
+        if self.flow.get() != Some(Flow::Exit) {
+            self.flow.set(None);
+        }
+
+        code
+    }
+
+    // Runs the startup files of an interactive shell, the ones that don't exist are skipped
+    pub(super) async fn run_startup(self: &Rc<Self>) {
+        let mut files = vec![PROFILE.to_string()];
+
+        if let Some(home) = self.proc.env.borrow().get("HOME") {
+            files.push(format!("{home}/{RC_FILE}"));
+        }
+
+        for path in files {
+            if self.proc.open(path.clone(), OpenFlags::READ).is_ok() {
+                self.source(&path, &[], &Streams::inherit(&self.proc)).await;
+            }
+        }
+    }
+
+    // Runs a script as `$0` with `args` as `$1`...
+    pub(super) async fn run_script(self: &Rc<Self>, path: &str, args: &[&str]) -> ExitCode {
+        let mut script_args = vec![path.to_string()];
+        script_args.extend(args.iter().map(|arg| arg.to_string()));
+
+        *self.args.borrow_mut() = script_args;
+
+        self.source(path, &[], &Streams::inherit(&self.proc)).await
+    }
+
+    // Recursive through compound commands, so the future has to be boxed
+    fn run_list<'a>(
+        self: &'a Rc<Self>,
+        list: &'a List,
+        streams: &'a Streams,
+    ) -> Pin<Box<dyn Future<Output = ExitCode> + 'a>> {
+        Box::pin(async move {
+            let mut code = self.status.get();
+
+            for and_or in list.iter() {
+                code = self.run_and_or(and_or, streams).await;
+
+                if self.flow.get().is_some() {
+                    break;
+                }
+            }
+
+            code
+        })
+    }
+
+    async fn run_and_or(self: &Rc<Self>, and_or: &AndOr, streams: &Streams) -> ExitCode {
+        let mut code = self.run_pipeline(&and_or.first, streams).await;
+
+        for (operator, pipeline) in and_or.rest.iter() {
+            if self.flow.get().is_some() {
+                break;
+            }
+
+            let run = match operator {
+                Operator::And => code == 0,
+                Operator::Or => code != 0,
+            };
+
+            if run {
+                code = self.run_pipeline(pipeline, streams).await;
+            }
+        }
+
+        code
+    }
+
+    // Starts every command of the pipeline, the exit code is the one of the last command
+    async fn run_pipeline(self: &Rc<Self>, pipeline: &Pipeline, streams: &Streams) -> ExitCode {
+        let mut starts: Vec<Pin<Box<dyn Future<Output = Job>>>> = vec![];
+        let mut stdin = streams.stdin.clone();
+
+        for (index, command) in pipeline.0.iter().enumerate() {
+            let last = index == pipeline.0.len() - 1;
+
+            let (next_stdin, stdout) = if last {
+                (None, streams.stdout.clone())
+            } else {
+                let (reader, writer) = new_pipe();
+
+                (
+                    Some(Rc::new(reader) as Rc<dyn ReadableDescriptor>),
+                    Some(Rc::new(writer) as Rc<dyn WritableDescriptor>),
+                )
+            };
+
+            let stage = Streams {
+                stdin: stdin.take(),
+                stdout,
+                stderr: streams.stderr.clone(),
+            };
+
+            starts.push(Box::pin(self.start(command, stage)));
+
+            stdin = next_stdin;
+        }
+
+        // started together, a stage opening a FIFO may wait for a later one
+        let jobs = JoinTask::new(starts).await;
+
+        let mut code = 0;
+
+        for job in jobs {
+            code = match job {
+                Job::Running(program) => self.proc.wait(&program).await,
+                Job::Done(code) => code,
+            };
+        }
+
+        self.status.set(code);
+
+        code
+    }
+
+    async fn start(self: &Rc<Self>, command: &Command, streams: Streams) -> Job {
+        match command {
+            Command::Simple(simple) => self.start_simple(simple, streams).await,
+            Command::Compound(compound, redirects) => {
+                Job::Done(self.run_compound(compound, redirects, streams).await)
+            }
+            Command::Function(name, body) => {
+                self.functions
+                    .borrow_mut()
+                    .insert(name.clone(), Rc::clone(body));
+
+                Job::Done(0)
+            }
+        }
+    }
+
+    async fn run_compound(
+        self: &Rc<Self>,
+        compound: &Compound,
+        redirects: &[Redirect],
+        mut streams: Streams,
+    ) -> ExitCode {
+        if let Err(message) = self.redirect(&mut streams, redirects).await {
+            streams.write_error(&format!("sh: {message}\n"));
+            return 1;
+        }
+
+        match compound {
+            Compound::If {
+                branches,
+                otherwise,
+            } => {
+                for (condition, body) in branches.iter() {
+                    let code = self.run_list(condition, &streams).await;
+
+                    if self.flow.get().is_some() {
+                        return code;
+                    }
+
+                    if code == 0 {
+                        return self.run_list(body, &streams).await;
+                    }
+                }
+
+                match otherwise {
+                    Some(body) => self.run_list(body, &streams).await,
+                    None => 0,
+                }
+            }
+            Compound::While { condition, body } => {
+                let mut code = 0;
+
+                loop {
+                    YieldTask { yielded: false }.await;
+
+                    let status = self.run_list(condition, &streams).await;
+
+                    if !self.next_iteration() || status != 0 {
+                        break;
+                    }
+
+                    code = self.run_list(body, &streams).await;
+
+                    if !self.next_iteration() {
+                        break;
+                    }
+                }
+
+                code
+            }
+            Compound::For { name, words, body } => {
+                let values: Vec<String> = match words {
+                    Some(words) => words
+                        .iter()
+                        .flat_map(|word| self.expand_word(word))
+                        .collect(),
+                    None => self.args.borrow()[1..].to_vec(),
+                };
+
+                let mut code = 0;
+
+                for value in values {
+                    YieldTask { yielded: false }.await;
+
+                    self.proc.env.borrow_mut().insert(name.clone(), value);
+
+                    code = self.run_list(body, &streams).await;
+
+                    if !self.next_iteration() {
+                        break;
+                    }
+                }
+
+                code
+            }
+            Compound::Group(list) => self.run_list(list, &streams).await,
+        }
+    }
+
+    // Handles `break` and `continue` after a part of a loop, false if the loop has to stop
+    fn next_iteration(&self) -> bool {
+        match self.flow.get() {
+            Some(Flow::Break) => {
+                self.flow.set(None);
+                false
+            }
+            Some(Flow::Continue) => {
+                self.flow.set(None);
+                true
+            }
+            Some(Flow::Return | Flow::Exit) => false,
+            None => true,
+        }
+    }
+
+    async fn start_simple(self: &Rc<Self>, command: &SimpleCommand, mut streams: Streams) -> Job {
+        if let Err(message) = self.redirect(&mut streams, &command.redirects).await {
+            streams.write_error(&format!("sh: {message}\n"));
+            return Job::Done(1);
+        }
+
+        let assignments: Vec<(String, String)> = command
+            .assignments
+            .iter()
+            .map(|(name, value)| (name.clone(), self.expand_word(value).join(" ")))
+            .collect();
+
+        let words: Vec<String> = command
+            .words
+            .iter()
+            .flat_map(|word| self.expand_word(word))
+            .collect();
+
+        let Some(name) = words.first() else {
+            // Without a command the assignments set variables of the shell
+            self.proc.env.borrow_mut().extend(assignments);
+            return Job::Done(0);
+        };
+
+        let args: Vec<&str> = words[1..].iter().map(|arg| arg.as_str()).collect();
+
+        // Assignments before a builtin or function apply until it returns
+        let saved = self.assign(&assignments);
+        let code = self.start_internal(name, &words, &streams).await;
+        self.restore(saved);
+
+        if let Some(code) = code {
+            return Job::Done(code);
+        }
+
+        let (program, args) =
+            if let Some((_, spawn)) = PROGRAMS.iter().find(|(program, _)| program == name) {
+                (spawn(&self.proc), args)
+            } else if name.contains('/') {
+                // a path to a script, which is run by a new shell with the path as first argument
+                let script = self
+                    .proc
+                    .access(name, MAY_EXECUTE)
+                    .and_then(|()| self.proc.open(name.clone(), OpenFlags::READ))
+                    .map(|file| file.read_sync(u32::MAX).unwrap_or_default());
+
+                match script {
+                    Ok(content) if content.starts_with("#!/bin/sh") => {
+                        let args = words.iter().map(|arg| arg.as_str()).collect();
+
+                        (spawn::<Shell>(&self.proc), args)
+                    }
+                    Ok(_) => {
+                        streams.write_error(&format!("{name}: cannot execute\n"));
+                        return Job::Done(126);
+                    }
+                    Err(OpenError::PermissionDenied) => {
+                        streams.write_error(&format!("{name}: PermissionDenied\n"));
+                        return Job::Done(126);
+                    }
+                    Err(err) => {
+                        streams.write_error(&format!("{name}: {err:?}\n"));
+                        return Job::Done(127);
+                    }
+                }
+            } else {
+                streams.write_error(&format!("{name}: command not found\n"));
+                return Job::Done(127);
+            };
+
+        let proc = program.get_proc();
+        *proc.cmdline.borrow_mut() = words.clone();
+
+        // Assignments before a command only apply to its environment
+        proc.env.borrow_mut().extend(assignments);
+        streams.apply(proc);
+
+        Rc::clone(&program).main(args);
+
+        Job::Running(program)
+    }
+
+    // Opens the files of the redirections in order, so `>out 2>&1` sends both to `out`.
+    // A FIFO waits for its other end before the command starts.
+    async fn redirect(&self, streams: &mut Streams, redirects: &[Redirect]) -> Result<(), String> {
+        for redirect in redirects {
+            let fields = self.expand_word(&redirect.target);
+
+            let [target] = fields.as_slice() else {
+                return Err("ambiguous redirect".to_string());
+            };
+
+            match (redirect.kind, redirect.fd) {
+                (RedirectKind::Input, 0) => {
+                    let file = self
+                        .proc
+                        .open(target.clone(), OpenFlags::READ)
+                        .map_err(|err| format!("{target}: {err:?}"))?;
+                    file.opened().await;
+
+                    streams.stdin = Some(Rc::new(file));
+                }
+                (RedirectKind::Output | RedirectKind::Append, fd @ (1 | 2)) => {
+                    let flags = match redirect.kind {
+                        RedirectKind::Append => OpenFlags::APPEND,
+                        _ => OpenFlags::TRUNCATE,
+                    };
+
+                    let file = self
+                        .proc
+                        .open(target.clone(), OpenFlags::WRITE | OpenFlags::CREATE | flags)
+                        .map_err(|err| format!("{target}: {err:?}"))?;
+                    file.opened().await;
+
+                    *streams.output(fd) = Some(Rc::new(file));
+                }
+                (RedirectKind::Duplicate, fd @ (1 | 2)) => {
+                    let duplicate = match target.as_str() {
+                        "1" => streams.stdout.clone(),
+                        "2" => streams.stderr.clone(),
+                        _ => return Err(format!("{target}: bad file descriptor")),
+                    };
+
+                    *streams.output(fd) = duplicate;
+                }
+                (_, fd) => return Err(format!("{fd}: bad file descriptor")),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Runs a command of the shell itself, None if there is no such builtin
+    // Runs a builtin or a function, `None` if `name` is neither
+    async fn start_internal(
+        self: &Rc<Self>,
+        name: &str,
+        words: &[String],
+        streams: &Streams,
+    ) -> Option<ExitCode> {
+        let args: Vec<&str> = words[1..].iter().map(|arg| arg.as_str()).collect();
+
+        if let Some(code) = self.builtin(name, &args, streams).await {
+            return Some(code);
+        }
+
+        let function = self.functions.borrow().get(name).cloned()?;
+        let (body, redirects) = function.as_ref();
+
+        // `$0` stays the name of the shell or script
+        let mut function_args = vec![self.args.borrow()[0].clone()];
+        function_args.extend(words[1..].iter().cloned());
+
+        let saved = self.args.replace(function_args);
+        let code = self.run_compound(body, redirects, streams.clone()).await;
+        self.args.replace(saved);
+
+        if self.flow.get() == Some(Flow::Return) {
+            self.flow.set(None);
+        }
+
+        Some(code)
+    }
+
+    // Sets variables of the shell, returns their old values for `restore`
+    fn assign(&self, assignments: &[(String, String)]) -> Vec<(String, Option<String>)> {
+        let mut env = self.proc.env.borrow_mut();
+
+        assignments
+            .iter()
+            .map(|(name, value)| (name.clone(), env.insert(name.clone(), value.clone())))
+            .collect()
+    }
+
+    fn restore(&self, saved: Vec<(String, Option<String>)>) {
+        let mut env = self.proc.env.borrow_mut();
+
+        // the first value of a name assigned twice is the old one
+        for (name, value) in saved.into_iter().rev() {
+            match value {
+                Some(value) => env.insert(name, value),
+                None => env.remove(&name),
+            };
+        }
+    }
+
+    async fn builtin(
+        self: &Rc<Self>,
+        name: &str,
+        args: &[&str],
+        streams: &Streams,
+    ) -> Option<ExitCode> {
+        let code = match name {
+            "cd" => {
+                let home = self.variable("HOME");
+                let dirname = match args.first() {
+                    Some(dirname) => dirname,
+                    None if home.is_empty() => "/",
+                    None => &home,
+                };
+
+                if let Err(err) = self.proc.chdir(dirname) {
+                    streams.write_error(&format!("cd: {dirname}: {:?}\n", err));
+                    1
+                } else {
+                    0
+                }
+            }
+            "history" => {
+                for (index, entry) in self.history.borrow().entries().iter().enumerate() {
+                    streams.write(&format!("{:>5}  {entry}\n", index + 1));
+                }
+
+                0
+            }
+            "alias" => self.alias(args, streams),
+            "unalias" => self.unalias(args, streams),
+            ":" | "true" => 0,
+            "false" => 1,
+            "test" | "[" => {
+                let args = match (name, args.split_last()) {
+                    ("[", Some((&"]", args))) => args,
+                    ("[", _) => {
+                        streams.write_error("[: missing ']'\n");
+                        return Some(2);
+                    }
+                    _ => args,
+                };
+
+                match test(&self.proc, args) {
+                    Ok(true) => 0,
+                    Ok(false) => 1,
+                    Err(err) => {
+                        streams.write_error(&format!("{name}: {err}\n"));
+                        2
+                    }
+                }
+            }
+            "source" | "." => {
+                let Some((path, args)) = args.split_first() else {
+                    streams.write_error(&format!("{name}: filename argument required\n"));
+                    return Some(2);
+                };
+
+                self.source(path, args, streams).await
+            }
+            "break" | "continue" => {
+                self.flow.set(Some(if name == "break" {
+                    Flow::Break
+                } else {
+                    Flow::Continue
+                }));
+
+                0
+            }
+            "return" | "exit" => {
+                let code = match args.first() {
+                    Some(code) => match code.parse() {
+                        Ok(code) => code,
+                        Err(_) => {
+                            streams.write_error(&format!(
+                                "{name}: {code}: numeric argument required\n"
+                            ));
+                            2
+                        }
+                    },
+                    None => self.status.get(),
+                };
+
+                self.flow.set(Some(if name == "return" {
+                    Flow::Return
+                } else {
+                    Flow::Exit
+                }));
+
+                code
+            }
+            _ => return None,
+        };
+
+        Some(code)
+    }
+
+    // `alias name=value` defines an alias, `alias name` or just `alias` prints them
+    fn alias(&self, args: &[&str], streams: &Streams) -> ExitCode {
+        let mut aliases = self.aliases.borrow_mut();
+
+        if args.is_empty() {
+            for (name, value) in aliases.iter() {
+                streams.write(&format!("alias {name}='{value}'\n"));
+            }
+
+            return 0;
+        }
+
+        let mut code = 0;
+
+        for arg in args {
+            if let Some((name, value)) = arg.split_once('=') {
+                aliases.insert(name.to_string(), value.to_string());
+            } else if let Some(value) = aliases.get(*arg) {
+                streams.write(&format!("alias {arg}='{value}'\n"));
+            } else {
+                streams.write_error(&format!("alias: {arg}: not found\n"));
+                code = 1;
+            }
+        }
+
+        code
+    }
+
+    // `unalias -a` removes every alias
+    fn unalias(&self, args: &[&str], streams: &Streams) -> ExitCode {
+        let mut aliases = self.aliases.borrow_mut();
+
+        if args == ["-a"] {
+            aliases.clear();
+            return 0;
+        }
+
+        let mut code = 0;
+
+        for arg in args {
+            if aliases.remove(*arg).is_none() {
+                streams.write_error(&format!("unalias: {arg}: not found\n"));
+                code = 1;
+            }
+        }
+
+        code
+    }
+
+    // Runs a file in this shell, with `args` as `$1`... if there are any
+    async fn source(self: &Rc<Self>, path: &str, args: &[&str], streams: &Streams) -> ExitCode {
+        let content = match self.proc.open(path.to_string(), OpenFlags::READ) {
+            Ok(file) => file.read_sync(u32::MAX).unwrap_or_default(),
+            Err(err) => {
+                streams.write_error(&format!("{path}: {err:?}\n"));
+                return 1;
+            }
+        };
+
+        let list = match parse_with_aliases(&content, &self.aliases.borrow()) {
+            Ok(list) => list,
+            Err(err) => {
+                streams.write_error(&format!("{path}: {err}\n"));
+                return 2;
+            }
+        };
+
+        let saved = if args.is_empty() {
+            None
+        } else {
+            let mut source_args = vec![self.args.borrow()[0].clone()];
+            source_args.extend(args.iter().map(|arg| arg.to_string()));
+
+            Some(self.args.replace(source_args))
+        };
+
+        let code = self.run_list(&list, streams).await;
+
+        if let Some(saved) = saved {
+            self.args.replace(saved);
+        }
+
+        if self.flow.get() == Some(Flow::Return) {
+            self.flow.set(None);
+        }
+
+        code
+    }
+
+    fn variable(&self, name: &str) -> String {
+        let args = self.args.borrow();
+
+        match name {
+            "?" => self.status.get().to_string(),
+            "$" => self.proc.pid.to_string(),
+            "#" => (args.len() - 1).to_string(),
+            "@" | "*" => args[1..].join(" "),
+            name if name.chars().all(|char| char.is_ascii_digit()) => name
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| args.get(index).cloned())
+                .unwrap_or_default(),
+            name => self
+                .proc
+                .env
+                .borrow()
+                .get(name)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    // Expands the variables of a word, unquoted variables are split on whitespace.
+    // A quoted `$@` gives one field per argument.
+    pub(super) fn expand_word(&self, word: &Word) -> Vec<String> {
+        let mut fields = vec![];
+        let mut current: Option<String> = None;
+
+        for part in word.0.iter() {
+            let (name, quoted) = match part {
+                WordPart::Literal { text, .. } => {
+                    current.get_or_insert_with(String::new).push_str(text);
+                    continue;
+                }
+                WordPart::Variable { name, quoted } => (name, *quoted),
+            };
+
+            let pieces: Vec<String> = match (name.as_str(), quoted) {
+                ("@", true) => self.args.borrow()[1..].to_vec(),
+                (_, true) => vec![self.variable(name)],
+                (_, false) => self
+                    .variable(name)
+                    .split_whitespace()
+                    .map(|piece| piece.to_string())
+                    .collect(),
+            };
+
+            let mut pieces = pieces.into_iter();
+
+            if let Some(first) = pieces.next() {
+                current.get_or_insert_with(String::new).push_str(&first);
+            }
+
+            for piece in pieces {
+                fields.extend(current.take());
+                current = Some(piece);
+            }
+        }
+
+        fields.extend(current);
+
+        fields
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{memory::MemoryFs, ROOT};
+    use std::{
+        cell::Cell,
+        collections::BTreeMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
+
+    // Root is global, the tests using it mustn't run at the same time
+    static LOCK: Mutex<()> = Mutex::new(());
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    // Executor ticks before a script is taken to hang
+    const MAX_TICKS: usize = 10_000;
+
+    struct Run {
+        // stdout and stderr together
+        output: String,
+        code: ExitCode,
+        // the empty tmpfs the script ran in
+        dir: String,
+    }
+
+    impl Run {
+        fn file(&self, name: &str) -> String {
+            let file = ROOT.fs.open(&format!("{}/{name}", self.dir), OpenFlags::READ).unwrap();
+
+            file.read_sync(u32::MAX).unwrap_or_default()
+        }
+    }
+
+    // Runs `script` as root in a new shell, until every command it started exited
+    fn execute(script: &str) -> Run {
+        let _lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+
+        let dir = format!("/interpreter{}", RUNS.fetch_add(1, Ordering::Relaxed));
+        ROOT.fs.mkdir(&dir).unwrap();
+        ROOT.fs.mount(&dir, "none", Rc::new(MemoryFs::new()), false).unwrap();
+
+        let shell = ROOT.spawner.spawn::<Shell>();
+        *shell.proc.cwd.borrow_mut() = dir.clone();
+
+        let (reader, writer) = new_pipe();
+        let writer: Rc<dyn WritableDescriptor> = Rc::new(writer);
+        shell.proc.stdin.close();
+        shell.proc.stdout.replace(Some(Rc::clone(&writer)));
+        shell.proc.stderr.replace(Some(writer));
+
+        let list = parse_with_aliases(script, &BTreeMap::new()).unwrap();
+        let code = Rc::new(Cell::new(None));
+
+        let result = Rc::clone(&code);
+        ROOT.executor.add_task(async move {
+            result.set(Some(shell.run(&list).await));
+            shell.proc.exit(0);
+        });
+
+        for _ in 0..MAX_TICKS {
+            ROOT.executor.execute();
+
+            if code.get().is_some() {
+                break;
+            }
+        }
+
+        let code = code.get().expect("The script doesn't finish");
+        let output = reader.read_sync(u32::MAX).unwrap_or_default();
+
+        Run { output, code, dir }
+    }
+
+    #[test]
+    fn pipelines() {
+        // every stage sees the end of its input and exits
+        let run = execute("echo hello | cat | cat");
+        assert_eq!((run.output.as_str(), run.code), ("hello\n", 0));
+
+        // the status is the one of the last stage
+        assert_eq!(execute("true | false").code, 1);
+        assert_eq!(execute("false | true").code, 0);
+        assert_eq!(execute("cat missing | true").code, 0);
+    }
+
+    #[test]
+    fn redirects() {
+        let run = execute("echo one > out; echo two >> out; cat < out");
+        assert_eq!(run.output, "one\ntwo\n");
+        assert_eq!(run.file("out"), "one\ntwo\n");
+
+        // `>` empties the file again
+        let run = execute("echo long > out; echo x > out");
+        assert_eq!(run.file("out"), "x\n");
+
+        let run = execute("cat missing 2> err");
+        assert_eq!((run.output.as_str(), run.code), ("", 1));
+        assert_eq!(run.file("err"), "cat: missing: NoEntry\n");
+
+        // duplicates are made in order
+        let run = execute("cat missing > both 2>&1; cat missing 2>&1 > out");
+        assert_eq!(run.file("both"), "cat: missing: NoEntry\n");
+        assert_eq!(run.file("out"), "");
+        assert_eq!(run.output, "cat: missing: NoEntry\n");
+
+        let run = execute("cat < missing");
+        assert_eq!((run.output.as_str(), run.code), ("sh: missing: NoEntry\n", 1));
+    }
+
+    #[test]
+    fn control() {
+        let run = execute("if [ 1 -lt 2 ]; then echo yes; else echo no; fi");
+        assert_eq!(run.output, "yes\n");
+
+        let run = execute(
+            "for i in a b c d; do
+                if [ $i = b ]; then continue; fi
+                if [ $i = d ]; then break; fi
+                echo $i
+            done",
+        );
+        assert_eq!(run.output, "a\nc\n");
+
+        let run = execute("i=o; while [ $i != xxxo ]; do i=x$i; done; echo $i");
+        assert_eq!(run.output, "xxxo\n");
+
+        let run = execute("false; echo $?; true; echo $?");
+        assert_eq!(run.output, "1\n0\n");
+
+        let run = execute("f() { echo in $1; return 3; echo never; }; f arg; echo $?");
+        assert_eq!((run.output.as_str(), run.code), ("in arg\n3\n", 0));
+
+        let run = execute("false && echo and; false || echo or");
+        assert_eq!((run.output.as_str(), run.code), ("or\n", 0));
+    }
+
+    #[test]
+    fn assignments() {
+        let run = execute("A=old; f() { echo $A; }; A=new f; echo $A");
+        assert_eq!(run.output, "new\nold\n");
+
+        // only for the builtin, not the shell
+        let run = execute("A=1 true; echo x$A; A=2; echo $A");
+        assert_eq!(run.output, "x\n2\n");
+    }
+}
@@ -0,0 +1,539 @@
+use std::fmt::Display;
+
+#[derive(Debug, PartialEq)]
+pub struct SyntaxError {
+    // char index in the input
+    pub position: usize,
+    pub message: String,
+    // the input ended in the middle of a command, more lines could complete it
+    pub incomplete: bool,
+}
+
+impl SyntaxError {
+    pub fn new(position: usize, message: &str) -> Self {
+        SyntaxError {
+            position,
+            message: message.to_string(),
+            incomplete: false,
+        }
+    }
+
+    pub fn incomplete(position: usize, message: &str) -> Self {
+        SyntaxError {
+            incomplete: true,
+            ..SyntaxError::new(position, message)
+        }
+    }
+
+    pub fn is_incomplete(&self) -> bool {
+        self.incomplete
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "syntax error at {}: {}", self.position, self.message)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum WordPart {
+    // quoted text is never split, nor treated as a keyword or operator
+    Literal { text: String, quoted: bool },
+    Variable { name: String, quoted: bool },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Word(pub Vec<WordPart>);
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RedirectKind {
+    // `<`
+    Input,
+    // `>`
+    Output,
+    // `>>`
+    Append,
+    // `>&`, the target is another descriptor
+    Duplicate,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Token {
+    Word(Word),
+    Newline,
+    Semicolon,
+    And,
+    Or,
+    Pipe,
+    LeftParen,
+    RightParen,
+    // the descriptor is only set if written explicitly, like `2>`
+    Redirect { fd: Option<u8>, kind: RedirectKind },
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(_) => f.write_str("word"),
+            Token::Newline => f.write_str("newline"),
+            Token::Semicolon => f.write_str("';'"),
+            Token::And => f.write_str("'&&'"),
+            Token::Or => f.write_str("'||'"),
+            Token::Pipe => f.write_str("'|'"),
+            Token::LeftParen => f.write_str("'('"),
+            Token::RightParen => f.write_str("')'"),
+            Token::Redirect { .. } => f.write_str("redirection"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Spanned {
+    pub token: Token,
+    pub position: usize,
+}
+
+pub fn tokenize(input: &str) -> Result<Vec<Spanned>, SyntaxError> {
+    let mut lexer = Lexer {
+        chars: input.chars().collect(),
+        position: 0,
+    };
+
+    let mut tokens = vec![];
+
+    while let Some(token) = lexer.next_token()? {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        self.chars.get(self.position + 1).copied()
+    }
+
+    fn next_token(&mut self) -> Result<Option<Spanned>, SyntaxError> {
+        // skip blanks, comments and escaped newlines
+        while let Some(char) = self.peek() {
+            if char == ' ' || char == '\t' {
+                self.position += 1;
+            } else if char == '\\' && self.peek_next() == Some('\n') {
+                self.position += 2;
+            } else if char == '#' {
+                while self.peek().is_some_and(|char| char != '\n') {
+                    self.position += 1;
+                }
+            } else {
+                break;
+            }
+        }
+
+        let position = self.position;
+
+        let Some(char) = self.peek() else {
+            return Ok(None);
+        };
+
+        if let Some(token) = self.redirect() {
+            return Ok(Some(Spanned { token, position }));
+        }
+
+        let token = match (char, self.peek_next()) {
+            ('\n', _) => Token::Newline,
+            (';', _) => Token::Semicolon,
+            ('&', Some('&')) => Token::And,
+            ('|', Some('|')) => Token::Or,
+            ('&', _) => {
+                return Err(SyntaxError::new(
+                    position,
+                    "background jobs are not supported",
+                ))
+            }
+            ('|', _) => Token::Pipe,
+            ('(', _) => Token::LeftParen,
+            (')', _) => Token::RightParen,
+            _ => Token::Word(self.word()?),
+        };
+
+        self.position = match token {
+            Token::Word(_) => self.position,
+            Token::And | Token::Or => position + 2,
+            _ => position + 1,
+        };
+
+        Ok(Some(Spanned { token, position }))
+    }
+
+    fn word(&mut self) -> Result<Word, SyntaxError> {
+        let mut parts = vec![];
+
+        while let Some(char) = self.peek() {
+            let start = self.position;
+
+            match char {
+                ' ' | '\t' | '\n' | ';' | '&' | '|' | '<' | '>' | '(' | ')' => break,
+                '\'' => {
+                    self.position += 1;
+
+                    let mut text = String::new();
+
+                    loop {
+                        match self.peek() {
+                            Some('\'') => break,
+                            Some(char) => text.push(char),
+                            None => {
+                                return Err(SyntaxError::incomplete(
+                                    start,
+                                    "unterminated single quote",
+                                ))
+                            }
+                        }
+
+                        self.position += 1;
+                    }
+
+                    self.position += 1;
+                    push_literal(&mut parts, &text, true);
+                }
+                '"' => {
+                    self.position += 1;
+
+                    // an empty string is still a word
+                    push_literal(&mut parts, "", true);
+
+                    loop {
+                        match self.peek() {
+                            Some('"') => break,
+                            // an escaped newline continues the line
+                            Some('\\') if self.peek_next() == Some('\n') => self.position += 2,
+                            Some('\\') if matches!(self.peek_next(), Some('"' | '\\' | '$')) => {
+                                self.position += 1;
+                                push_literal(&mut parts, &self.peek().unwrap().to_string(), true);
+                                self.position += 1;
+                            }
+                            Some('$') => self.variable(&mut parts, true)?,
+                            Some(char) => {
+                                push_literal(&mut parts, &char.to_string(), true);
+                                self.position += 1;
+                            }
+                            None => {
+                                return Err(SyntaxError::incomplete(
+                                    start,
+                                    "unterminated double quote",
+                                ))
+                            }
+                        }
+                    }
+
+                    self.position += 1;
+                }
+                '\\' => {
+                    // a backslash at the end continues on the next line
+                    let Some(escaped) = self.peek_next() else {
+                        return Err(SyntaxError::incomplete(start, "nothing to escape"));
+                    };
+
+                    self.position += 2;
+
+                    if escaped != '\n' {
+                        push_literal(&mut parts, &escaped.to_string(), true);
+                    }
+                }
+                '$' => self.variable(&mut parts, false)?,
+                char => {
+                    self.position += 1;
+                    push_literal(&mut parts, &char.to_string(), false);
+                }
+            }
+        }
+
+        Ok(Word(parts))
+    }
+
+    // Reads a redirection operator with an optional descriptor number before it
+    fn redirect(&mut self) -> Option<Token> {
+        let mut index = self.position;
+        let mut fd = None;
+
+        if let (Some(digit), Some('<' | '>')) = (self.peek(), self.peek_next()) {
+            if let Some(digit) = digit.to_digit(10) {
+                fd = Some(digit as u8);
+                index += 1;
+            }
+        }
+
+        let (kind, length) = match (self.chars.get(index), self.chars.get(index + 1)) {
+            (Some('<'), _) => (RedirectKind::Input, 1),
+            (Some('>'), Some('>')) => (RedirectKind::Append, 2),
+            (Some('>'), Some('&')) => (RedirectKind::Duplicate, 2),
+            (Some('>'), _) => (RedirectKind::Output, 1),
+            _ => return None,
+        };
+
+        self.position = index + length;
+
+        Some(Token::Redirect { fd, kind })
+    }
+
+    // Reads `$NAME`, `${NAME}` or a special parameter like `$?`
+    fn variable(&mut self, parts: &mut Vec<WordPart>, quoted: bool) -> Result<(), SyntaxError> {
+        let start = self.position;
+        self.position += 1;
+
+        let name = match self.peek() {
+            Some('{') => {
+                self.position += 1;
+
+                let mut name = String::new();
+
+                loop {
+                    match self.peek() {
+                        Some('}') => break,
+                        Some(char) => name.push(char),
+                        None => return Err(SyntaxError::incomplete(start, "unterminated '${'")),
+                    }
+
+                    self.position += 1;
+                }
+
+                self.position += 1;
+
+                if !is_name(&name) && !is_special(&name) {
+                    return Err(SyntaxError::new(start, "bad substitution"));
+                }
+
+                name
+            }
+            Some(char) if is_special(&char.to_string()) => {
+                self.position += 1;
+                char.to_string()
+            }
+            Some(char) if char.is_ascii_alphabetic() || char == '_' => {
+                let mut name = String::new();
+
+                while let Some(char) = self.peek() {
+                    if !(char.is_ascii_alphanumeric() || char == '_') {
+                        break;
+                    }
+
+                    name.push(char);
+                    self.position += 1;
+                }
+
+                name
+            }
+            // a lone dollar sign
+            _ => {
+                push_literal(parts, "$", quoted);
+                return Ok(());
+            }
+        };
+
+        parts.push(WordPart::Variable { name, quoted });
+
+        Ok(())
+    }
+}
+
+fn push_literal(parts: &mut Vec<WordPart>, string: &str, is_quoted: bool) {
+    if let Some(WordPart::Literal { text, quoted }) = parts.last_mut() {
+        if *quoted == is_quoted {
+            text.push_str(string);
+            return;
+        }
+    }
+
+    parts.push(WordPart::Literal {
+        text: string.to_string(),
+        quoted: is_quoted,
+    });
+}
+
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+// `$?` is the last exit code, `$$` the pid of the shell,
+// `$0`, `$1`... the arguments, `$#` their count and `$@` all of them
+fn is_special(name: &str) -> bool {
+    matches!(name, "?" | "$" | "#" | "@" | "*")
+        || !name.is_empty() && name.chars().all(|char| char.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn literal(text: &str, quoted: bool) -> WordPart {
+        WordPart::Literal {
+            text: text.to_string(),
+            quoted,
+        }
+    }
+
+    fn variable(name: &str, quoted: bool) -> WordPart {
+        WordPart::Variable {
+            name: name.to_string(),
+            quoted,
+        }
+    }
+
+    fn tokens(input: &str) -> Vec<Token> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .map(|spanned| spanned.token)
+            .collect()
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(
+            tokens("a&&b || c;d | e # comment"),
+            vec![
+                Token::Word(Word(vec![literal("a", false)])),
+                Token::And,
+                Token::Word(Word(vec![literal("b", false)])),
+                Token::Or,
+                Token::Word(Word(vec![literal("c", false)])),
+                Token::Semicolon,
+                Token::Word(Word(vec![literal("d", false)])),
+                Token::Pipe,
+                Token::Word(Word(vec![literal("e", false)])),
+            ]
+        );
+    }
+
+    #[test]
+    fn quotes() {
+        assert_eq!(
+            tokens(r#"echo "hello  world"x 'a $b' \"c"#),
+            vec![
+                Token::Word(Word(vec![literal("echo", false)])),
+                Token::Word(Word(vec![
+                    literal("hello  world", true),
+                    literal("x", false)
+                ])),
+                Token::Word(Word(vec![literal("a $b", true)])),
+                Token::Word(Word(vec![literal("\"", true), literal("c", false)])),
+            ]
+        );
+
+        assert_eq!(
+            tokens("''"),
+            vec![Token::Word(Word(vec![literal("", true)]))]
+        );
+    }
+
+    #[test]
+    fn continuation() {
+        assert_eq!(
+            tokens("echo ab\\\ncd \\\n \"e\\\nf\""),
+            vec![
+                Token::Word(Word(vec![literal("echo", false)])),
+                Token::Word(Word(vec![literal("abcd", false)])),
+                Token::Word(Word(vec![literal("ef", true)])),
+            ]
+        );
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(
+            tokens(r#"$A${B}c "$?-$" $ $12 ${12} "$@""#),
+            vec![
+                Token::Word(Word(vec![
+                    variable("A", false),
+                    variable("B", false),
+                    literal("c", false)
+                ])),
+                Token::Word(Word(vec![
+                    literal("", true),
+                    variable("?", true),
+                    literal("-$", true)
+                ])),
+                Token::Word(Word(vec![literal("$", false)])),
+                Token::Word(Word(vec![variable("1", false), literal("2", false)])),
+                Token::Word(Word(vec![variable("12", false)])),
+                Token::Word(Word(vec![literal("", true), variable("@", true)])),
+            ]
+        );
+    }
+
+    #[test]
+    fn redirections() {
+        let word = |text| Token::Word(Word(vec![literal(text, false)]));
+        let redirect = |fd, kind| Token::Redirect { fd, kind };
+
+        assert_eq!(
+            tokens("a>b >>c 2>&1 <d 2>e a2>f"),
+            vec![
+                word("a"),
+                redirect(None, RedirectKind::Output),
+                word("b"),
+                redirect(None, RedirectKind::Append),
+                word("c"),
+                redirect(Some(2), RedirectKind::Duplicate),
+                word("1"),
+                redirect(None, RedirectKind::Input),
+                word("d"),
+                redirect(Some(2), RedirectKind::Output),
+                word("e"),
+                word("a2"),
+                redirect(None, RedirectKind::Output),
+                word("f"),
+            ]
+        );
+    }
+
+    #[test]
+    fn positions() {
+        let positions: Vec<usize> = tokenize("ab && cd")
+            .unwrap()
+            .iter()
+            .map(|spanned| spanned.position)
+            .collect();
+
+        assert_eq!(positions, vec![0, 3, 6]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            tokenize("echo 'abc"),
+            Err(SyntaxError::incomplete(5, "unterminated single quote"))
+        );
+        assert_eq!(
+            tokenize("echo \"abc"),
+            Err(SyntaxError::incomplete(5, "unterminated double quote"))
+        );
+        assert_eq!(
+            tokenize("echo a\\"),
+            Err(SyntaxError::incomplete(6, "nothing to escape"))
+        );
+        assert_eq!(
+            tokenize("echo ${a-b}"),
+            Err(SyntaxError::new(5, "bad substitution"))
+        );
+        assert_eq!(
+            tokenize("a & b"),
+            Err(SyntaxError::new(2, "background jobs are not supported"))
+        );
+    }
+}
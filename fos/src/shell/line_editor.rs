@@ -0,0 +1,264 @@
+#[derive(Debug, PartialEq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Tab,
+    // Ctrl-W
+    DeleteWord,
+    // Ctrl-U
+    DeleteLine,
+    // Ctrl-R
+    ReverseSearch,
+    // Ctrl-G
+    Cancel,
+}
+
+// Turns the chars read from stdin into keys, escape sequences span multiple chars.
+pub struct KeyDecoder {
+    escape: Option<String>,
+}
+
+impl KeyDecoder {
+    pub fn new() -> Self {
+        KeyDecoder { escape: None }
+    }
+
+    pub fn feed(&mut self, char: char) -> Option<Key> {
+        if let Some(sequence) = self.escape.as_mut() {
+            sequence.push(char);
+
+            if sequence.len() == 1 && char != '[' {
+                self.escape = None;
+                return None;
+            }
+
+            if !(char.is_ascii_alphabetic() || char == '~') {
+                return None;
+            }
+
+            let sequence = self.escape.take().unwrap();
+
+            return match &sequence[1..] {
+                "A" => Some(Key::Up),
+                "B" => Some(Key::Down),
+                "C" => Some(Key::Right),
+                "D" => Some(Key::Left),
+                "H" | "1~" => Some(Key::Home),
+                "F" | "4~" => Some(Key::End),
+                "3~" => Some(Key::Delete),
+                _ => None,
+            };
+        }
+
+        match char {
+            '\x1b' => {
+                self.escape = Some(String::new());
+                None
+            }
+            '\n' | '\r' => Some(Key::Enter),
+            '\x7f' | '\x08' => Some(Key::Backspace),
+            '\t' => Some(Key::Tab),
+            '\x01' => Some(Key::Home),
+            '\x05' => Some(Key::End),
+            '\x17' => Some(Key::DeleteWord),
+            '\x15' => Some(Key::DeleteLine),
+            '\x12' => Some(Key::ReverseSearch),
+            '\x07' => Some(Key::Cancel),
+            char if char.is_control() => None,
+            char => Some(Key::Char(char)),
+        }
+    }
+}
+
+pub struct LineEditor {
+    buffer: Vec<char>,
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        LineEditor {
+            buffer: vec![],
+            cursor: 0,
+        }
+    }
+
+    pub fn line(&self) -> String {
+        self.buffer.iter().collect()
+    }
+
+    pub fn before_cursor(&self) -> String {
+        self.buffer[..self.cursor].iter().collect()
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        for char in text.chars() {
+            self.buffer.insert(self.cursor, char);
+            self.cursor += 1;
+        }
+    }
+
+    // Returns the line and clears the editor
+    pub fn take(&mut self) -> String {
+        let line = self.line();
+
+        self.buffer.clear();
+        self.cursor = 0;
+
+        line
+    }
+
+    // Replaces the line, the cursor is placed at the end
+    pub fn set(&mut self, line: &str) {
+        self.buffer = line.chars().collect();
+        self.cursor = self.buffer.len();
+    }
+
+    // Applies an editing key, returns false if the key doesn't edit the line.
+    pub fn edit(&mut self, key: &Key) -> bool {
+        match key {
+            Key::Char(char) => {
+                self.buffer.insert(self.cursor, *char);
+                self.cursor += 1;
+            }
+            Key::Backspace => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.buffer.remove(self.cursor);
+                }
+            }
+            Key::Delete => {
+                if self.cursor < self.buffer.len() {
+                    self.buffer.remove(self.cursor);
+                }
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.buffer.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.buffer.len(),
+            Key::DeleteWord => {
+                let mut start = self.cursor;
+
+                // skip the whitespace before the cursor, then the word itself
+                while start > 0 && self.buffer[start - 1].is_whitespace() {
+                    start -= 1;
+                }
+
+                while start > 0 && !self.buffer[start - 1].is_whitespace() {
+                    start -= 1;
+                }
+
+                self.buffer.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            Key::DeleteLine => {
+                self.buffer.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::Enter | Key::Up | Key::Down | Key::Tab | Key::ReverseSearch | Key::Cancel => {
+                return false
+            }
+        }
+
+        true
+    }
+
+    // Escape sequence that redraws the whole line and places the cursor
+    pub fn render(&self, prompt: &str) -> String {
+        let mut output = format!("\r{prompt}{}\x1b[K", self.line());
+
+        let after = self.buffer.len() - self.cursor;
+        if after > 0 {
+            output.push_str(&format!("\x1b[{after}D"));
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn type_keys(editor: &mut LineEditor, input: &str) {
+        let mut decoder = KeyDecoder::new();
+
+        for char in input.chars() {
+            if let Some(key) = decoder.feed(char) {
+                editor.edit(&key);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_escape_sequences() {
+        let mut decoder = KeyDecoder::new();
+
+        let keys: Vec<Key> = "a\x1b[D\x1b[3~\x7f\n"
+            .chars()
+            .filter_map(|char| decoder.feed(char))
+            .collect();
+
+        assert_eq!(
+            keys,
+            vec![
+                Key::Char('a'),
+                Key::Left,
+                Key::Delete,
+                Key::Backspace,
+                Key::Enter
+            ]
+        );
+    }
+
+    #[test]
+    fn backspace_and_delete() {
+        let mut editor = LineEditor::new();
+
+        type_keys(&mut editor, "abcd\x7f\x1b[D\x1b[D\x1b[3~");
+
+        assert_eq!(editor.line(), "ac");
+        assert_eq!(editor.cursor, 1);
+    }
+
+    #[test]
+    fn insert_in_the_middle() {
+        let mut editor = LineEditor::new();
+
+        type_keys(&mut editor, "ac\x1b[Db\x1b[Hx\x1b[Fy");
+
+        assert_eq!(editor.line(), "xabcy");
+        assert_eq!(editor.cursor, 5);
+    }
+
+    #[test]
+    fn delete_word_and_line() {
+        let mut editor = LineEditor::new();
+
+        type_keys(&mut editor, "cat some file  \x17");
+        assert_eq!(editor.line(), "cat some ");
+
+        type_keys(&mut editor, "\x1b[D\x15");
+        assert_eq!(editor.line(), " ");
+        assert_eq!(editor.cursor, 0);
+    }
+
+    #[test]
+    fn render_cursor() {
+        let mut editor = LineEditor::new();
+
+        type_keys(&mut editor, "abc\x1b[D\x1b[D");
+
+        assert_eq!(editor.render("$ "), "\r$ abc\x1b[K\x1b[2D");
+        assert_eq!(editor.take(), "abc");
+        assert_eq!(editor.render("$ "), "\r$ \x1b[K");
+    }
+}
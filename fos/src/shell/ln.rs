@@ -0,0 +1,67 @@
+use std::rc::Rc;
+
+use crate::{
+    root::{Proc, Process, descriptor::WritableDescriptor},
+    Kind,
+};
+
+pub struct LnProgram {
+    pub proc: Proc,
+}
+
+impl Process for LnProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        LnProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "ln"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        // `-s` creates a symbolic link instead of a hard link
+        let (symbolic, args) = match args.split_first() {
+            Some((&"-s", rest)) => (true, rest),
+            _ => (false, &args[..]),
+        };
+
+        let [target, path] = args[..] else {
+            self.proc.stderr.write("ln: expected a target and a link name\n");
+            self.proc.exit(1);
+            return;
+        };
+
+        // linking into a directory keeps the name
+        let path = match self.proc.stat(path) {
+            Ok(stat) if stat.kind == Kind::Directory => {
+                let name = target.trim_end_matches('/').rsplit('/').next().unwrap_or(target);
+
+                format!("{}/{name}", path.trim_end_matches('/'))
+            }
+            _ => path.to_string(),
+        };
+
+        let linked = if symbolic {
+            self.proc.symlink(target, &path)
+        } else {
+            self.proc.link(target, &path)
+        };
+
+        let code = match linked {
+            Ok(()) => 0,
+            Err(err) => {
+                self.proc.stderr.write(&format!("ln: {path}: {err:?}\n"));
+                1
+            }
+        };
+
+        self.proc.exit(code);
+    }
+}
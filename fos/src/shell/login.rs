@@ -0,0 +1,138 @@
+use std::rc::Rc;
+
+use crate::{
+    root::{
+        descriptor::{ReadableDescriptor, WritableDescriptor},
+        ExitCode, Proc, Process,
+    },
+    OpenError, ROOT,
+};
+
+use super::Shell;
+
+pub struct LoginProgram {
+    pub proc: Proc,
+}
+
+impl Process for LoginProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        LoginProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "login"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        if args.len() > 1 {
+            self.proc.stderr.write("usage: login [user]\n");
+            self.proc.exit(1);
+            return;
+        }
+
+        let name = args.first().map(|name| name.to_string());
+        let self_clone = Rc::clone(&self);
+
+        ROOT.executor.add_task(async move {
+            let proc = &self_clone.proc;
+
+            let name = match name {
+                Some(name) => Some(name),
+                None => {
+                    proc.stdout.write("login: ");
+                    read_line(proc, true).await
+                }
+            };
+
+            let Some(name) = name else {
+                proc.exit(1);
+                return;
+            };
+
+            // a new session starts at home
+            let code = match start_shell(proc, &name, true).await {
+                Ok(code) => code,
+                Err(_) => {
+                    proc.stderr.write("Login incorrect\n");
+                    1
+                }
+            };
+
+            proc.exit(code);
+        });
+    }
+}
+
+// A line from stdin, a password isn't shown while it's typed
+pub async fn read_line(proc: &Proc, echo: bool) -> Option<String> {
+    let mut line = String::new();
+
+    loop {
+        match proc.stdin.read_char().await {
+            Ok('\n' | '\r') => {
+                proc.stdout.write_char('\n');
+                return Some(line);
+            }
+            Ok('\x7f' | '\x08') => {
+                if line.pop().is_some() && echo {
+                    proc.stdout.write("\x08\x1b[K");
+                }
+            }
+            Ok(char) => {
+                line.push(char);
+
+                if echo {
+                    proc.stdout.write_char(char);
+                }
+            }
+            Err(_) => return None,
+        }
+    }
+}
+
+// Runs a shell as the user `name` until it exits, asking for the password unless we are
+// root or there is none
+pub async fn start_shell(proc: &Proc, name: &str, home: bool) -> Result<ExitCode, OpenError> {
+    let user = proc
+        .users()
+        .into_iter()
+        .find(|user| user.name == name)
+        .ok_or(OpenError::NoEntry)?;
+
+    let password = if proc.credentials().is_root() || !proc.needs_password(&user) {
+        String::new()
+    } else {
+        proc.stdout.write("Password: ");
+        read_line(proc, false).await.unwrap_or_default()
+    };
+
+    let shell: Rc<dyn Process> = proc.spawn::<Shell>();
+    let shell_proc = shell.get_proc();
+
+    let switched = shell_proc.switch_user(name, &password).and_then(|user| {
+        if home {
+            shell_proc.chdir(&user.home)?;
+        }
+
+        Ok(())
+    });
+
+    if let Err(err) = switched {
+        // it never ran
+        shell_proc.exit(1);
+        proc.wait(&shell).await;
+
+        return Err(err);
+    }
+
+    Rc::clone(&shell).main(vec![]);
+
+    Ok(proc.wait(&shell).await)
+}
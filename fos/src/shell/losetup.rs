@@ -0,0 +1,66 @@
+use std::rc::Rc;
+
+#[cfg(feature = "host")]
+use crate::{block::FileDisk, image::BLOCK_SIZE};
+use crate::{
+    root::{descriptor::WritableDescriptor, Proc, Process},
+    OpenError,
+};
+
+pub struct LosetupProgram {
+    pub proc: Proc,
+}
+
+impl Process for LosetupProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        LosetupProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "losetup"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    // Attaches a file of the host as `/dev/loopN` and prints the path
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        let [file] = args[..] else {
+            self.proc.stderr.write("usage: losetup FILE\n");
+            self.proc.exit(1);
+            return;
+        };
+
+        match self.attach(file) {
+            Ok(path) => {
+                self.proc.stdout.write(&format!("{path}\n"));
+                self.proc.exit(0);
+            }
+            Err(err) => {
+                self.proc
+                    .stderr
+                    .write(&format!("losetup: {file}: {err:?}\n"));
+                self.proc.exit(1);
+            }
+        }
+    }
+}
+
+impl LosetupProgram {
+    #[cfg(feature = "host")]
+    fn attach(&self, file: &str) -> Result<String, OpenError> {
+        let disk = FileDisk::open(file, BLOCK_SIZE)?;
+
+        self.proc.attach("loop", Rc::new(disk))
+    }
+
+    // only native frontends can reach files of the host
+    #[cfg(not(feature = "host"))]
+    fn attach(&self, _file: &str) -> Result<String, OpenError> {
+        Err(OpenError::NotSupported)
+    }
+}
@@ -1,10 +1,12 @@
 use std::rc::Rc;
 
 use crate::{
-    root::{Proc, Process, descriptor::{ReadableDescriptor, WritableDescriptor}},
-    ROOT, Inode,
+    root::{Proc, Process, descriptor::WritableDescriptor},
+    Group, Kind, Stat, User,
 };
 
+use super::stat::{format_mode, format_owner, format_time};
+
 pub struct LsProgram {
     pub proc: Proc,
 }
@@ -26,31 +28,106 @@ impl Process for LsProgram {
     }
 
     fn main(self: Rc<Self>, args: Vec<&str>) {
-        assert!(args.len() == 1);
-
-        let dir_name = args[0];
-
-        let dir = self.proc.open_dir(dir_name.to_string());
-
-        match dir {
-            Ok(desc) => {    
-                desc.0.iter().for_each(|node| {
-                    match node {
-                        (name, Inode::Directory(_)) => {
-                            self.proc.stdout.write(&format!("Directory: {:?} \n", name.0));
-                        },
-                        (name, Inode::File(_)) => {
-                            self.proc.stdout.write(&format!("File: {:?}\n", name.0));
-                        }
+        // `-l` shows the metadata of every entry
+        let (long, args) = match args.split_first() {
+            Some((&"-l", rest)) => (true, rest),
+            _ => (false, &args[..]),
+        };
+
+        // list the working directory by default
+        let paths = if args.is_empty() { &["."][..] } else { args };
+
+        let (users, groups) = (self.proc.users(), self.proc.groups());
+        let mut code = 0;
+
+        for (index, path) in paths.iter().enumerate() {
+            // a link to a directory lists the directory
+            let directory = matches!(self.proc.stat(path), Ok(Stat { kind: Kind::Directory, .. }));
+
+            if !directory {
+                match self.proc.lstat(path) {
+                    Ok(stat) => {
+                        let line = self.format_entry(path, path, &stat, long, &users, &groups);
+                        self.proc.stdout.write(&line);
+                    }
+                    Err(err) => {
+                        self.proc.stderr.write(&format!("ls: {path}: {err:?}\n"));
+                        code = 1;
                     }
-                });
-            },
-            Err(err) => {
-                let err = format!("Error: {:?}", err);
-                self.proc.stdout.write(&err);
+                }
+
+                continue;
             }
+
+            match self.proc.readdir(path) {
+                Ok(entries) => {
+                    // the directories are told apart when there are more
+                    if paths.len() > 1 {
+                        let separator = if index > 0 { "\n" } else { "" };
+                        self.proc.stdout.write(&format!("{separator}{path}:\n"));
+                    }
+
+                    for (name, stat) in entries.iter() {
+                        let line = self.format_entry(
+                            &format!("{path}/{name}"),
+                            name,
+                            stat,
+                            long,
+                            &users,
+                            &groups,
+                        );
+                        self.proc.stdout.write(&line);
+                    }
+                }
+                Err(err) => {
+                    self.proc.stderr.write(&format!("ls: {path}: {err:?}\n"));
+                    code = 1;
+                }
+            }
+        }
+
+        self.proc.exit(code);
+    }
+}
+
+impl LsProgram {
+    // One line for the entry `name` found at `path`
+    fn format_entry(
+        &self,
+        path: &str,
+        name: &str,
+        stat: &Stat,
+        long: bool,
+        users: &[User],
+        groups: &[Group],
+    ) -> String {
+        if long {
+            let name = match self.proc.readlink(path) {
+                Ok(target) => format!("{name} -> {target}"),
+                Err(_) => name.to_string(),
+            };
+
+            let (user, group) = format_owner(stat, users, groups);
+
+            return format!(
+                "{} {:>2} {user:<6} {group:<6} {:>6} {} {name}\n",
+                format_mode(stat),
+                stat.links,
+                stat.size,
+                &format_time(stat.modified)[..16],
+            );
         }
 
-        self.proc.exit();
+        match stat.kind {
+            Kind::Directory => format!("Directory: {:?} \n", name),
+            Kind::File => format!("File: {:?}\n", name),
+            Kind::Symlink => {
+                let target = self.proc.readlink(path).unwrap_or_default();
+                format!("Link: {:?} -> {:?}\n", name, target)
+            }
+            Kind::BlockDevice | Kind::CharDevice => format!("Device: {:?}\n", name),
+            Kind::Fifo => format!("Pipe: {:?}\n", name),
+            Kind::Socket => format!("Socket: {:?}\n", name),
+        }
     }
 }
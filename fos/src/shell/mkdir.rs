@@ -0,0 +1,44 @@
+use std::rc::Rc;
+
+use crate::root::{Proc, Process, descriptor::WritableDescriptor};
+
+pub struct MkdirProgram {
+    pub proc: Proc,
+}
+
+impl Process for MkdirProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        MkdirProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "mkdir"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        if args.is_empty() {
+            self.proc.stderr.write("mkdir: missing operand\n");
+            self.proc.exit(1);
+            return;
+        }
+
+        let mut code = 0;
+
+        // parents have to exist already
+        for path in args {
+            if let Err(err) = self.proc.mkdir(path) {
+                self.proc.stderr.write(&format!("mkdir: {path}: {err:?}\n"));
+                code = 1;
+            }
+        }
+
+        self.proc.exit(code);
+    }
+}
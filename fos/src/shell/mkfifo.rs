@@ -0,0 +1,46 @@
+use std::rc::Rc;
+
+use crate::root::{descriptor::WritableDescriptor, Proc, Process};
+
+pub struct MkfifoProgram {
+    pub proc: Proc,
+}
+
+impl Process for MkfifoProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        MkfifoProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "mkfifo"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        if args.is_empty() {
+            self.proc.stderr.write("mkfifo: missing operand\n");
+            self.proc.exit(1);
+            return;
+        }
+
+        let mut code = 0;
+
+        // unrelated processes can then talk through the path
+        for path in args {
+            if let Err(err) = self.proc.mkfifo(path) {
+                self.proc
+                    .stderr
+                    .write(&format!("mkfifo: {path}: {err:?}\n"));
+                code = 1;
+            }
+        }
+
+        self.proc.exit(code);
+    }
+}
@@ -0,0 +1,53 @@
+use std::rc::Rc;
+
+use crate::{
+    image::ImageFs,
+    root::{descriptor::WritableDescriptor, Proc, Process},
+};
+
+pub struct MkfsProgram {
+    pub proc: Proc,
+}
+
+impl Process for MkfsProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        MkfsProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "mkfs"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    // Writes an empty image to a block device, which can then be mounted with `-t imgfs`
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        let [device] = args[..] else {
+            self.proc.stderr.write("usage: mkfs DEVICE\n");
+            self.proc.exit(1);
+            return;
+        };
+
+        // one inode for every 4 blocks, like the host tool
+        let result = self.proc.block_device(device).and_then(|disk| {
+            let inodes = (disk.block_count() / 4) as u32;
+
+            ImageFs::format(disk, inodes)
+        });
+
+        match result {
+            Ok(_) => self.proc.exit(0),
+            Err(err) => {
+                self.proc
+                    .stderr
+                    .write(&format!("mkfs: {device}: {err:?}\n"));
+                self.proc.exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::rc::Rc;
+
+use crate::root::{descriptor::WritableDescriptor, ExitCode, Proc, Process};
+
+pub struct MountProgram {
+    pub proc: Proc,
+}
+
+impl Process for MountProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        MountProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "mount"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        let code = match args[..] {
+            // list what is mounted
+            [] => {
+                for mount in self.proc.mounts() {
+                    let options = if mount.read_only { "ro" } else { "rw" };
+
+                    self.proc.stdout.write(&format!(
+                        "{} on {} type {} ({options})\n",
+                        mount.source,
+                        mount.path,
+                        mount.fs.name()
+                    ));
+                }
+
+                0
+            }
+            ["-t", kind, source, dir] => self.mount(kind, source, dir, ""),
+            // like `ro` to refuse changes or `uid=1000,umask=077` on vfat
+            ["-t", kind, "-o", options, source, dir] => self.mount(kind, source, dir, options),
+            _ => {
+                self.proc
+                    .stderr
+                    .write("usage: mount [-t type [-o options] source directory]\n");
+                1
+            }
+        };
+
+        self.proc.exit(code);
+    }
+}
+
+impl MountProgram {
+    fn mount(&self, kind: &str, source: &str, dir: &str, options: &str) -> ExitCode {
+        match self.proc.mount(kind, source, dir, options) {
+            Ok(()) => 0,
+            Err(err) => {
+                self.proc.stderr.write(&format!("mount: {dir}: {err:?}\n"));
+                1
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::rc::Rc;
+
+use crate::{
+    root::{Proc, Process, descriptor::WritableDescriptor},
+    Kind,
+};
+
+pub struct MvProgram {
+    pub proc: Proc,
+}
+
+impl Process for MvProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        MvProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "mv"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        let [from, to] = args[..] else {
+            self.proc.stderr.write("mv: expected a source and a destination\n");
+            self.proc.exit(1);
+            return;
+        };
+
+        // moving into a directory keeps the name
+        let to = match self.proc.stat(to) {
+            Ok(stat) if stat.kind == Kind::Directory => {
+                let name = from.trim_end_matches('/').rsplit('/').next().unwrap_or(from);
+
+                format!("{}/{name}", to.trim_end_matches('/'))
+            }
+            _ => to.to_string(),
+        };
+
+        let code = match self.proc.rename(from, &to) {
+            Ok(()) => 0,
+            Err(err) => {
+                self.proc.stderr.write(&format!("mv: {from}: {err:?}\n"));
+                1
+            }
+        };
+
+        self.proc.exit(code);
+    }
+}
@@ -0,0 +1,776 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+use super::lexer::{is_name, tokenize, RedirectKind, Spanned, SyntaxError, Token, Word, WordPart};
+
+#[derive(Debug, PartialEq)]
+pub struct Redirect {
+    pub fd: u8,
+    pub kind: RedirectKind,
+    // a file, or a descriptor number for `>&`
+    pub target: Word,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SimpleCommand {
+    // `NAME=value` before the command
+    pub assignments: Vec<(String, Word)>,
+    pub words: Vec<Word>,
+    // applied in order
+    pub redirects: Vec<Redirect>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Compound {
+    // `if` and `elif` conditions with their bodies
+    If {
+        branches: Vec<(List, List)>,
+        otherwise: Option<List>,
+    },
+    While {
+        condition: List,
+        body: List,
+    },
+    // without `in` the loop goes over the arguments
+    For {
+        name: String,
+        words: Option<Vec<Word>>,
+        body: List,
+    },
+    // `{ list; }`
+    Group(List),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Simple(SimpleCommand),
+    // redirections after a compound command apply to all of it
+    Compound(Compound, Vec<Redirect>),
+    // `name() body`
+    Function(String, Rc<FunctionBody>),
+}
+
+// The body of a function is a compound command with its redirections
+pub type FunctionBody = (Compound, Vec<Redirect>);
+
+// Commands joined by `|`, the output of each command is the input of the next one
+#[derive(Debug, PartialEq)]
+pub struct Pipeline(pub Vec<Command>);
+
+#[derive(Debug, PartialEq)]
+pub enum Operator {
+    And,
+    Or,
+}
+
+// Pipelines joined by `&&` and `||`
+#[derive(Debug, PartialEq)]
+pub struct AndOr {
+    pub first: Pipeline,
+    pub rest: Vec<(Operator, Pipeline)>,
+}
+
+// Commands separated by `;` or newlines
+pub type List = Vec<AndOr>;
+
+// Parses the input replacing the first word of every simple command if it's an alias
+pub fn parse_with_aliases(
+    input: &str,
+    aliases: &BTreeMap<String, String>,
+) -> Result<List, SyntaxError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        index: 0,
+        end: input.chars().count(),
+        aliases,
+    };
+
+    parser.list(&[])
+}
+
+// Words that start or end compound commands when they are the first word
+const KEYWORDS: [&str; 10] = [
+    "if", "then", "elif", "else", "fi", "while", "do", "done", "for", "{",
+];
+
+// The text of a word without quotes or variables
+fn plain(word: &Word) -> Option<&str> {
+    match word.0.as_slice() {
+        [WordPart::Literal {
+            text,
+            quoted: false,
+        }] => Some(text),
+        _ => None,
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Spanned>,
+    index: usize,
+    // position reported for errors at the end of the input
+    end: usize,
+    aliases: &'a BTreeMap<String, String>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|spanned| &spanned.token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.end, |spanned| spanned.position)
+    }
+
+    // The current token if it's an unquoted word
+    fn keyword(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(word)) => plain(word),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, keyword: &str) -> Result<(), SyntaxError> {
+        if self.keyword() != Some(keyword) {
+            return Err(self.unexpected());
+        }
+
+        self.index += 1;
+
+        Ok(())
+    }
+
+    fn unexpected(&self) -> SyntaxError {
+        if let Some(word) = self.keyword() {
+            return SyntaxError::new(self.position(), &format!("unexpected '{word}'"));
+        }
+
+        match self.peek() {
+            Some(token) => SyntaxError::new(self.position(), &format!("unexpected {token}")),
+            None => SyntaxError::incomplete(self.end, "unexpected end of input"),
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while matches!(self.peek(), Some(Token::Newline | Token::Semicolon)) {
+            self.index += 1;
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Token::Newline) {
+            self.index += 1;
+        }
+    }
+
+    // Reads commands until the end or one of the `terminators` keywords
+    fn list(&mut self, terminators: &[&str]) -> Result<List, SyntaxError> {
+        let mut list = vec![];
+
+        let terminated = |parser: &Self| {
+            parser
+                .keyword()
+                .is_some_and(|keyword| terminators.contains(&keyword))
+        };
+
+        self.skip_separators();
+
+        while self.peek().is_some() && !terminated(self) {
+            list.push(self.and_or()?);
+
+            // a keyword can directly follow a compound command, like `fi done`
+            if terminated(self) {
+                break;
+            }
+
+            match self.peek() {
+                Some(Token::Newline | Token::Semicolon) => self.skip_separators(),
+                Some(_) => return Err(self.unexpected()),
+                None => {}
+            }
+        }
+
+        Ok(list)
+    }
+
+    fn and_or(&mut self) -> Result<AndOr, SyntaxError> {
+        let first = self.pipeline()?;
+        let mut rest = vec![];
+
+        loop {
+            let operator = match self.peek() {
+                Some(Token::And) => Operator::And,
+                Some(Token::Or) => Operator::Or,
+                _ => break,
+            };
+
+            self.index += 1;
+
+            // the command can continue on the next line
+            self.skip_newlines();
+
+            rest.push((operator, self.pipeline()?));
+        }
+
+        Ok(AndOr { first, rest })
+    }
+
+    fn pipeline(&mut self) -> Result<Pipeline, SyntaxError> {
+        let mut commands = vec![self.command()?];
+
+        while self.peek() == Some(&Token::Pipe) {
+            self.index += 1;
+            self.skip_newlines();
+
+            commands.push(self.command()?);
+        }
+
+        Ok(Pipeline(commands))
+    }
+
+    // The body of a compound command, which can't be empty
+    fn body(&mut self, terminators: &[&str]) -> Result<List, SyntaxError> {
+        let list = self.list(terminators)?;
+
+        if list.is_empty() {
+            return Err(self.unexpected());
+        }
+
+        Ok(list)
+    }
+
+    // Replaces the current word with the tokens of its alias
+    fn expand_alias(&mut self) -> Result<(), SyntaxError> {
+        // an alias isn't expanded again inside its own value, so `ls='ls -a'` works
+        let mut expanded = vec![];
+
+        while let Some(name) = self.keyword() {
+            if expanded.iter().any(|alias| alias == name) {
+                break;
+            }
+
+            let Some(value) = self.aliases.get(name) else {
+                break;
+            };
+
+            let position = self.position();
+
+            // errors are reported at the alias
+            let tokens = tokenize(value).map_err(|err| SyntaxError::new(position, &err.message))?;
+            let tokens = tokens.into_iter().map(|spanned| Spanned {
+                token: spanned.token,
+                position,
+            });
+
+            expanded.push(name.to_string());
+            self.tokens.splice(self.index..self.index + 1, tokens);
+        }
+
+        Ok(())
+    }
+
+    fn command(&mut self) -> Result<Command, SyntaxError> {
+        self.expand_alias()?;
+
+        let compound = match self.keyword() {
+            Some("if") => self.if_clause()?,
+            Some("while") => self.while_clause()?,
+            Some("for") => self.for_clause()?,
+            Some("{") => {
+                self.index += 1;
+
+                let list = self.body(&["}"])?;
+                self.expect("}")?;
+
+                Compound::Group(list)
+            }
+            Some(keyword) if KEYWORDS.contains(&keyword) || keyword == "}" => {
+                return Err(self.unexpected());
+            }
+            _ => {
+                if self
+                    .tokens
+                    .get(self.index + 1)
+                    .map(|spanned| &spanned.token)
+                    == Some(&Token::LeftParen)
+                {
+                    return self.function();
+                }
+
+                return self.simple_command();
+            }
+        };
+
+        let mut redirects = vec![];
+
+        while let Some(redirect) = self.redirect()? {
+            redirects.push(redirect);
+        }
+
+        Ok(Command::Compound(compound, redirects))
+    }
+
+    fn if_clause(&mut self) -> Result<Compound, SyntaxError> {
+        self.index += 1;
+
+        let mut branches = vec![];
+        let mut otherwise = None;
+
+        loop {
+            let condition = self.body(&["then"])?;
+            self.expect("then")?;
+
+            let body = self.body(&["elif", "else", "fi"])?;
+            branches.push((condition, body));
+
+            match self.keyword() {
+                Some("elif") => self.index += 1,
+                Some("else") => {
+                    self.index += 1;
+                    otherwise = Some(self.body(&["fi"])?);
+                    self.expect("fi")?;
+
+                    break;
+                }
+                _ => {
+                    self.expect("fi")?;
+                    break;
+                }
+            }
+        }
+
+        Ok(Compound::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    fn while_clause(&mut self) -> Result<Compound, SyntaxError> {
+        self.index += 1;
+
+        let condition = self.body(&["do"])?;
+        self.expect("do")?;
+
+        let body = self.body(&["done"])?;
+        self.expect("done")?;
+
+        Ok(Compound::While { condition, body })
+    }
+
+    fn for_clause(&mut self) -> Result<Compound, SyntaxError> {
+        self.index += 1;
+
+        let name = match self.keyword() {
+            Some(name) if is_name(name) => name.to_string(),
+            _ => return Err(self.unexpected()),
+        };
+
+        self.index += 1;
+        self.skip_newlines();
+
+        let mut words = None;
+
+        if self.keyword() == Some("in") {
+            self.index += 1;
+
+            let mut list = vec![];
+
+            while let Some(Token::Word(word)) = self.peek() {
+                list.push(word.clone());
+                self.index += 1;
+            }
+
+            // the words have to be terminated before `do`
+            if !matches!(self.peek(), Some(Token::Newline | Token::Semicolon)) {
+                return Err(self.unexpected());
+            }
+
+            words = Some(list);
+        }
+
+        self.skip_separators();
+        self.expect("do")?;
+
+        let body = self.body(&["done"])?;
+        self.expect("done")?;
+
+        Ok(Compound::For { name, words, body })
+    }
+
+    fn function(&mut self) -> Result<Command, SyntaxError> {
+        let name = match self.keyword() {
+            Some(name) if is_name(name) => name.to_string(),
+            _ => return Err(self.unexpected()),
+        };
+
+        self.index += 2;
+
+        if self.peek() != Some(&Token::RightParen) {
+            return Err(self.unexpected());
+        }
+
+        self.index += 1;
+        self.skip_newlines();
+
+        let position = self.position();
+
+        // only compound commands can be the body
+        match self.command()? {
+            Command::Compound(body, redirects) => {
+                Ok(Command::Function(name, Rc::new((body, redirects))))
+            }
+            _ => Err(SyntaxError::new(position, "expected a compound command")),
+        }
+    }
+
+    fn redirect(&mut self) -> Result<Option<Redirect>, SyntaxError> {
+        let Some(Token::Redirect { fd, kind }) = self.peek() else {
+            return Ok(None);
+        };
+
+        // `<` reads stdin, the others write stdout
+        let fd = fd.unwrap_or(match kind {
+            RedirectKind::Input => 0,
+            _ => 1,
+        });
+        let kind = *kind;
+
+        self.index += 1;
+
+        let Some(Token::Word(target)) = self.peek() else {
+            return Err(self.unexpected());
+        };
+
+        let target = target.clone();
+        self.index += 1;
+
+        Ok(Some(Redirect { fd, kind, target }))
+    }
+
+    fn simple_command(&mut self) -> Result<Command, SyntaxError> {
+        let mut assignments = vec![];
+        let mut words = vec![];
+        let mut redirects = vec![];
+
+        loop {
+            if let Some(redirect) = self.redirect()? {
+                redirects.push(redirect);
+                continue;
+            }
+
+            let Some(Token::Word(word)) = self.peek() else {
+                break;
+            };
+
+            match assignment(word) {
+                Some(assignment) if words.is_empty() => assignments.push(assignment),
+                _ => words.push(word.clone()),
+            }
+
+            self.index += 1;
+        }
+
+        if assignments.is_empty() && words.is_empty() && redirects.is_empty() {
+            return Err(self.unexpected());
+        }
+
+        Ok(Command::Simple(SimpleCommand {
+            assignments,
+            words,
+            redirects,
+        }))
+    }
+}
+
+// Splits `NAME=value` in the name and the value
+fn assignment(word: &Word) -> Option<(String, Word)> {
+    let Some(WordPart::Literal {
+        text,
+        quoted: false,
+    }) = word.0.first()
+    else {
+        return None;
+    };
+
+    let (name, value) = text.split_once('=')?;
+
+    if !is_name(name) {
+        return None;
+    }
+
+    let mut parts = word.0.clone();
+
+    if value.is_empty() {
+        parts.remove(0);
+    } else {
+        parts[0] = WordPart::Literal {
+            text: value.to_string(),
+            quoted: false,
+        };
+    }
+
+    Some((name.to_string(), Word(parts)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(input: &str) -> Result<List, SyntaxError> {
+        parse_with_aliases(input, &BTreeMap::new())
+    }
+
+    fn word(text: &str) -> Word {
+        Word(vec![WordPart::Literal {
+            text: text.to_string(),
+            quoted: false,
+        }])
+    }
+
+    fn simple(words: &[&str]) -> Command {
+        Command::Simple(SimpleCommand {
+            assignments: vec![],
+            words: words.iter().map(|text| word(text)).collect(),
+            redirects: vec![],
+        })
+    }
+
+    fn pipeline(words: &[&str]) -> Pipeline {
+        Pipeline(vec![simple(words)])
+    }
+
+    fn and_or(words: &[&str]) -> AndOr {
+        AndOr {
+            first: pipeline(words),
+            rest: vec![],
+        }
+    }
+
+    #[test]
+    fn list() {
+        assert_eq!(
+            parse("ls; echo a && echo b || echo c\n\npstree"),
+            Ok(vec![
+                AndOr {
+                    first: pipeline(&["ls"]),
+                    rest: vec![],
+                },
+                AndOr {
+                    first: pipeline(&["echo", "a"]),
+                    rest: vec![
+                        (Operator::And, pipeline(&["echo", "b"])),
+                        (Operator::Or, pipeline(&["echo", "c"])),
+                    ],
+                },
+                AndOr {
+                    first: pipeline(&["pstree"]),
+                    rest: vec![],
+                },
+            ])
+        );
+
+        assert_eq!(parse(" ; \n"), Ok(vec![]));
+    }
+
+    #[test]
+    fn assignments() {
+        assert_eq!(
+            parse("A=1 B= echo C=2"),
+            Ok(vec![AndOr {
+                first: Pipeline(vec![Command::Simple(SimpleCommand {
+                    assignments: vec![
+                        ("A".to_string(), word("1")),
+                        ("B".to_string(), Word(vec![]))
+                    ],
+                    words: vec![word("echo"), word("C=2")],
+                    redirects: vec![],
+                })]),
+                rest: vec![],
+            }])
+        );
+    }
+
+    #[test]
+    fn pipelines() {
+        assert_eq!(
+            parse("echo a | cat |\n cat && ls"),
+            Ok(vec![AndOr {
+                first: Pipeline(vec![
+                    simple(&["echo", "a"]),
+                    simple(&["cat"]),
+                    simple(&["cat"])
+                ]),
+                rest: vec![(Operator::And, pipeline(&["ls"]))],
+            }])
+        );
+    }
+
+    #[test]
+    fn redirections() {
+        assert_eq!(
+            parse("cat <in >out 2>&1"),
+            Ok(vec![AndOr {
+                first: Pipeline(vec![Command::Simple(SimpleCommand {
+                    assignments: vec![],
+                    words: vec![word("cat")],
+                    redirects: vec![
+                        Redirect {
+                            fd: 0,
+                            kind: RedirectKind::Input,
+                            target: word("in"),
+                        },
+                        Redirect {
+                            fd: 1,
+                            kind: RedirectKind::Output,
+                            target: word("out"),
+                        },
+                        Redirect {
+                            fd: 2,
+                            kind: RedirectKind::Duplicate,
+                            target: word("1"),
+                        },
+                    ],
+                })]),
+                rest: vec![],
+            }])
+        );
+    }
+
+    #[test]
+    fn compound_commands() {
+        assert_eq!(
+            parse("if a; then b\nelif c; then d; else e; fi > out"),
+            Ok(vec![AndOr {
+                first: Pipeline(vec![Command::Compound(
+                    Compound::If {
+                        branches: vec![
+                            (vec![and_or(&["a"])], vec![and_or(&["b"])]),
+                            (vec![and_or(&["c"])], vec![and_or(&["d"])]),
+                        ],
+                        otherwise: Some(vec![and_or(&["e"])]),
+                    },
+                    vec![Redirect {
+                        fd: 1,
+                        kind: RedirectKind::Output,
+                        target: word("out"),
+                    }],
+                )]),
+                rest: vec![],
+            }])
+        );
+
+        assert_eq!(
+            parse("for x in a b; do while c; do { d; }; done done"),
+            Ok(vec![AndOr {
+                first: Pipeline(vec![Command::Compound(
+                    Compound::For {
+                        name: "x".to_string(),
+                        words: Some(vec![word("a"), word("b")]),
+                        body: vec![AndOr {
+                            first: Pipeline(vec![Command::Compound(
+                                Compound::While {
+                                    condition: vec![and_or(&["c"])],
+                                    body: vec![AndOr {
+                                        first: Pipeline(vec![Command::Compound(
+                                            Compound::Group(vec![and_or(&["d"])]),
+                                            vec![],
+                                        )]),
+                                        rest: vec![],
+                                    }],
+                                },
+                                vec![],
+                            )]),
+                            rest: vec![],
+                        }],
+                    },
+                    vec![],
+                )]),
+                rest: vec![],
+            }])
+        );
+
+        // keywords are only recognized as the first word
+        assert_eq!(parse("echo if fi"), Ok(vec![and_or(&["echo", "if", "fi"])]));
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(
+            parse("greet() {\n echo hi\n}"),
+            Ok(vec![AndOr {
+                first: Pipeline(vec![Command::Function(
+                    "greet".to_string(),
+                    Rc::new((Compound::Group(vec![and_or(&["echo", "hi"])]), vec![])),
+                )]),
+                rest: vec![],
+            }])
+        );
+
+        assert_eq!(
+            parse("greet() echo hi"),
+            Err(SyntaxError::new(8, "expected a compound command"))
+        );
+    }
+
+    #[test]
+    fn aliases() {
+        let aliases = BTreeMap::from([
+            ("ll".to_string(), "ls -l".to_string()),
+            ("ls".to_string(), "ls -a".to_string()),
+            ("both".to_string(), "echo a; echo b |".to_string()),
+        ]);
+
+        assert_eq!(
+            parse_with_aliases("ll x; 'll'; echo ll", &aliases),
+            Ok(vec![
+                and_or(&["ls", "-a", "-l", "x"]),
+                AndOr {
+                    first: Pipeline(vec![Command::Simple(SimpleCommand {
+                        assignments: vec![],
+                        words: vec![Word(vec![WordPart::Literal {
+                            text: "ll".to_string(),
+                            quoted: true,
+                        }])],
+                        redirects: vec![],
+                    })]),
+                    rest: vec![],
+                },
+                and_or(&["echo", "ll"]),
+            ])
+        );
+
+        assert_eq!(
+            parse_with_aliases("both cat", &aliases),
+            Ok(vec![
+                and_or(&["echo", "a"]),
+                AndOr {
+                    first: Pipeline(vec![simple(&["echo", "b"]), simple(&["cat"])]),
+                    rest: vec![],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("&& ls"), Err(SyntaxError::new(0, "unexpected '&&'")));
+        assert_eq!(
+            parse("ls &&"),
+            Err(SyntaxError::incomplete(5, "unexpected end of input"))
+        );
+        assert_eq!(parse("ls || ;"), Err(SyntaxError::new(6, "unexpected ';'")));
+        assert_eq!(
+            parse("ls | | cat"),
+            Err(SyntaxError::new(5, "unexpected '|'"))
+        );
+        assert_eq!(parse("ls > ;"), Err(SyntaxError::new(5, "unexpected ';'")));
+        assert_eq!(
+            parse("if a; then fi"),
+            Err(SyntaxError::new(11, "unexpected 'fi'"))
+        );
+        assert_eq!(parse("done"), Err(SyntaxError::new(0, "unexpected 'done'")));
+
+        let incomplete = parse("while a; do").unwrap_err();
+        assert!(incomplete.is_incomplete());
+    }
+}
@@ -0,0 +1,113 @@
+// State of the shell shown by the prompt
+pub struct PromptInfo<'a> {
+    pub user: &'a str,
+    pub host: &'a str,
+    pub cwd: &'a str,
+    pub home: &'a str,
+    // whether the shell runs as root
+    pub root: bool,
+    pub status: u8,
+    // milliseconds since the unix epoch
+    pub time: u64,
+}
+
+// Replaces the escapes of `PS1`:
+// `\u` user, `\h` host, `\w` working directory, `\W` its last name, `\?` exit status,
+// `\t` time as HH:MM:SS, `\A` time as HH:MM, `\$` `#` for root and `$` for everyone else,
+// `\n` newline, `\\` backslash
+pub fn expand_prompt(ps1: &str, info: &PromptInfo) -> String {
+    let mut prompt = String::new();
+    let mut chars = ps1.chars();
+
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            prompt.push(char);
+            continue;
+        }
+
+        let seconds = info.time / 1000;
+        let (hours, minutes, seconds) = (seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+
+        match chars.next() {
+            Some('u') => prompt.push_str(info.user),
+            Some('h') => prompt.push_str(info.host),
+            Some('w') => prompt.push_str(&abbreviate_home(info.cwd, info.home)),
+            Some('W') => {
+                let cwd = abbreviate_home(info.cwd, info.home);
+                let name = cwd.rsplit('/').find(|name| !name.is_empty()).unwrap_or("/");
+
+                prompt.push_str(name)
+            }
+            Some('?') => prompt.push_str(&info.status.to_string()),
+            Some('t') => prompt.push_str(&format!("{hours:02}:{minutes:02}:{seconds:02}")),
+            Some('A') => prompt.push_str(&format!("{hours:02}:{minutes:02}")),
+            Some('$') => prompt.push(if info.root { '#' } else { '$' }),
+            Some('n') => prompt.push('\n'),
+            Some('\\') => prompt.push('\\'),
+            // unknown escapes are shown as they are
+            Some(other) => {
+                prompt.push('\\');
+                prompt.push(other);
+            }
+            None => prompt.push('\\'),
+        }
+    }
+
+    prompt
+}
+
+// The home directory is shown as `~`
+fn abbreviate_home(cwd: &str, home: &str) -> String {
+    if home.is_empty() || home == "/" {
+        return cwd.to_string();
+    }
+
+    match cwd.strip_prefix(home) {
+        Some("") => "~".to_string(),
+        Some(rest) if rest.starts_with('/') => format!("~{rest}"),
+        _ => cwd.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn info(cwd: &str) -> PromptInfo<'_> {
+        PromptInfo {
+            user: "fritz",
+            host: "tekenen",
+            cwd,
+            home: "/home/fritz",
+            root: false,
+            status: 1,
+            // 01:02:03
+            time: 3723 * 1000,
+        }
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            expand_prompt("\\u@\\h:\\w\\$ ", &info("/home/fritz")),
+            "fritz@tekenen:~$ "
+        );
+        assert_eq!(
+            expand_prompt("[\\?] \\t \\A\\n> ", &info("/")),
+            "[1] 01:02:03 01:02\n> "
+        );
+        assert_eq!(expand_prompt("\\x \\\\ \\", &info("/")), "\\x \\ \\");
+
+        let root = PromptInfo { root: true, ..info("/root") };
+        assert_eq!(expand_prompt("\\w\\$ ", &root), "/root# ");
+    }
+
+    #[test]
+    fn directories() {
+        assert_eq!(expand_prompt("\\w", &info("/home/fritz/docs")), "~/docs");
+        assert_eq!(expand_prompt("\\w", &info("/home/fritzy")), "/home/fritzy");
+        assert_eq!(expand_prompt("\\W", &info("/home/fritz/docs")), "docs");
+        assert_eq!(expand_prompt("\\W", &info("/home/fritz")), "~");
+        assert_eq!(expand_prompt("\\W", &info("/")), "/");
+    }
+}
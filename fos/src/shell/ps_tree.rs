@@ -1,10 +1,17 @@
 use std::rc::Rc;
 
 use crate::{
-    root::{Proc, Process, descriptor::{WritableDescriptor}},
-    Root, ROOT,
+    root::{
+        descriptor::{ReadableDescriptor, WritableDescriptor},
+        file::OpenFlags,
+        Pid, Proc, Process,
+    },
+    OpenError,
 };
 
+// Pid, parent and name of a process
+type Status = (Pid, Pid, String);
+
 pub struct PsTreeProgram {
     pub proc: Proc,
 }
@@ -26,20 +33,60 @@ impl Process for PsTreeProgram {
     }
 
     fn main(self: Rc<Self>, _: Vec<&str>) {
-        let root: Rc<Root> = Rc::clone(&*ROOT);
-        self.print(&(root as Rc<dyn Process>), 0);
+        match self.processes() {
+            Ok(processes) => {
+                // root is the process 0
+                if let Some((pid, _, name)) = processes.iter().find(|(pid, _, _)| *pid == 0) {
+                    self.print(&processes, *pid, name, 0);
+                }
 
-        self.proc.exit();
+                self.proc.exit(0);
+            }
+            Err(err) => {
+                self.proc.stderr.write(&format!("pstree: /proc: {err:?}\n"));
+                self.proc.exit(1);
+            }
+        }
     }
 }
 
 impl PsTreeProgram {
-    pub fn print(&self, node: &Rc<dyn Process>, indent: u32) {
-        let proc = node.get_proc();
+    // Read from the status of every process in `/proc`
+    fn processes(&self) -> Result<Vec<Status>, OpenError> {
+        let mut processes = vec![];
+
+        for (name, _) in self.proc.readdir("/proc")? {
+            let Ok(pid) = name.parse::<Pid>() else {
+                continue;
+            };
 
-        let name = node.get_process_name();
-        let pid = proc.pid;
+            // it may have been waited for since
+            let Ok(status) = self.read(&format!("/proc/{pid}/status")) else {
+                continue;
+            };
+
+            let field = |key: &str| {
+                status
+                    .lines()
+                    .find_map(|line| line.strip_prefix(key))
+                    .map(|value| value.trim().to_string())
+                    .unwrap_or_default()
+            };
+
+            processes.push((pid, field("PPid:").parse().unwrap_or(0), field("Name:")));
+        }
+
+        Ok(processes)
+    }
+
+    fn read(&self, path: &str) -> Result<String, OpenError> {
+        let file = self.proc.open(path.to_string(), OpenFlags::READ)?;
+
+        file.read_sync(u32::MAX)
+            .map_err(|_| OpenError::InvalidArgument)
+    }
 
+    pub fn print(&self, processes: &[Status], pid: Pid, name: &str, indent: u32) {
         let string = format!("[{pid}]{name}");
 
         for _ in 0..indent {
@@ -50,8 +97,12 @@ impl PsTreeProgram {
 
         self.proc.stdout.write_char('\n');
 
-        for child in proc.children.borrow().iter() {
-            self.print(child, indent + 2)
+        // root is its own parent
+        for (child, _, name) in processes
+            .iter()
+            .filter(|(child, parent, _)| *parent == pid && *child != pid)
+        {
+            self.print(processes, *child, name, indent + 2)
         }
     }
 }
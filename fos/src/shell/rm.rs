@@ -0,0 +1,44 @@
+use std::rc::Rc;
+
+use crate::root::{Proc, Process, descriptor::WritableDescriptor};
+
+pub struct RmProgram {
+    pub proc: Proc,
+}
+
+impl Process for RmProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        RmProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "rm"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        if args.is_empty() {
+            self.proc.stderr.write("rm: missing operand\n");
+            self.proc.exit(1);
+            return;
+        }
+
+        let mut code = 0;
+
+        // directories are removed with rmdir
+        for path in args {
+            if let Err(err) = self.proc.unlink(path) {
+                self.proc.stderr.write(&format!("rm: {path}: {err:?}\n"));
+                code = 1;
+            }
+        }
+
+        self.proc.exit(code);
+    }
+}
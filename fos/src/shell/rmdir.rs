@@ -0,0 +1,44 @@
+use std::rc::Rc;
+
+use crate::root::{Proc, Process, descriptor::WritableDescriptor};
+
+pub struct RmdirProgram {
+    pub proc: Proc,
+}
+
+impl Process for RmdirProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        RmdirProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "rmdir"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        if args.is_empty() {
+            self.proc.stderr.write("rmdir: missing operand\n");
+            self.proc.exit(1);
+            return;
+        }
+
+        let mut code = 0;
+
+        // only empty directories are removed
+        for path in args {
+            if let Err(err) = self.proc.rmdir(path) {
+                self.proc.stderr.write(&format!("rmdir: {path}: {err:?}\n"));
+                code = 1;
+            }
+        }
+
+        self.proc.exit(code);
+    }
+}
@@ -0,0 +1,196 @@
+use std::rc::Rc;
+
+use crate::{
+    root::{Proc, Process, descriptor::WritableDescriptor},
+    Group, Kind, Stat, User,
+};
+
+pub struct StatProgram {
+    pub proc: Proc,
+}
+
+impl Process for StatProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        StatProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "stat"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        if args.is_empty() {
+            self.proc.stderr.write("stat: missing operand\n");
+            self.proc.exit(1);
+            return;
+        }
+
+        let mut code = 0;
+        let (users, groups) = (self.proc.users(), self.proc.groups());
+
+        for path in args {
+            // links are shown themselves, not their target
+            match self.proc.lstat(path) {
+                Ok(stat) => {
+                    let kind = match stat.kind {
+                        Kind::File => "regular file",
+                        Kind::Directory => "directory",
+                        Kind::Symlink => "symbolic link",
+                        Kind::BlockDevice => "block special file",
+                        Kind::CharDevice => "character special file",
+                        Kind::Fifo => "fifo",
+                        Kind::Socket => "socket",
+                    };
+
+                    let path = match self.proc.readlink(path) {
+                        Ok(target) => format!("{path} -> {target}"),
+                        Err(_) => path.to_string(),
+                    };
+
+                    let (user, group) = format_owner(&stat, &users, &groups);
+
+                    self.proc.stdout.write(&format!(
+                        "  File: {path}\n  Size: {:<10} Type: {kind}\n Inode: {:<10} Links: {}\n  Mode: {:04o}/{}  Uid: {}/{user}  Gid: {}/{group}\nAccess: {}\nModify: {}\nCreate: {}\n",
+                        stat.size,
+                        stat.ino,
+                        stat.links,
+                        stat.mode,
+                        format_mode(&stat),
+                        stat.uid,
+                        stat.gid,
+                        format_time(stat.accessed),
+                        format_time(stat.modified),
+                        format_time(stat.created),
+                    ));
+                }
+                Err(err) => {
+                    self.proc.stderr.write(&format!("stat: {path}: {err:?}\n"));
+                    code = 1;
+                }
+            }
+        }
+
+        self.proc.exit(code);
+    }
+}
+
+// Type and permissions like `drwxr-xr-x`
+pub fn format_mode(stat: &Stat) -> String {
+    let mut mode = String::from(match stat.kind {
+        Kind::File => '-',
+        Kind::Directory => 'd',
+        Kind::Symlink => 'l',
+        Kind::BlockDevice => 'b',
+        Kind::CharDevice => 'c',
+        Kind::Fifo => 'p',
+        Kind::Socket => 's',
+    });
+
+    for shift in [6, 3, 0] {
+        let bits = stat.mode >> shift;
+
+        mode.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        mode.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        mode.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+
+    mode
+}
+
+// The names of the owner and the group, their numbers when they have none
+pub fn format_owner(stat: &Stat, users: &[User], groups: &[Group]) -> (String, String) {
+    let user = users
+        .iter()
+        .find(|user| user.uid == stat.uid)
+        .map_or(stat.uid.to_string(), |user| user.name.clone());
+
+    let group = groups
+        .iter()
+        .find(|group| group.gid == stat.gid)
+        .map_or(stat.gid.to_string(), |group| group.name.clone());
+
+    (user, group)
+}
+
+// Milliseconds since the unix epoch as `YYYY-MM-DD HH:MM:SS` in UTC
+pub fn format_time(time: u64) -> String {
+    let seconds = time / 1000;
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+    // civil from days, counting in eras of 400 years starting in March
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn times() {
+        assert_eq!(format_time(0), "1970-01-01 00:00:00");
+        assert_eq!(format_time(951_782_400_000), "2000-02-29 00:00:00");
+        assert_eq!(format_time(1_700_000_000_123), "2023-11-14 22:13:20");
+    }
+
+    #[test]
+    fn modes() {
+        let stat = |kind, mode| Stat {
+            ino: 1,
+            kind,
+            size: 0,
+            mode,
+            uid: 0,
+            gid: 0,
+            links: 1,
+            created: 0,
+            modified: 0,
+            accessed: 0,
+            device: 0,
+        };
+
+        assert_eq!(format_mode(&stat(Kind::Directory, 0o755)), "drwxr-xr-x");
+        assert_eq!(format_mode(&stat(Kind::File, 0o640)), "-rw-r-----");
+        assert_eq!(format_mode(&stat(Kind::BlockDevice, 0o660)), "brw-rw----");
+        assert_eq!(format_mode(&stat(Kind::CharDevice, 0o666)), "crw-rw-rw-");
+        assert_eq!(format_mode(&stat(Kind::Fifo, 0o644)), "prw-r--r--");
+
+        let users = crate::parse_users("root::0:0:root:/root:/bin/sh\n");
+        let groups = crate::parse_groups("video::44:fritz\n");
+        let device = Stat {
+            gid: 44,
+            ..stat(Kind::CharDevice, 0o660)
+        };
+        let owner = format_owner(&device, &users, &groups);
+        assert_eq!(owner, ("root".to_string(), "video".to_string()));
+
+        let orphan = Stat {
+            uid: 1001,
+            ..stat(Kind::File, 0o644)
+        };
+        let owner = format_owner(&orphan, &users, &groups);
+        assert_eq!(owner, ("1001".to_string(), "0".to_string()));
+    }
+}
@@ -0,0 +1,64 @@
+use std::rc::Rc;
+
+use crate::{
+    root::{descriptor::WritableDescriptor, Proc, Process},
+    OpenError, ROOT,
+};
+
+use super::login::start_shell;
+
+pub struct SuProgram {
+    pub proc: Proc,
+}
+
+impl Process for SuProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        SuProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "su"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        // root when no user is given
+        let name = match args[..] {
+            [] => "root".to_string(),
+            [name] => name.to_string(),
+            _ => {
+                self.proc.stderr.write("usage: su [user]\n");
+                self.proc.exit(1);
+                return;
+            }
+        };
+
+        let self_clone = Rc::clone(&self);
+
+        ROOT.executor.add_task(async move {
+            let proc = &self_clone.proc;
+
+            // unlike `login` the working directory stays
+            let code = match start_shell(proc, &name, false).await {
+                Ok(code) => code,
+                Err(OpenError::NoEntry) => {
+                    proc.stderr
+                        .write(&format!("su: user {name} does not exist\n"));
+                    1
+                }
+                Err(_) => {
+                    proc.stderr.write("su: Authentication failure\n");
+                    1
+                }
+            };
+
+            proc.exit(code);
+        });
+    }
+}
@@ -0,0 +1,212 @@
+use std::{io::SeekFrom, pin::Pin, rc::Rc};
+
+use crate::{
+    fc::future::{Context, Future, Poll},
+    root::{
+        descriptor::{IOError, ReadableDescriptor, WritableDescriptor},
+        file::{FileDescriptor, OpenFlags},
+        watch::Event,
+        Proc, Process,
+    },
+    ROOT,
+};
+
+// Bytes read from a file at a time
+const CHUNK_SIZE: u32 = 4096;
+
+// Lines printed without `-n`
+const DEFAULT_LINES: usize = 10;
+
+const USAGE: &str = "usage: tail [-f] [-n count] [file]\n";
+
+pub struct TailProgram {
+    pub proc: Proc,
+}
+
+impl Process for TailProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        TailProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "tail"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        let mut follow = false;
+        let mut count = DEFAULT_LINES;
+        let mut path = None;
+
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg {
+                "-f" => follow = true,
+                "-n" => match args.next().and_then(|count| count.parse().ok()) {
+                    Some(lines) => count = lines,
+                    None => {
+                        self.proc.stderr.write(USAGE);
+                        self.proc.exit(1);
+                        return;
+                    }
+                },
+                _ if path.is_none() => path = Some(arg.to_string()),
+                _ => {
+                    self.proc.stderr.write(USAGE);
+                    self.proc.exit(1);
+                    return;
+                }
+            }
+        }
+
+        let self_clone = Rc::clone(&self);
+
+        // a pipe has nothing to follow
+        let Some(path) = path else {
+            ROOT.executor.add_task(async move {
+                let proc = &self_clone.proc;
+                let mut text = String::new();
+
+                while let Ok(content) = proc.stdin.read(CHUNK_SIZE).await {
+                    text.push_str(&content);
+                }
+
+                proc.stdout.write(last_lines(&text, count));
+                proc.exit(0);
+            });
+
+            return;
+        };
+
+        let opened = self
+            .proc
+            .open(path.clone(), OpenFlags::READ)
+            .and_then(|file| {
+                // watching before reading so no change is missed
+                let watch = if follow {
+                    Some(self.proc.watch(&path)?)
+                } else {
+                    None
+                };
+
+                Ok((file, watch))
+            });
+
+        let (file, watch) = match opened {
+            Ok(opened) => opened,
+            Err(err) => {
+                self.proc.stderr.write(&format!("tail: {path}: {err:?}\n"));
+                self.proc.exit(1);
+                return;
+            }
+        };
+
+        ROOT.executor.add_task(async move {
+            let proc = &self_clone.proc;
+            let mut text = String::new();
+
+            while let Ok(content) = file.read(CHUNK_SIZE).await {
+                text.push_str(&content);
+            }
+
+            proc.stdout.write(last_lines(&text, count));
+
+            let Some(watch) = watch else {
+                proc.exit(0);
+                return;
+            };
+
+            // until Ctrl-C or the end of the input, otherwise nothing could stop it
+            loop {
+                let key = KeyOrChange {
+                    key: proc.stdin.read_char(),
+                    change: watch.next(),
+                }
+                .await;
+
+                match key {
+                    Some(Ok('\x03') | Err(_)) => break,
+                    Some(Ok(_)) => {}
+                    None => print_new(proc, &file, &path),
+                }
+            }
+
+            proc.exit(0);
+        });
+    }
+}
+
+// Whichever comes first of a key and a change of the file, which is `None`
+struct KeyOrChange {
+    key: Pin<Box<dyn Future<Output = Result<char, IOError>>>>,
+    change: Pin<Box<dyn Future<Output = Event>>>,
+}
+
+impl Future for KeyOrChange {
+    type Output = Option<Result<char, IOError>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.change.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+
+        self.key.as_mut().poll(cx).map(Some)
+    }
+}
+
+// Prints what was appended to the file since it was read, from the start if it was truncated
+fn print_new(proc: &Proc, file: &FileDescriptor, path: &str) {
+    let offset = file.tell();
+
+    let Ok(size) = file.seek(SeekFrom::End(0)) else {
+        return;
+    };
+
+    if size < offset {
+        proc.stderr
+            .write(&format!("tail: {path}: file truncated\n"));
+        let _ = file.seek(SeekFrom::Start(0));
+    } else {
+        let _ = file.seek(SeekFrom::Start(offset as u64));
+    }
+
+    while let Ok(content) = file.read_sync(CHUNK_SIZE) {
+        proc.stdout.write(&content);
+    }
+}
+
+// The end of `text` with its last `count` lines
+fn last_lines(text: &str, count: usize) -> &str {
+    if count == 0 {
+        return "";
+    }
+
+    // a newline at the end doesn't start another line
+    let body = text.strip_suffix('\n').unwrap_or(text);
+
+    match body.rmatch_indices('\n').nth(count - 1) {
+        Some((index, _)) => &text[index + 1..],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lines() {
+        assert_eq!(last_lines("a\nb\nc\n", 2), "b\nc\n");
+        assert_eq!(last_lines("a\nb\nc", 1), "c");
+        assert_eq!(last_lines("a\nb\n", 5), "a\nb\n");
+        assert_eq!(last_lines("a\nb\n", 0), "");
+        assert_eq!(last_lines("", 3), "");
+    }
+}
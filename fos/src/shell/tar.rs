@@ -0,0 +1,227 @@
+use std::rc::Rc;
+
+use crate::{
+    root::{
+        descriptor::{IOError, ReadableDescriptor, WritableDescriptor},
+        file::OpenFlags,
+        ExitCode, Proc, Process,
+    },
+    tar::{self, Content, Member},
+    Kind, OpenError,
+};
+
+pub struct TarProgram {
+    pub proc: Proc,
+}
+
+impl Process for TarProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        TarProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "tar"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        let code = match args[..] {
+            ["-cf", archive, ref paths @ ..] if !paths.is_empty() => self.create(archive, paths),
+            ["-xf", archive] => self.extract(archive, "."),
+            ["-xf", archive, "-C", directory] => self.extract(archive, directory),
+            ["-tf", archive] => self.list(archive),
+            _ => {
+                self.proc.stderr.write(
+                    "usage: tar -cf archive path... | -xf archive [-C directory] | -tf archive\n",
+                );
+                1
+            }
+        };
+
+        self.proc.exit(code);
+    }
+}
+
+impl TarProgram {
+    fn create(&self, archive: &str, paths: &[&str]) -> ExitCode {
+        let mut members = vec![];
+        let mut code = 0;
+
+        for path in paths {
+            if let Err(err) = self.collect(path, &mut members) {
+                self.proc.stderr.write(&format!("tar: {path}: {err:?}\n"));
+                code = 1;
+            }
+        }
+
+        let written = tar::write(&members).and_then(|bytes| {
+            let file = self.proc.open(
+                archive.to_string(),
+                OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+            )?;
+
+            // the headers are text and the files were strings already
+            file.write(&String::from_utf8_lossy(&bytes))
+                .map_err(|_| OpenError::NoSpace)
+        });
+
+        if let Err(err) = written {
+            self.proc
+                .stderr
+                .write(&format!("tar: {archive}: {err:?}\n"));
+            return 1;
+        }
+
+        code
+    }
+
+    // Adds `path` and everything inside of it, links are kept as links
+    fn collect(&self, path: &str, members: &mut Vec<Member>) -> Result<(), OpenError> {
+        let stat = self.proc.lstat(path)?;
+
+        let content = match stat.kind {
+            Kind::Directory => Content::Directory,
+            Kind::Symlink => Content::Symlink(self.proc.readlink(path)?),
+            Kind::BlockDevice => Content::BlockDevice(stat.device),
+            Kind::CharDevice => Content::CharDevice(stat.device),
+            Kind::Fifo => Content::Fifo,
+            // a socket only means something while it's bound
+            Kind::Socket => return Ok(()),
+            Kind::File => Content::File(self.read(path)?),
+        };
+
+        // like other tars, absolute paths are extracted relative to the directory
+        members.push(Member {
+            path: path.trim_start_matches('/').to_string(),
+            mode: stat.mode,
+            uid: stat.uid,
+            gid: stat.gid,
+            modified: stat.modified,
+            content,
+        });
+
+        if stat.kind == Kind::Directory {
+            for (name, _) in self.proc.readdir(path)? {
+                self.collect(&format!("{}/{name}", path.trim_end_matches('/')), members)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, OpenError> {
+        let file = self.proc.open(path.to_string(), OpenFlags::READ)?;
+
+        match file.read_sync(u32::MAX) {
+            Ok(content) => Ok(content.into_bytes()),
+            Err(IOError::ChannelClosed) => Ok(vec![]),
+            Err(_) => Err(OpenError::InvalidArgument),
+        }
+    }
+
+    fn members(&self, archive: &str) -> Option<Vec<Member>> {
+        match self.read(archive).and_then(|bytes| tar::read(&bytes)) {
+            Ok(members) => Some(members),
+            Err(err) => {
+                self.proc
+                    .stderr
+                    .write(&format!("tar: {archive}: {err:?}\n"));
+                None
+            }
+        }
+    }
+
+    fn extract(&self, archive: &str, directory: &str) -> ExitCode {
+        let Some(members) = self.members(archive) else {
+            return 1;
+        };
+
+        let root = self.proc.credentials().is_root();
+        let mut code = 0;
+
+        for member in members {
+            let path = format!("{}/{}", directory.trim_end_matches('/'), member.path);
+
+            let result = match &member.content {
+                Content::File(data) => self
+                    .proc
+                    .open(
+                        path.clone(),
+                        OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+                    )
+                    .and_then(|file| {
+                        file.write(&String::from_utf8_lossy(data))
+                            .map_err(|_| OpenError::NoSpace)
+                    }),
+                Content::Directory => match self.proc.mkdir(&path) {
+                    Err(OpenError::AlreadyExists) => Ok(()),
+                    result => result,
+                },
+                Content::Symlink(target) => self.proc.symlink(target, &path),
+                Content::Fifo => self.proc.mkfifo(&path),
+                Content::Link(existing) => self.proc.link(
+                    &format!("{}/{existing}", directory.trim_end_matches('/')),
+                    &path,
+                ),
+                // only the kernel makes devices
+                Content::BlockDevice(_) | Content::CharDevice(_) => Err(OpenError::NotSupported),
+            };
+
+            let result = result.and_then(|()| self.restore(&path, &member, root));
+
+            if let Err(err) = result {
+                self.proc.stderr.write(&format!("tar: {path}: {err:?}\n"));
+                code = 1;
+            }
+        }
+
+        code
+    }
+
+    // The mode, and the owners when extracting as root like other tars, if the filesystem
+    // keeps them
+    fn restore(&self, path: &str, member: &Member, root: bool) -> Result<(), OpenError> {
+        if matches!(member.content, Content::Symlink(_) | Content::Link(_)) {
+            return Ok(());
+        }
+
+        let mut result = self.proc.chmod(path, member.mode);
+
+        if root {
+            result =
+                result.and_then(|()| self.proc.chown(path, Some(member.uid), Some(member.gid)));
+        }
+
+        match result {
+            Err(OpenError::NotSupported) => Ok(()),
+            result => result,
+        }
+    }
+
+    fn list(&self, archive: &str) -> ExitCode {
+        let Some(members) = self.members(archive) else {
+            return 1;
+        };
+
+        for member in members {
+            let suffix = match member.content {
+                Content::Directory => "/".to_string(),
+                Content::Symlink(target) => format!(" -> {target}"),
+                Content::Link(existing) => format!(" link to {existing}"),
+                _ => String::new(),
+            };
+
+            self.proc
+                .stdout
+                .write(&format!("{}{suffix}\n", member.path));
+        }
+
+        0
+    }
+}
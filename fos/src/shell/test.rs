@@ -0,0 +1,84 @@
+use crate::{Kind, Proc};
+
+// Evaluates the expression of `test` or `[`, an error if it's malformed
+pub fn test(proc: &Proc, args: &[&str]) -> Result<bool, String> {
+    match args {
+        [] => Ok(false),
+        ["!", rest @ ..] => test(proc, rest).map(|result| !result),
+        [string] => Ok(!string.is_empty()),
+        [operator, operand] => unary(proc, operator, operand),
+        [left, operator, right] => binary(operator, left, right),
+        _ => Err("too many arguments".to_string()),
+    }
+}
+
+fn unary(proc: &Proc, operator: &str, operand: &str) -> Result<bool, String> {
+    let kind = || proc.stat(operand).ok().map(|stat| stat.kind);
+
+    match operator {
+        "-n" => Ok(!operand.is_empty()),
+        "-z" => Ok(operand.is_empty()),
+        "-e" => Ok(kind().is_some()),
+        "-f" => Ok(kind() == Some(Kind::File)),
+        "-d" => Ok(kind() == Some(Kind::Directory)),
+        "-b" => Ok(kind() == Some(Kind::BlockDevice)),
+        "-c" => Ok(kind() == Some(Kind::CharDevice)),
+        "-p" => Ok(kind() == Some(Kind::Fifo)),
+        "-S" => Ok(kind() == Some(Kind::Socket)),
+        "-L" | "-h" => Ok(proc.lstat(operand).is_ok_and(|stat| stat.kind == Kind::Symlink)),
+        operator => Err(format!("{operator}: unary operator expected")),
+    }
+}
+
+fn binary(operator: &str, left: &str, right: &str) -> Result<bool, String> {
+    match operator {
+        "=" => return Ok(left == right),
+        "!=" => return Ok(left != right),
+        _ => {}
+    }
+
+    let number = |string: &str| {
+        string
+            .parse::<i64>()
+            .map_err(|_| format!("{string}: integer expression expected"))
+    };
+
+    let (left, right) = (number(left)?, number(right)?);
+
+    match operator {
+        "-eq" => Ok(left == right),
+        "-ne" => Ok(left != right),
+        "-lt" => Ok(left < right),
+        "-le" => Ok(left <= right),
+        "-gt" => Ok(left > right),
+        "-ge" => Ok(left >= right),
+        operator => Err(format!("{operator}: binary operator expected")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings() {
+        let proc = Proc::new(0);
+
+        assert_eq!(test(&proc, &[]), Ok(false));
+        assert_eq!(test(&proc, &["a"]), Ok(true));
+        assert_eq!(test(&proc, &["-z", ""]), Ok(true));
+        assert_eq!(test(&proc, &["!", "-n", "a"]), Ok(false));
+        assert_eq!(test(&proc, &["a", "=", "a"]), Ok(true));
+        assert_eq!(test(&proc, &["a", "!=", "a"]), Ok(false));
+    }
+
+    #[test]
+    fn numbers() {
+        let proc = Proc::new(0);
+
+        assert_eq!(test(&proc, &["2", "-lt", "10"]), Ok(true));
+        assert_eq!(test(&proc, &["2", "-ge", "10"]), Ok(false));
+        assert!(test(&proc, &["a", "-eq", "1"]).is_err());
+        assert!(test(&proc, &["1", "-x", "1"]).is_err());
+    }
+}
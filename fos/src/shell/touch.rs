@@ -0,0 +1,44 @@
+use std::rc::Rc;
+
+use crate::root::{Proc, Process, descriptor::WritableDescriptor, file::OpenFlags};
+
+pub struct TouchProgram {
+    pub proc: Proc,
+}
+
+impl Process for TouchProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        TouchProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "touch"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        if args.is_empty() {
+            self.proc.stderr.write("touch: missing operand\n");
+            self.proc.exit(1);
+            return;
+        }
+
+        let mut code = 0;
+
+        // opening creates the file without changing it
+        for path in args {
+            if let Err(err) = self.proc.open(path.to_string(), OpenFlags::WRITE | OpenFlags::CREATE) {
+                self.proc.stderr.write(&format!("touch: {path}: {err:?}\n"));
+                code = 1;
+            }
+        }
+
+        self.proc.exit(code);
+    }
+}
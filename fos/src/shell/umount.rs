@@ -0,0 +1,43 @@
+use std::rc::Rc;
+
+use crate::root::{descriptor::WritableDescriptor, Proc, Process};
+
+pub struct UmountProgram {
+    pub proc: Proc,
+}
+
+impl Process for UmountProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        UmountProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "umount"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, args: Vec<&str>) {
+        if args.is_empty() {
+            self.proc.stderr.write("umount: missing operand\n");
+            self.proc.exit(1);
+            return;
+        }
+
+        let mut code = 0;
+
+        for dir in args {
+            if let Err(err) = self.proc.umount(dir) {
+                self.proc.stderr.write(&format!("umount: {dir}: {err:?}\n"));
+                code = 1;
+            }
+        }
+
+        self.proc.exit(code);
+    }
+}
@@ -0,0 +1,39 @@
+use std::rc::Rc;
+
+use crate::root::{descriptor::WritableDescriptor, Proc, Process};
+
+pub struct WhoamiProgram {
+    pub proc: Proc,
+}
+
+impl Process for WhoamiProgram {
+    fn new(proc: Proc) -> Self
+    where
+        Self: Sized,
+    {
+        WhoamiProgram { proc }
+    }
+
+    fn get_process_name(&self) -> &str {
+        "whoami"
+    }
+
+    fn get_proc(&self) -> &Proc {
+        &self.proc
+    }
+
+    fn main(self: Rc<Self>, _: Vec<&str>) {
+        let uid = self.proc.credentials().uid;
+
+        // the user the process acts as, not the one of `USER`
+        let name = self
+            .proc
+            .users()
+            .into_iter()
+            .find(|user| user.uid == uid)
+            .map_or(uid.to_string(), |user| user.name);
+
+        self.proc.stdout.write(&format!("{name}\n"));
+        self.proc.exit(0);
+    }
+}
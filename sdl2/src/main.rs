@@ -4,7 +4,7 @@ use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::video::Window;
 use sdl2::EventPump;
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use fos::{tekenen::Pixels, Event, Keycode, Keymod, PlatformTrait};
 
@@ -118,10 +118,23 @@ impl PlatformTrait for SDLPlatform {
                         }
                     }
 
+                    let keycode = match keycode {
+                        keyboard::Keycode::Backspace => Keycode::Backspace,
+                        keyboard::Keycode::Delete => Keycode::Delete,
+                        keyboard::Keycode::Tab => Keycode::Tab,
+                        keyboard::Keycode::Left => Keycode::Left,
+                        keyboard::Keycode::Right => Keycode::Right,
+                        keyboard::Keycode::Up => Keycode::Up,
+                        keyboard::Keycode::Down => Keycode::Down,
+                        keyboard::Keycode::Home => Keycode::Home,
+                        keyboard::Keycode::End => Keycode::End,
+                        _ => Keycode::Temp,
+                    };
+
                     return Some(Event::KeyDown {
                         repeat,
                         char,
-                        keycode: Keycode::Temp,
+                        keycode,
                         keymod: Keymod {
                             shift: shift_mod,
                             ctrl: ctrl_mod,
@@ -138,6 +151,12 @@ impl PlatformTrait for SDLPlatform {
         None
     }
 
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64)
+    }
+
     fn set_interval(mut callback: Box<dyn FnMut() -> bool>, fps: u32) {
         // let now = std::time::SystemTime::now();
 
@@ -152,6 +171,13 @@ impl PlatformTrait for SDLPlatform {
             std::thread::sleep(Duration::new(0, 1_000_000_000u32 / fps));
         }
     }
+
+    // usage: sdl2 [ARCHIVE], a tar archive replaces the initial files of fos
+    fn root_archive() -> Option<Vec<u8>> {
+        let path = std::env::args().nth(1)?;
+
+        Some(std::fs::read(&path).unwrap_or_else(|err| panic!("Cannot read {path}: {err}")))
+    }
 }
 
 fn main() {
@@ -1,11 +1,14 @@
 use console::{style, Key, Term};
 
-use std::cell::RefCell;
+use std::{
+    cell::RefCell,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use fos::{tekenen::Pixels, Event, Keycode, Keymod, PlatformTrait};
 
 thread_local! {
-    static NEXT: RefCell<Option<char>> = RefCell::new(None);
+    static NEXT: RefCell<Option<(Option<char>, Keycode)>> = RefCell::new(None);
     static TERM: Term = Term::stdout()
 }
 
@@ -55,11 +58,11 @@ impl PlatformTrait for ShellPlatform {
             next.take()
         });
 
-        if let Some(key) = char {
+        if let Some((key, keycode)) = char {
             Some(Event::KeyDown {
                 repeat: false,
-                char: Some(key),
-                keycode: Keycode::Temp,
+                char: key,
+                keycode,
                 keymod: Keymod {
                     shift: false,
                     ctrl: false,
@@ -72,28 +75,44 @@ impl PlatformTrait for ShellPlatform {
         }
     }
 
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64)
+    }
+
     fn set_interval(mut callback: Box<dyn FnMut() -> bool>, _fps: u32) {
         // let now = std::time::SystemTime::now();
 
         'running: loop {
             let char = TERM.with(|term| term.read_key());
 
-            match char {
-                Ok(Key::Char(key)) => NEXT.with(|next| {
-                    let mut next = next.borrow_mut();
-
-                    let _ = next.insert(key);
-                }),
-                Ok(Key::Enter) => NEXT.with(|next| {
-                    let mut next = next.borrow_mut();
-
-                    let _ = next.insert('\n');
-                }),
+            let key = match char {
+                Ok(Key::Char(key)) => Some((Some(key), Keycode::Temp)),
+                Ok(Key::Enter) => Some((Some('\n'), Keycode::Temp)),
+                Ok(Key::Backspace) => Some((None, Keycode::Backspace)),
+                Ok(Key::Del) => Some((None, Keycode::Delete)),
+                Ok(Key::Tab) => Some((None, Keycode::Tab)),
+                Ok(Key::ArrowLeft) => Some((None, Keycode::Left)),
+                Ok(Key::ArrowRight) => Some((None, Keycode::Right)),
+                Ok(Key::ArrowUp) => Some((None, Keycode::Up)),
+                Ok(Key::ArrowDown) => Some((None, Keycode::Down)),
+                Ok(Key::Home) => Some((None, Keycode::Home)),
+                Ok(Key::End) => Some((None, Keycode::End)),
                 _ => {
                     println!("{:?}", char);
+                    None
                 }
             };
 
+            if let Some(key) = key {
+                NEXT.with(|next| {
+                    let mut next = next.borrow_mut();
+
+                    let _ = next.insert(key);
+                })
+            }
+
             // TODO: pass time since startup to callback
             let should_stop = callback();
 
@@ -104,6 +123,13 @@ impl PlatformTrait for ShellPlatform {
             // std::thread::sleep(Duration::new(0, 1_000_000_000u32 / fps));
         }
     }
+
+    // usage: shell [ARCHIVE], a tar archive replaces the initial files of fos
+    fn root_archive() -> Option<Vec<u8>> {
+        let path = std::env::args().nth(1)?;
+
+        Some(std::fs::read(&path).unwrap_or_else(|err| panic!("Cannot read {path}: {err}")))
+    }
 }
 
 fn main() {
@@ -45,6 +45,10 @@ impl PlatformTrait for WASMTerminal {
         })
     }
 
+    fn now(&self) -> u64 {
+        js_date_now() as u64
+    }
+
     fn set_interval(callback: Box<dyn FnMut() -> bool>, fps: u32)
     where
         Self: Sized,
@@ -102,4 +106,7 @@ extern "C" {
 
     #[wasm_bindgen]
     fn js_display_pixels(pixels: Box<[u8]>);
+
+    #[wasm_bindgen(js_namespace = Date, js_name = now)]
+    fn js_date_now() -> f64;
 }